@@ -0,0 +1,7 @@
+#ifndef __STDARG_H
+#define __STDARG_H
+
+// va_list, va_start, va_arg, va_end and va_copy are built into rcc.
+typedef va_list __gnuc_va_list;
+
+#endif
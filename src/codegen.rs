@@ -1,9 +1,54 @@
 use crate::{
-    intermediate_process::{blockstr_to_identid, FunctionLocalVariable, FN_ARG_BLOC_STR},
-    parse::{Function, Node, NodeKind},
+    errors::error_at,
+    intermediate_process::{
+        asm_constraint_letter, blockstr_to_identid, usual_arith_conv,
+        FunctionLocalVariable, StaticData, Variable, FN_ARG_BLOC_STR,
+    },
+    parse::{Function, Node, NodeKind, Program, StorageClass},
+    tokenize::{find_member, literal_bytes, Member, Type},
 };
 use std::{fs::File, io::prelude::*};
 
+// 関数の引数を渡すregister. 7つ目以降の引数はstackで渡す.
+const ARG_REGS: [&str; 6] = ["rdi", "rsi", "rdx", "rcx", "r8", "r9"];
+// 浮動小数点数の引数は、%xmm0から%xmm7で渡す.
+const FP_ARG_REGS: usize = 8;
+
+// 引数をどこで渡すか. 汎用register, xmm register, stackのそれぞれの何番目か.
+#[derive(Clone, Copy)]
+enum ArgLoc {
+    Gp(usize),
+    Fp(usize),
+    Stack(usize),
+}
+
+// SysV ABIに従って、引数のtypeから引数を渡す場所を決める.
+// 整数とpointerは汎用register、浮動小数点数はxmm registerを前から使い、
+// 足りなくなったらstackで渡す.
+// long doubleは常にstackで、16byte alignされた2slotを使う.
+// Stack(n)はstack上の8byte slotの位置で、slotの総数も返す.
+fn classify_args(types: &[Type]) -> (Vec<ArgLoc>, usize) {
+    let (mut gp, mut fp, mut stack) = (0, 0, 0);
+    let mut locs = vec![];
+    for typ in types.iter() {
+        let loc = if *typ.unqual() == Type::LDOUBLE {
+            stack = (stack + 1) / 2 * 2 + 2;
+            ArgLoc::Stack(stack - 2)
+        } else if typ.is_flonum() && fp < FP_ARG_REGS {
+            fp += 1;
+            ArgLoc::Fp(fp - 1)
+        } else if !typ.is_flonum() && gp < ARG_REGS.len() {
+            gp += 1;
+            ArgLoc::Gp(gp - 1)
+        } else {
+            stack += 1;
+            ArgLoc::Stack(stack - 1)
+        };
+        locs.push(loc);
+    }
+    return (locs, stack);
+}
+
 // forやifでjmpする先のLabelを管理するstruct.
 struct CodeLabel {
     cur_index: usize,
@@ -17,25 +62,93 @@ impl CodeLabel {
     }
 }
 
+// stack machineとしてpushされている値の数を管理するstruct.
+// 関数呼び出しの際に、rspが16byte alignされているかを判断するのに使う.
+// MEMO: prologueの直後はrspが16byte alignされているので、depthが偶数ならalignされている.
+struct StackDepth {
+    depth: usize,
+}
+impl StackDepth {
+    fn new() -> Self {
+        return StackDepth { depth: 0 };
+    }
+    #[allow(unused_must_use)]
+    fn push(&mut self, f: &mut File, operand: &str) {
+        writeln!(f, "push {}", operand);
+        self.depth += 1;
+    }
+    #[allow(unused_must_use)]
+    fn pop(&mut self, f: &mut File, operand: &str) {
+        writeln!(f, "pop {}", operand);
+        self.depth -= 1;
+    }
+    // xmm registerはpush/popできないので、rspを動かしてmovsdする.
+    // MEMO: floatも8byteのslotに置く.(上位4byteは不定)
+    #[allow(unused_must_use)]
+    fn push_xmm(&mut self, f: &mut File, reg: &str) {
+        writeln!(f, "sub $8, %rsp");
+        writeln!(f, "movsd {}, (%rsp)", reg);
+        self.depth += 1;
+    }
+    #[allow(unused_must_use)]
+    fn pop_xmm(&mut self, f: &mut File, reg: &str) {
+        writeln!(f, "movsd (%rsp), {}", reg);
+        writeln!(f, "add $8, %rsp");
+        self.depth -= 1;
+    }
+    // long doubleは、x87のregister stackの先頭(%st0)と2slot(16byte)の間でやりとりする.
+    #[allow(unused_must_use)]
+    fn push_x87(&mut self, f: &mut File) {
+        writeln!(f, "sub $16, %rsp");
+        writeln!(f, "fstpt (%rsp)");
+        self.depth += 2;
+    }
+    #[allow(unused_must_use)]
+    fn pop_x87(&mut self, f: &mut File) {
+        writeln!(f, "fldt (%rsp)");
+        writeln!(f, "add $16, %rsp");
+        self.depth -= 2;
+    }
+    // stack topの値を、typeに合わせて%raxに取り出す.
+    // long doubleは%raxに入らないので捨てる.
+    #[allow(unused_must_use)]
+    fn pop_value(&mut self, f: &mut File, typ: &Type) {
+        if *typ.unqual() == Type::LDOUBLE {
+            writeln!(f, "add $16, %rsp");
+            self.depth -= 2;
+            return;
+        }
+        self.pop(f, "%rax");
+    }
+}
+
 #[allow(unused_must_use)]
-pub fn codegen(functions: Vec<Function>) {
+pub fn codegen(program: Program) {
     let mut output_file = create_file("./gen.s");
     writeln!(output_file, ".text");
-    for f in functions.iter() {
-        codegen_func(f.clone(), &mut output_file);
+    // MEMO: labelは関数をまたいで一意になる様に、全関数で共有する.
+    let mut cl = CodeLabel::new();
+    for f in program.functions.iter() {
+        codegen_func(f.clone(), &mut output_file, &mut cl);
+    }
+    for data in program.global_data.iter() {
+        codegen_static_data(data, &mut output_file);
     }
 }
 
 #[allow(unused_must_use)]
-pub fn codegen_func(function: Function, f: &mut File) {
+fn codegen_func(function: Function, f: &mut File, cl: &mut CodeLabel) {
     let root_node = &function.root_node;
 
     // let mut lv = LocalVariable::new();
     let mut lv = function.local_variable.clone();
-    let mut cl = CodeLabel::new();
+    let mut sd = StackDepth::new();
 
     // put start up.
-    writeln!(f, ".global {}", function.fn_name);
+    // staticな関数は他のfileから見えない様に、.globalを付けない.
+    if !function.is_static {
+        writeln!(f, ".global {}", function.fn_name);
+    }
     writeln!(f, "{}:", function.fn_name);
     writeln!(f, "pushq %rbp");
     writeln!(f, "movq %rsp, %rbp");
@@ -54,8 +167,9 @@ pub fn codegen_func(function: Function, f: &mut File) {
     );
 
     // 関数の引数をmemに配置する
+    let arg_types: Vec<Type> = function.fn_args.iter().map(|a| a.typ.clone()).collect();
+    let (locs, _) = classify_args(&arg_types);
     for (i, arg) in function.fn_args.iter().cloned().enumerate() {
-        let reg = vec!["rdi", "rsi", "rdx", "rcx", "r8", "r9"];
         let ident_id =
             blockstr_to_identid(arg.sym.clone(), String::from(FN_ARG_BLOC_STR));
 
@@ -64,36 +178,168 @@ pub fn codegen_func(function: Function, f: &mut File) {
             .get_val_offset_by_identid_recursively(ident_id)
             .unwrap_or_else(|| panic!("symbol: {} not found", arg.sym.clone()));
 
-        writeln!(f, "mov %{}, -{}(%rbp)", reg[i], val.offset);
+        match locs[i] {
+            | ArgLoc::Gp(n) => writeln!(f, "mov %{}, -{}(%rbp)", ARG_REGS[n], val.offset),
+            | ArgLoc::Fp(n) => writeln!(f, "movsd %xmm{}, -{}(%rbp)", n, val.offset),
+            // registerに乗らない引数は、caller側のstack(return addressとrbpの上)に置かれている.
+            | ArgLoc::Stack(n) => {
+                writeln!(f, "mov {}(%rbp), %rax", 16 + 8 * n);
+                writeln!(f, "mov %rax, -{}(%rbp)", val.offset);
+                if *arg.typ.unqual() == Type::LDOUBLE {
+                    writeln!(f, "mov {}(%rbp), %rax", 24 + 8 * n);
+                    writeln!(f, "mov %rax, -{}(%rbp)", val.offset - 8);
+                }
+                Ok(())
+            }
+        };
+    }
+
+    // 可変長引数の関数では、va_argで読める様に引数のregisterを全てregister save areaに退避する.
+    // MEMO: xmm registerは、gp registerの後ろに16byteずつ置く.
+    if let Some(offset) = lv.va_area_offset {
+        for (i, reg) in ARG_REGS.iter().enumerate() {
+            writeln!(f, "mov %{}, {}(%rbp)", reg, 8 * i as i64 - offset as i64);
+        }
+        for i in 0..8 {
+            writeln!(
+                f,
+                "movsd %xmm{}, {}(%rbp)",
+                i,
+                48 + 16 * i as i64 - offset as i64
+            );
+        }
     }
 
     // 各stmt毎にcodegen.
     // TODO: 将来的には(Nodeというより)Function毎にcodegenをしていくイメージ.
     //       また、関数ごとに(上で書いている様な)prologue,epilogueの処理を入れる.
     for node in root_node.fn_blocks.clone() {
-        gen(&node, f, &mut lv, &mut cl);
+        gen(&node, f, &mut lv, cl, &mut sd);
     }
-    writeln!(f, "pop %rax");
+    // MEMO: returnせずに関数の末尾に達した場合は、最後に評価した式文の値が%raxに残っている.
     writeln!(f, "mov %rbp, %rsp");
     writeln!(f, "pop %rbp");
     writeln!(f, "ret");
+
+    // 関数内のstatic変数を.dataに置く.
+    for data in lv.static_data.iter() {
+        codegen_static_data(data, f);
+    }
+}
+
+// static変数とfile scopeの変数を置く.
+// 初期値が全て0なら.bssに、そうでなければ.dataに初期値のbyte列を置く.
+// constな変数は、書き換えられない様に.rodataに置く.
+// MEMO: アドレスを置く変数は、PIEでは実行時にloaderが書き換えるのでconstでも.dataに置く.
+#[allow(unused_must_use)]
+fn codegen_static_data(data: &StaticData, f: &mut File) {
+    if data.is_global {
+        writeln!(f, ".global {}", data.label);
+    }
+    // symbolのtypeとsizeは、linkerやdebuggerがobjectの範囲を知るのに使う.
+    writeln!(f, ".type {}, @object", data.label);
+    writeln!(f, ".size {}, {}", data.label, data.typ.size());
+    let readonly = is_readonly(&data.typ) && data.relocs.is_empty();
+    if !readonly && data.init.iter().all(|b| *b == 0) && data.relocs.is_empty() {
+        writeln!(f, ".bss");
+        writeln!(f, ".align {}", data.align);
+        writeln!(f, "{}:", data.label);
+        writeln!(f, ".zero {}", data.init.len());
+    } else {
+        let section = if readonly {
+            ".section .rodata"
+        } else {
+            ".data"
+        };
+        writeln!(f, "{}", section);
+        writeln!(f, ".align {}", data.align);
+        writeln!(f, "{}:", data.label);
+        // アドレスを置く位置は`.quad label`にして、その間のbyte列を`.byte`で書く.
+        let write_bytes = |f: &mut File, bytes: &[u8]| {
+            if !bytes.is_empty() {
+                let bytes: Vec<String> = bytes.iter().map(|b| b.to_string()).collect();
+                writeln!(f, ".byte {}", bytes.join(","));
+            }
+        };
+        let mut pos = 0;
+        let mut relocs = data.relocs.clone();
+        relocs.sort();
+        for (offset, label) in relocs.iter() {
+            write_bytes(f, &data.init[pos..*offset]);
+            writeln!(f, ".quad {}", label);
+            pos = offset + 8;
+        }
+        write_bytes(f, &data.init[pos..]);
+    }
+    writeln!(f, ".text");
+}
+
+// 書き換えられないobject(constな変数か、constな要素の配列)のtypeかどうか.
+fn is_readonly(typ: &Type) -> bool {
+    match typ.unqual() {
+        | Type::ARRAY(elem, _) => typ.is_const() || is_readonly(elem),
+        | _ => typ.is_const(),
+    }
 }
 
 // 引数で渡されたNodeを展開して、その評価結果をstack topにpushする.
 #[allow(unused_must_use)]
-fn gen(node: &Node, f: &mut File, lv: &mut FunctionLocalVariable, cl: &mut CodeLabel) {
+fn gen(
+    node: &Node,
+    f: &mut File,
+    lv: &mut FunctionLocalVariable,
+    cl: &mut CodeLabel,
+    sd: &mut StackDepth,
+) {
     /*
         gen from unary node.
     */
     if node.kind == NodeKind::ND_NUM {
-        writeln!(f, "push ${}", node.val);
+        sd.push(f, &format!("${}", node.val));
+        return;
+    }
+    // 浮動小数点数は、そのbit表現を即値としてpushする.
+    // MEMO: long doubleのliteralは、doubleの精度で読んだ値をx87で拡張する.
+    if node.kind == NodeKind::ND_FNUM && node.typ == Type::LDOUBLE {
+        writeln!(f, "mov ${}, %rax", node.fval.to_bits() as i64);
+        writeln!(f, "push %rax");
+        writeln!(f, "fldl (%rsp)");
+        writeln!(f, "add $8, %rsp");
+        sd.push_x87(f);
+        return;
+    }
+    if node.kind == NodeKind::ND_FNUM {
+        let bits = match node.typ {
+            | Type::FLOAT => (node.fval as f32).to_bits() as u64,
+            | _ => node.fval.to_bits(),
+        };
+        writeln!(f, "mov ${}, %rax", bits as i64);
+        sd.push(f, "%rax");
+        return;
+    }
+    // typedefと_Static_assertはcodeを生成しない.
+    if node.kind == NodeKind::ND_TYPEDEF || node.kind == NodeKind::ND_STATIC_ASSERT {
+        return;
+    }
+    if node.kind == NodeKind::ND_ASM {
+        gen_asm(node, f, lv, cl, sd);
         return;
     }
     // MEMO: このnodeだけ例外的にepilogueもコードに入れている.
     if node.kind == NodeKind::ND_RETURN {
-        // evaluate expr.
-        gen(node.l.as_ref().unwrap().as_ref(), f, lv, cl);
-        writeln!(f, "pop %rax");
+        // evaluate expr. (`return;`の場合は何も評価しない)
+        // 浮動小数点数の返り値は%xmm0で、long doubleは%st0で返す.
+        if let Some(l) = node.l.as_ref() {
+            gen(l.as_ref(), f, lv, cl, sd);
+            if *l.typ.unqual() == Type::LDOUBLE {
+                sd.pop_x87(f);
+            } else {
+                sd.pop(f, "%rax");
+                if l.typ.is_flonum() {
+                    writeln!(f, "movq %rax, %xmm0");
+                }
+            }
+        }
         writeln!(f, "mov %rbp, %rsp");
         writeln!(f, "pop %rbp");
         // MEMO: 評価はreturn後のexprがされるが、
@@ -101,8 +347,16 @@ fn gen(node: &Node, f: &mut File, lv: &mut FunctionLocalVariable, cl: &mut CodeL
         writeln!(f, "ret");
         return;
     }
-    if node.kind == NodeKind::ND_EXPR || node.kind == NodeKind::ND_STMT {
-        gen(node.l.as_ref().unwrap().as_ref(), f, lv, cl);
+    if node.kind == NodeKind::ND_EXPR {
+        gen(node.l.as_ref().unwrap().as_ref(), f, lv, cl, sd);
+        return;
+    }
+    // 式文の値はstackに残さず、%raxに取り出しておく.
+    if node.kind == NodeKind::ND_STMT {
+        gen(node.l.as_ref().unwrap().as_ref(), f, lv, cl, sd);
+        if pushes_value(node.l.as_ref().unwrap()) {
+            sd.pop_value(f, &node.l.as_ref().unwrap().typ);
+        }
         return;
     }
     if node.kind == NodeKind::ND_IDENT {
@@ -111,9 +365,13 @@ fn gen(node: &Node, f: &mut File, lv: &mut FunctionLocalVariable, cl: &mut CodeL
             node.str.clone(),
             node.block_str.clone(),
         )) {
-            writeln!(f, "lea -{}(%rbp), %rax", val.offset);
-            writeln!(f, "mov (%rax), %rax");
-            writeln!(f, "push %rax");
+            gen_addr(f, &val);
+            // va_listや配列は、値ではなくアドレスをpushする.
+            if val.typ == Type::VA_LIST || val.typ.is_array() {
+                sd.push(f, "%rax");
+                return;
+            }
+            push_load(f, sd, &val.typ);
             return;
         }
 
@@ -121,9 +379,13 @@ fn gen(node: &Node, f: &mut File, lv: &mut FunctionLocalVariable, cl: &mut CodeL
         let ident_id =
             blockstr_to_identid(node.str.clone(), String::from(FN_ARG_BLOC_STR));
         if let Some(val) = lv.get_val_offset_by_identid_recursively(ident_id) {
-            writeln!(f, "lea -{}(%rbp), %rax", val.offset);
-            writeln!(f, "mov (%rax), %rax");
-            writeln!(f, "push %rax");
+            gen_addr(f, &val);
+            // va_listは配列なので、値ではなくアドレスをpushする.
+            if val.typ == Type::VA_LIST {
+                sd.push(f, "%rax");
+                return;
+            }
+            push_load(f, sd, &val.typ);
             return;
         } else {
             panic!("sym :{} not found.", node.str.clone())
@@ -135,38 +397,150 @@ fn gen(node: &Node, f: &mut File, lv: &mut FunctionLocalVariable, cl: &mut CodeL
             blockstr_to_identid(src_node.str.clone(), src_node.block_str.clone());
 
         if let Some(val) = lv.get_val_offset_by_identid_recursively(ident_id) {
-            writeln!(f, "leaq -{}(%rbp), %rax", val.offset);
-            writeln!(f, "push %rax");
+            gen_addr(f, &val);
+            sd.push(f, "%rax");
             return;
         } else {
             panic!("sym :{} not found.", src_node.str.clone())
         }
     }
+    // `&s.x`や`&a[i]`は、左辺値のアドレスをそのままpushする.
+    if node.kind == NodeKind::ND_ADDR {
+        gen_lvalue(node.l.as_ref().unwrap(), f, lv, cl, sd);
+        return;
+    }
     if node.kind == NodeKind::ND_PTR_DEREF {
         let src_node = node.ptr_deref_ident.clone().unwrap().as_ref().clone();
         let ident_id =
             blockstr_to_identid(src_node.str.clone(), src_node.block_str.clone());
 
         if let Some(val) = lv.get_val_offset_by_identid_recursively(ident_id) {
-            writeln!(f, "mov -{}(%rbp), %rax", val.offset);
+            gen_addr(f, &val);
             writeln!(f, "mov (%rax), %rax");
-            writeln!(f, "push %rax");
+            push_load(f, sd, val.typ.base().unwrap_or(&node.typ));
             return;
         } else {
             panic!("sym :{} not found.", node.str.clone())
         }
     }
     if node.kind == NodeKind::ND_FNCALL {
-        let reg = vec!["rdi", "rsi", "rdx", "rcx", "r8", "r9"];
-        // 引数をregisterにおく
-        for (i, arg) in node.fn_call_args.iter().cloned().enumerate() {
-            gen(arg.val.unwrap().as_ref(), f, lv, cl);
-            writeln!(f, "pop %rax");
-            writeln!(f, "mov %rax, %{}", reg[i]); // arg1
+        let arg_types: Vec<Type> = node
+            .fn_call_args
+            .iter()
+            .map(|arg| arg.val.as_ref().unwrap().typ.clone())
+            .collect();
+        let (locs, stack_slots) = classify_args(&arg_types);
+        let is_stack = |loc: &ArgLoc| matches!(loc, ArgLoc::Stack(_));
+        let fp_args = locs
+            .iter()
+            .filter(|loc| matches!(loc, ArgLoc::Fp(_)))
+            .count();
+
+        // call時にrspが16byte alignされる様に、必要なら引数を積む前にpaddingを入れる.
+        // stackで渡す引数は積んだまま渡すので、その分も含めて計算する.
+        let padding = (sd.depth + stack_slots) % 2;
+        if padding == 1 {
+            writeln!(f, "sub $8, %rsp");
+            sd.depth += 1;
         }
 
-        writeln!(f, "call {}", node.fn_name);
-        writeln!(f, "push %rax");
+        // 引数は右から順に評価してstackに積む.
+        // (引数の評価中に関数呼び出しがあっても、registerが壊されない様に)
+        // stackで渡す引数を先に積んで、registerで渡す引数をその上に積む.
+        // long doubleの前に空いたslotは、8byteずつ詰めて位置を合わせる.
+        let mut cur_slot = stack_slots;
+        for (arg, loc) in node.fn_call_args.iter().zip(locs.iter()).rev() {
+            if let ArgLoc::Stack(n) = loc {
+                let typ = &arg.val.as_ref().unwrap().typ;
+                let slots = if *typ.unqual() == Type::LDOUBLE { 2 } else { 1 };
+                while cur_slot > n + slots {
+                    writeln!(f, "sub $8, %rsp");
+                    sd.depth += 1;
+                    cur_slot -= 1;
+                }
+                gen(arg.val.as_ref().unwrap().as_ref(), f, lv, cl, sd);
+                cur_slot -= slots;
+            }
+        }
+        for (arg, loc) in node.fn_call_args.iter().zip(locs.iter()).rev() {
+            if !is_stack(loc) {
+                gen(arg.val.as_ref().unwrap().as_ref(), f, lv, cl, sd);
+            }
+        }
+        // 関数pointerを通した呼び出しでは、呼び出し先のアドレスを%r10に置く.
+        if let Some(callee) = node.l.as_ref() {
+            gen(callee, f, lv, cl, sd);
+            sd.pop(f, "%r10");
+        }
+        // registerで渡す引数を、先頭から順にregisterにおく.
+        for loc in locs.iter() {
+            match loc {
+                | ArgLoc::Gp(n) => sd.pop(f, &format!("%{}", ARG_REGS[*n])),
+                | ArgLoc::Fp(n) => sd.pop_xmm(f, &format!("%xmm{}", n)),
+                | ArgLoc::Stack(_) => (),
+            }
+        }
+
+        // 可変長引数の関数に対しては、%alに引数で使ったvector registerの数を渡す.
+        // (可変長引数でない関数には無視されるので、常に設定しておく)
+        writeln!(f, "mov ${}, %al", fp_args);
+        match node.l {
+            | Some(_) => writeln!(f, "call *%r10"),
+            | None => writeln!(f, "call {}", node.fn_name),
+        };
+
+        // stackで渡した引数とpaddingを捨てる.
+        if stack_slots + padding > 0 {
+            writeln!(f, "add ${}, %rsp", 8 * (stack_slots + padding));
+            sd.depth -= stack_slots + padding;
+        }
+        // 浮動小数点数の返り値は%xmm0に、long doubleは%st0に入っている.
+        // 整数の返り値の上位bitは不定なので、typeに合わせて拡張しておく.
+        if *node.typ.unqual() == Type::LDOUBLE {
+            sd.push_x87(f);
+            return;
+        }
+        match node.typ.unqual() {
+            | Type::FLOAT => writeln!(f, "movd %xmm0, %eax"),
+            | Type::DOUBLE => writeln!(f, "movq %xmm0, %rax"),
+            | _ => Ok(extend(f, &node.typ)),
+        };
+        sd.push(f, "%rax");
+        return;
+    }
+    // 関数のアドレスは、別のfileで定義された関数でも使える様にGOT経由で取得する.
+    if node.kind == NodeKind::ND_FN_ADDR {
+        writeln!(f, "mov {}@GOTPCREL(%rip), %rax", node.str);
+        sd.push(f, "%rax");
+        return;
+    }
+    // string literalは.rodataに置いて、そのアドレスをpushする.
+    if node.kind == NodeKind::ND_STR {
+        cl.cur_index += 1;
+        let label = format!(".L.str{}", cl.cur_label_index());
+        let mut bytes: Vec<String> = literal_bytes(&node.str)
+            .iter()
+            .map(|b| b.to_string())
+            .collect();
+        bytes.push(String::from("0"));
+        writeln!(f, ".section .rodata");
+        writeln!(f, "{}:", label);
+        writeln!(f, ".byte {}", bytes.join(","));
+        writeln!(f, ".text");
+        writeln!(f, "lea {}(%rip), %rax", label);
+        sd.push(f, "%rax");
+        return;
+    }
+    if node.kind == NodeKind::ND_CAST {
+        gen(node.l.as_ref().unwrap().as_ref(), f, lv, cl, sd);
+        let from = &node.l.as_ref().unwrap().typ;
+        if *from.unqual() == Type::LDOUBLE || *node.typ.unqual() == Type::LDOUBLE {
+            cast_x87(f, sd, from, &node.typ);
+            return;
+        }
+        sd.pop(f, "%rax");
+        cast(f, cl, &node.l.as_ref().unwrap().typ, &node.typ);
+        sd.push(f, "%rax");
         return;
     }
 
@@ -176,6 +550,55 @@ fn gen(node: &Node, f: &mut File, lv: &mut FunctionLocalVariable, cl: &mut CodeL
     // TODO: ここのpathは評価結果をstackにpushしないから、
     //       assignで終了するような入力ソースコードを受け取ると、
     //       変な終了コードになりそう.(まあ、さしあたりはそんなことは気にしない.)
+    // `*p`の値. 配列の場合は、値ではなく先頭のアドレスのままにする.
+    if node.kind == NodeKind::ND_DEREF {
+        gen(node.l.as_ref().unwrap().as_ref(), f, lv, cl, sd);
+        if !node.typ.is_array() && !node.typ.is_vla() {
+            sd.pop(f, "%rax");
+            push_load(f, sd, &object_type(node));
+        }
+        return;
+    }
+    // `s.x`の値. 配列の場合は、値ではなく先頭のアドレスのままにする.
+    if node.kind == NodeKind::ND_MEMBER {
+        gen_lvalue(node, f, lv, cl, sd);
+        // bit-fieldは、storage unitを読んでからそのbitだけを取り出し、
+        // 上位bitに寄せてから戻すことで、符号拡張かゼロ拡張をする.
+        if let Some(member) = bitfield(node) {
+            let width = member.bit_width.unwrap();
+            sd.pop(f, "%rax");
+            load(f, &object_type(node));
+            writeln!(f, "shl ${}, %rax", 64 - width - member.bit_offset);
+            let shift = if node.typ.is_unsigned() { "shr" } else { "sar" };
+            writeln!(f, "{} ${}, %rax", shift, 64 - width);
+            sd.push(f, "%rax");
+            return;
+        }
+        if !node.typ.is_array() {
+            sd.pop(f, "%rax");
+            push_load(f, sd, &object_type(node));
+        }
+        return;
+    }
+    // `a[i] = x`や`s.x = y`の様な、pointerの指す先やmemberへの代入.
+    if node.kind == NodeKind::ND_ASSIGN
+        && matches!(
+            node.l.as_ref().unwrap().kind,
+            NodeKind::ND_DEREF | NodeKind::ND_MEMBER
+        )
+    {
+        let lhs = node.l.as_ref().unwrap();
+        gen_lvalue(lhs, f, lv, cl, sd);
+        gen(node.r.as_ref().unwrap().as_ref(), f, lv, cl, sd);
+        match bitfield(lhs) {
+            | Some(m) => {
+                let width = m.bit_width.unwrap();
+                pop_store_bitfield(f, sd, &object_type(lhs), width, m.bit_offset);
+            }
+            | None => pop_store(f, sd, &object_type(lhs)),
+        }
+        return;
+    }
     if node.kind == NodeKind::ND_ASSIGN {
         // 右辺のoffsetをs取得
         let val = lv
@@ -187,24 +610,51 @@ fn gen(node: &Node, f: &mut File, lv: &mut FunctionLocalVariable, cl: &mut CodeL
         // 左辺のstrと紐付けた形でstack上にデータ領域を確保.
         // -> ND_EXPRのcodeを生成.
         // TODO: getoffsetで、identIDを入れたらoffsetが出て9両に
-        writeln!(f, "lea -{}(%rbp), %rax", val.offset);
-        writeln!(f, "push %rax");
+        gen_addr(f, &val);
+        sd.push(f, "%rax");
 
-        gen(node.r.as_ref().unwrap().as_ref(), f, lv, cl);
-        writeln!(f, "pop %rax");
-        writeln!(f, "pop %rdi");
-        writeln!(f, "mov %rax, (%rdi)");
+        gen(node.r.as_ref().unwrap().as_ref(), f, lv, cl, sd);
+        pop_store(f, sd, &val.typ);
+        return;
+    }
+    // compound literalは、名前のない変数を初期化してからその値をpushする.
+    if node.kind == NodeKind::ND_COMPOUND_LIT {
+        gen(node.l.as_ref().unwrap().as_ref(), f, lv, cl, sd);
+        gen(node.r.as_ref().unwrap().as_ref(), f, lv, cl, sd);
         return;
     }
     if node.kind == NodeKind::ND_BLOCK {
         let node_vec = node.block_stmts.clone();
         for node in node_vec.iter() {
-            gen(node, f, lv, cl);
+            gen(node, f, lv, cl, sd);
+        }
+        // blockで確保した可変長配列の領域を解放する.
+        // 最初に確保したVLAの先頭 + そのsizeが、確保する前のrspになる.
+        // MEMO: 宣言は、ND_STMT2(ND_STMT(ND_DECL))としてblockに入っている.
+        let first_vla = node_vec
+            .iter()
+            .map(|mut n| {
+                while n.kind == NodeKind::ND_STMT2 || n.kind == NodeKind::ND_STMT {
+                    n = n.l.as_ref().unwrap();
+                }
+                return n;
+            })
+            .find(|n| n.kind == NodeKind::ND_DECL && n.decl_type.is_vla());
+        if let Some(decl) = first_vla {
+            if let Type::VLA(_, size_var) = &decl.decl_type {
+                gen_load_var(f, lv, size_var, &decl.block_str);
+                writeln!(f, "add $15, %rax");
+                writeln!(f, "and $-16, %rax");
+                writeln!(f, "mov %rax, %rdi");
+                gen_load_var(f, lv, &decl.l.as_ref().unwrap().str, &decl.block_str);
+                writeln!(f, "add %rdi, %rax");
+                writeln!(f, "mov %rax, %rsp");
+            }
         }
         return;
     }
     if node.kind == NodeKind::ND_STMT2 {
-        gen(node.l.as_ref().unwrap().as_ref(), f, lv, cl);
+        gen(node.l.as_ref().unwrap().as_ref(), f, lv, cl, sd);
         return;
     }
     if node.kind == NodeKind::ND_FOR {
@@ -217,15 +667,18 @@ fn gen(node: &Node, f: &mut File, lv: &mut FunctionLocalVariable, cl: &mut CodeL
         // increment the label index at this timing.
         cl.cur_index += 1;
 
-        gen(node.for_node_first_assign.as_ref().unwrap(), f, lv, cl);
+        gen(node.for_node_first_assign.as_ref().unwrap(), f, lv, cl, sd);
         writeln!(f, ".{}:", for_start_label);
-        gen(node.for_node_second_condition.as_ref().unwrap(), f, lv, cl);
-        writeln!(f, "pop %rax");
+        gen(node.for_node_second_condition.as_ref().unwrap(), f, lv, cl, sd);
+        sd.pop(f, "%rax");
         writeln!(f, "mov $1, %rdi");
         writeln!(f, "cmp %rdi, %rax");
         writeln!(f, "jne .{}", for_end_label);
-        gen(node.for_node_stmts.as_ref().unwrap(), f, lv, cl);
-        gen(node.for_node_third_expr.as_ref().unwrap(), f, lv, cl);
+        gen(node.for_node_stmts.as_ref().unwrap(), f, lv, cl, sd);
+        gen(node.for_node_third_expr.as_ref().unwrap(), f, lv, cl, sd);
+        if pushes_value(node.for_node_third_expr.as_ref().unwrap()) {
+            sd.pop_value(f, &node.for_node_third_expr.as_ref().unwrap().typ);
+        }
         writeln!(f, "jmp .{}", for_start_label);
         writeln!(f, ".{}:", for_end_label);
         return;
@@ -233,44 +686,54 @@ fn gen(node: &Node, f: &mut File, lv: &mut FunctionLocalVariable, cl: &mut CodeL
     // NodeKind::ND_IFSTMT is the node that will be the entry
     // for all if statements. This block calls the ND_IF, ND_ELSIF,
     // and ND_ELSE statement codegen.
+    // Once one of the branches is taken, it jumps to the end of
+    // the if statement so that the other branches are not executed.
     if node.kind == NodeKind::ND_IFSTMT {
         let if_node = node.if_node.as_ref().unwrap();
         let elsif_node = node.elsif_node.as_ref();
         let else_node = node.else_node.as_ref();
 
+        cl.cur_index += 1;
+        let if_end_label = format!("L_IF_END{}", cl.cur_label_index());
+
         // codegen ND_IF
-        gen(if_node, f, lv, cl);
+        gen_if_branch(if_node, &if_end_label, f, lv, cl, sd);
 
         if !elsif_node.is_none() {
-            gen(elsif_node.unwrap(), f, lv, cl);
+            gen_if_branch(elsif_node.unwrap(), &if_end_label, f, lv, cl, sd);
         }
 
         if !else_node.is_none() {
-            gen(else_node.unwrap(), f, lv, cl);
+            gen(else_node.unwrap(), f, lv, cl, sd);
         }
+        writeln!(f, ".{}:", if_end_label);
 
         return;
     }
-    if node.kind == NodeKind::ND_IF || node.kind == NodeKind::ND_ELSIF {
-        cl.cur_index += 1;
-        let i = cl.cur_label_index();
-        gen(node.l.as_ref().unwrap(), f, lv, cl);
-        writeln!(f, "pop %rax");
-        writeln!(f, "mov $1, %rdi");
-        writeln!(f, "cmp %rdi, %rax");
+    if node.kind == NodeKind::ND_ELSE {
+        gen(node.l.as_ref().unwrap(), f, lv, cl, sd);
+        return;
+    }
+    if node.kind == NodeKind::ND_IFCOND {
+        gen(node.l.as_ref().unwrap(), f, lv, cl, sd);
+        return;
+    }
 
-        writeln!(f, "jne .L{}", i);
-        // stmt
-        gen(node.r.as_ref().unwrap(), f, lv, cl);
-        writeln!(f, ".L{}:", i);
+    if node.kind == NodeKind::ND_VA_START {
+        gen_va_start(node, f, lv, cl, sd);
         return;
     }
-    if node.kind == NodeKind::ND_ELSE {
-        gen(node.l.as_ref().unwrap(), f, lv, cl);
+    if node.kind == NodeKind::ND_VA_ARG {
+        gen_va_arg(node, f, lv, cl, sd);
         return;
     }
-    if node.kind == NodeKind::ND_IFCOND {
-        gen(node.l.as_ref().unwrap(), f, lv, cl);
+    if node.kind == NodeKind::ND_VA_COPY {
+        gen_va_copy(node, f, lv, cl, sd);
+        return;
+    }
+    // va_endは何もしない.(apの評価だけ行う)
+    if node.kind == NodeKind::ND_VA_END {
+        gen(node.l.as_ref().unwrap(), f, lv, cl, sd);
         return;
     }
 
@@ -288,22 +751,92 @@ fn gen(node: &Node, f: &mut File, lv: &mut FunctionLocalVariable, cl: &mut CodeL
             })
             .clone();
 
-        writeln!(f, "lea -{}(%rbp), %rax", val.offset);
-        writeln!(f, "push %rax");
+        // 可変長配列は、sizeを計算してからstack上に領域を確保し、その先頭のアドレスを変数に置く.
+        // MEMO: rspは16byte alignしたまま動かすので、depthの偶奇は変わらない.
+        if let Type::VLA(_, size_var) = &val.typ {
+            for size_decl in node.block_stmts.iter() {
+                gen(size_decl, f, lv, cl, sd);
+            }
+            gen_load_var(f, lv, size_var, &node.block_str);
+            writeln!(f, "add $15, %rax");
+            writeln!(f, "and $-16, %rax");
+            writeln!(f, "sub %rax, %rsp");
+            writeln!(f, "mov %rsp, -{}(%rbp)", val.offset);
+            return;
+        }
 
-        gen(node.r.as_ref().unwrap().as_ref(), f, lv, cl);
-        writeln!(f, "pop %rax");
-        writeln!(f, "pop %rdi");
-        writeln!(f, "mov %rax, (%rdi)");
+        // 初期化式のない宣言は何もしない.
+        // (static変数は.dataで初期化され、extern変数は領域を持たない)
+        if node.r.is_none() || val.storage != StorageClass::AUTO {
+            return;
+        }
+        // 初期化式が`{}`の場合は、全体を0で埋めてから要素ごとに代入する.
+        let r = node.r.as_ref().unwrap();
+        if r.kind == NodeKind::ND_INIT_LIST {
+            gen_addr(f, &val);
+            writeln!(f, "mov %rax, %rdi");
+            writeln!(f, "mov ${}, %rcx", val.typ.size());
+            writeln!(f, "mov $0, %al");
+            writeln!(f, "rep stosb");
+            for init in r.block_stmts.iter() {
+                gen_addr(f, &val);
+                writeln!(f, "add ${}, %rax", init.val);
+                sd.push(f, "%rax");
+                gen(init.l.as_ref().unwrap().as_ref(), f, lv, cl, sd);
+                match init.bit_field {
+                    | Some((width, bit_offset)) => {
+                        pop_store_bitfield(f, sd, &init.typ, width, bit_offset)
+                    }
+                    | None => pop_store(f, sd, &init.typ),
+                }
+            }
+            return;
+        }
+        gen_addr(f, &val);
+        sd.push(f, "%rax");
+
+        gen(node.r.as_ref().unwrap().as_ref(), f, lv, cl, sd);
+        pop_store(f, sd, &val.typ);
         return;
     }
 
     // other binary operation.
-    gen(node.l.as_ref().unwrap().as_ref(), f, lv, cl);
-    gen(node.r.as_ref().unwrap().as_ref(), f, lv, cl);
+    gen(node.l.as_ref().unwrap().as_ref(), f, lv, cl, sd);
+    gen(node.r.as_ref().unwrap().as_ref(), f, lv, cl, sd);
+
+    // 浮動小数点数の演算は、xmm registerで行う.
+    let typ =
+        usual_arith_conv(&node.l.as_ref().unwrap().typ, &node.r.as_ref().unwrap().typ);
+    if typ.is_flonum() {
+        gen_flonum_binary(node, &typ, f, sd);
+        return;
+    }
+
+    sd.pop(f, "%rdi"); // right side.
+    sd.pop(f, "%rax"); // left side.
 
-    writeln!(f, "pop %rdi"); // right side.
-    writeln!(f, "pop %rax"); // left side.
+    // pointerの加減算は、指す先のsize単位で行う.
+    // 可変長配列を指す場合は、intermediate_processでscale済み.
+    let scale = |typ: &Type| typ.base().filter(|t| !t.is_vla()).map(|t| t.size());
+    let (l_base, r_base) = match node.kind {
+        | NodeKind::ND_ADD | NodeKind::ND_SUB => (
+            scale(&node.l.as_ref().unwrap().typ),
+            scale(&node.r.as_ref().unwrap().typ),
+        ),
+        | _ => (None, None),
+    };
+    match (l_base, r_base) {
+        | (Some(size), None) => writeln!(f, "imul ${}, %rdi", size),
+        | (None, Some(size)) => writeln!(f, "imul ${}, %rax", size),
+        | _ => Ok(()),
+    };
+
+    // 比較や除算は、operandがunsignedかどうかで命令が変わる.
+    let unsigned = usual_arith_conv(
+        &node.l.as_ref().unwrap().typ,
+        &node.r.as_ref().unwrap().typ,
+    )
+    .is_unsigned();
 
     match node.kind {
         // 四則演算.
@@ -312,13 +845,24 @@ fn gen(node: &Node, f: &mut File, lv: &mut FunctionLocalVariable, cl: &mut CodeL
         }
         | NodeKind::ND_SUB => {
             writeln!(f, "sub %rdi, %rax");
+            // pointer同士の差は、要素数にする.
+            if let (Some(size), Some(_)) = (l_base, r_base) {
+                writeln!(f, "mov ${}, %rdi", size);
+                writeln!(f, "cqo");
+                writeln!(f, "idiv %rdi");
+            }
         }
         | NodeKind::ND_MUL => {
             writeln!(f, "imul %rdi, %rax");
         }
         | NodeKind::ND_DIV => {
-            writeln!(f, "cqo");
-            writeln!(f, "idiv %rdi");
+            if unsigned {
+                writeln!(f, "mov $0, %rdx");
+                writeln!(f, "div %rdi");
+            } else {
+                writeln!(f, "cqo");
+                writeln!(f, "idiv %rdi");
+            }
         }
         // 比較演算.
         | NodeKind::ND_EQ => {
@@ -333,28 +877,881 @@ fn gen(node: &Node, f: &mut File, lv: &mut FunctionLocalVariable, cl: &mut CodeL
         }
         | NodeKind::ND_LE => {
             writeln!(f, "cmp %rdi, %rax");
-            writeln!(f, "setle %al");
+            writeln!(f, "{} %al", if unsigned { "setbe" } else { "setle" });
             writeln!(f, "movzb %al, %rax");
         }
         | NodeKind::ND_LT => {
             writeln!(f, "cmp %rdi, %rax");
-            writeln!(f, "setl %al");
+            writeln!(f, "{} %al", if unsigned { "setb" } else { "setl" });
             writeln!(f, "movzb %al, %rax");
         }
         | NodeKind::ND_BE => {
             writeln!(f, "cmp %rax, %rdi");
-            writeln!(f, "setle %al");
+            writeln!(f, "{} %al", if unsigned { "setbe" } else { "setle" });
             writeln!(f, "movzb %al, %rax");
         }
         | NodeKind::ND_BT => {
             writeln!(f, "cmp %rax, %rdi");
-            writeln!(f, "setl %al");
+            writeln!(f, "{} %al", if unsigned { "setb" } else { "setl" });
             writeln!(f, "movzb %al, %rax");
         }
         | _ => {}
     }
 
-    writeln!(f, "push %rax");
+    // 四則演算の結果は、演算したtypeの範囲に収める.
+    match node.kind {
+        | NodeKind::ND_ADD | NodeKind::ND_SUB | NodeKind::ND_MUL | NodeKind::ND_DIV => {
+            extend(f, &node.typ);
+        }
+        | _ => {}
+    }
+
+    sd.push(f, "%rax");
+}
+
+// 浮動小数点数の二項演算のcodegen.
+// 両方のoperandは、intermediate_processでtypに変換されている.
+#[allow(unused_must_use)]
+fn gen_flonum_binary(node: &Node, typ: &Type, f: &mut File, sd: &mut StackDepth) {
+    if *typ == Type::LDOUBLE {
+        gen_x87_binary(node, f, sd);
+        return;
+    }
+    sd.pop_xmm(f, "%xmm1"); // right side.
+    sd.pop_xmm(f, "%xmm0"); // left side.
+    let sfx = if *typ == Type::FLOAT { "ss" } else { "sd" };
+    let op = match node.kind {
+        | NodeKind::ND_ADD => "add",
+        | NodeKind::ND_SUB => "sub",
+        | NodeKind::ND_MUL => "mul",
+        | NodeKind::ND_DIV => "div",
+        | _ => "",
+    };
+    if !op.is_empty() {
+        writeln!(f, "{}{} %xmm1, %xmm0", op, sfx);
+        sd.push_xmm(f, "%xmm0");
+        return;
+    }
+
+    // 比較演算. NaNとの比較(PF=1)は`!=`以外は偽になる.
+    // `<`, `<=`はoperandを入れ替えて`>`, `>=`として比較する.
+    let (lhs, rhs) = match node.kind {
+        | NodeKind::ND_LT | NodeKind::ND_LE => ("%xmm0", "%xmm1"),
+        | _ => ("%xmm1", "%xmm0"),
+    };
+    writeln!(f, "ucomi{} {}, {}", sfx, lhs, rhs);
+    match node.kind {
+        | NodeKind::ND_EQ => {
+            writeln!(f, "sete %al");
+            writeln!(f, "setnp %dl");
+            writeln!(f, "and %dl, %al");
+        }
+        | NodeKind::ND_NEQ => {
+            writeln!(f, "setne %al");
+            writeln!(f, "setp %dl");
+            writeln!(f, "or %dl, %al");
+        }
+        | NodeKind::ND_BT | NodeKind::ND_LT => {
+            writeln!(f, "seta %al");
+        }
+        | _ => {
+            writeln!(f, "setae %al");
+        }
+    }
+    writeln!(f, "movzb %al, %rax");
+    sd.push(f, "%rax");
+}
+
+// long doubleの二項演算のcodegen.
+// stackに積まれた2つの16byteの値をx87のregister stackに読み込んで演算する.
+#[allow(unused_must_use)]
+fn gen_x87_binary(node: &Node, f: &mut File, sd: &mut StackDepth) {
+    let op = match node.kind {
+        | NodeKind::ND_ADD => "faddp",
+        | NodeKind::ND_SUB => "fsubp",
+        | NodeKind::ND_MUL => "fmulp",
+        | NodeKind::ND_DIV => "fdivp",
+        | _ => "",
+    };
+    // 四則演算では左辺を%st0に置く.
+    // 比較では`>`, `>=`として比較する方を%st0に置く. (`<`, `<=`は右辺)
+    let (first, second) = match node.kind {
+        | NodeKind::ND_LT | NodeKind::ND_LE => ("16(%rsp)", "(%rsp)"),
+        | _ => ("(%rsp)", "16(%rsp)"),
+    };
+    writeln!(f, "fldt {}", first);
+    writeln!(f, "fldt {}", second);
+    writeln!(f, "add $32, %rsp");
+    sd.depth -= 4;
+    if !op.is_empty() {
+        writeln!(f, "{}", op);
+        sd.push_x87(f);
+        return;
+    }
+
+    writeln!(f, "fcomip");
+    writeln!(f, "fstp %st(0)");
+    match node.kind {
+        | NodeKind::ND_EQ => {
+            writeln!(f, "sete %al");
+            writeln!(f, "setnp %dl");
+            writeln!(f, "and %dl, %al");
+        }
+        | NodeKind::ND_NEQ => {
+            writeln!(f, "setne %al");
+            writeln!(f, "setp %dl");
+            writeln!(f, "or %dl, %al");
+        }
+        | NodeKind::ND_BT | NodeKind::ND_LT => {
+            writeln!(f, "seta %al");
+        }
+        | _ => {
+            writeln!(f, "setae %al");
+        }
+    }
+    writeln!(f, "movzb %al, %rax");
+    sd.push(f, "%rax");
+}
+
+// ND_IF or ND_ELSIFのcodegen.
+// 条件が成り立った場合はstmtsを実行し、if文の末尾(end_label)へjmpする.
+#[allow(unused_must_use)]
+fn gen_if_branch(
+    node: &Node,
+    end_label: &str,
+    f: &mut File,
+    lv: &mut FunctionLocalVariable,
+    cl: &mut CodeLabel,
+    sd: &mut StackDepth,
+) {
+    cl.cur_index += 1;
+    let i = cl.cur_label_index();
+    gen(node.l.as_ref().unwrap(), f, lv, cl, sd);
+    sd.pop(f, "%rax");
+    writeln!(f, "mov $1, %rdi");
+    writeln!(f, "cmp %rdi, %rax");
+
+    writeln!(f, "jne .L{}", i);
+    // stmt
+    gen(node.r.as_ref().unwrap(), f, lv, cl, sd);
+    writeln!(f, "jmp .{}", end_label);
+    writeln!(f, ".L{}:", i);
+}
+
+// 変数のアドレスを%raxに置く.
+// extern変数は別のfile(shared libraryの場合もある)にあるので、GOT経由で取得する.
+#[allow(unused_must_use)]
+fn gen_addr(f: &mut File, val: &Variable) {
+    match val.storage {
+        // 16byteより大きいalignmentの変数は、確保した領域の中でアドレスを切り上げる.
+        | StorageClass::AUTO if val.align > 16 => {
+            let disp = (val.align - 1) as i64 - val.offset as i64;
+            writeln!(f, "lea {}(%rbp), %rax", disp);
+            writeln!(f, "and ${}, %rax", -(val.align as i64))
+        }
+        | StorageClass::AUTO => writeln!(f, "lea -{}(%rbp), %rax", val.offset),
+        | StorageClass::STATIC => writeln!(f, "lea {}(%rip), %rax", val.label),
+        | StorageClass::EXTERN => writeln!(f, "mov {}@GOTPCREL(%rip), %rax", val.label),
+    };
+}
+
+// block_strのscopeから見える変数nameの値(8byte)を%raxに読み込む.
+#[allow(unused_must_use)]
+fn gen_load_var(f: &mut File, lv: &FunctionLocalVariable, name: &str, block_str: &str) {
+    let ident_id = blockstr_to_identid(String::from(name), String::from(block_str));
+    let val = lv
+        .get_val_offset_by_identid_recursively(ident_id)
+        .unwrap_or_else(|| panic!("sym :{} not found.", name));
+    gen_addr(f, &val);
+    writeln!(f, "mov (%rax), %rax");
+}
+
+// va_start(ap, last)のcodegen.
+// SysV ABIのva_list(gp_offset, fp_offset, overflow_arg_area, reg_save_area)を初期化する.
+// MEMO: void式だが、他の式と同じくapのアドレスをstackに残す.
+#[allow(unused_must_use)]
+fn gen_va_start(
+    node: &Node,
+    f: &mut File,
+    lv: &mut FunctionLocalVariable,
+    cl: &mut CodeLabel,
+    sd: &mut StackDepth,
+) {
+    // 固定の引数が使ったregisterとstackの数から、可変長引数の開始位置を求める.
+    let (locs, stack_slots) = classify_args(&lv.va_fixed_args);
+    let count = |pred: fn(&ArgLoc) -> bool| locs.iter().filter(|loc| pred(loc)).count();
+    let gp_count = count(|loc| matches!(loc, ArgLoc::Gp(_)));
+    let fp_count = count(|loc| matches!(loc, ArgLoc::Fp(_)));
+    let va_area_offset = lv.va_area_offset.unwrap();
+
+    gen(node.l.as_ref().unwrap(), f, lv, cl, sd);
+    writeln!(f, "mov (%rsp), %rax");
+    writeln!(f, "movl ${}, (%rax)", 8 * gp_count);
+    writeln!(f, "movl ${}, 4(%rax)", 8 * ARG_REGS.len() + 16 * fp_count);
+    writeln!(f, "lea {}(%rbp), %rdx", 16 + 8 * stack_slots);
+    writeln!(f, "mov %rdx, 8(%rax)");
+    writeln!(f, "lea -{}(%rbp), %rdx", va_area_offset);
+    writeln!(f, "mov %rdx, 16(%rax)");
+}
+
+// va_copy(dest, src)のcodegen.
+// va_listはpointerを含まない24byteのstructなので、そのままcopyすればよい.
+#[allow(unused_must_use)]
+fn gen_va_copy(
+    node: &Node,
+    f: &mut File,
+    lv: &mut FunctionLocalVariable,
+    cl: &mut CodeLabel,
+    sd: &mut StackDepth,
+) {
+    gen(node.l.as_ref().unwrap(), f, lv, cl, sd);
+    gen(node.r.as_ref().unwrap(), f, lv, cl, sd);
+    sd.pop(f, "%rsi");
+    writeln!(f, "mov (%rsp), %rax");
+    for offset in (0..24).step_by(8) {
+        writeln!(f, "mov {}(%rsi), %rdx", offset);
+        writeln!(f, "mov %rdx, {}(%rax)", offset);
+    }
+}
+
+// va_arg(ap, type)のcodegen.
+// gp_offset(浮動小数点数はfp_offset)がregister save areaの範囲内ならそこから、
+// そうでなければoverflow_arg_areaから読む.
+// long doubleは常にoverflow_arg_areaの16byte alignされた位置から読む.
+#[allow(unused_must_use)]
+fn gen_va_arg(
+    node: &Node,
+    f: &mut File,
+    lv: &mut FunctionLocalVariable,
+    cl: &mut CodeLabel,
+    sd: &mut StackDepth,
+) {
+    if *node.typ.unqual() == Type::LDOUBLE {
+        gen(node.l.as_ref().unwrap(), f, lv, cl, sd);
+        sd.pop(f, "%rax");
+        writeln!(f, "mov 8(%rax), %rdx");
+        writeln!(f, "add $15, %rdx");
+        writeln!(f, "and $-16, %rdx");
+        writeln!(f, "lea 16(%rdx), %rcx");
+        writeln!(f, "mov %rcx, 8(%rax)");
+        writeln!(f, "fldt (%rdx)");
+        sd.push_x87(f);
+        return;
+    }
+    cl.cur_index += 1;
+    let i = cl.cur_label_index();
+
+    // 読むoffsetのfieldと、register save areaでのその範囲の終わり、1つの引数の大きさ.
+    let (field, limit, step) = match node.typ.is_flonum() {
+        | true => (4, 8 * ARG_REGS.len() + 16 * FP_ARG_REGS, 16),
+        | false => (0, 8 * ARG_REGS.len(), 8),
+    };
+
+    gen(node.l.as_ref().unwrap(), f, lv, cl, sd);
+    sd.pop(f, "%rax");
+    writeln!(f, "movl {}(%rax), %ecx", field);
+    writeln!(f, "cmp ${}, %ecx", limit);
+    writeln!(f, "jae .L_VA_OVERFLOW{}", i);
+    writeln!(f, "mov 16(%rax), %rdx");
+    writeln!(f, "add %rcx, %rdx");
+    writeln!(f, "add ${}, %ecx", step);
+    writeln!(f, "movl %ecx, {}(%rax)", field);
+    writeln!(f, "jmp .L_VA_END{}", i);
+    writeln!(f, ".L_VA_OVERFLOW{}:", i);
+    writeln!(f, "mov 8(%rax), %rdx");
+    writeln!(f, "lea 8(%rdx), %rcx");
+    writeln!(f, "mov %rcx, 8(%rax)");
+    writeln!(f, ".L_VA_END{}:", i);
+    writeln!(f, "mov %rdx, %rax");
+    push_load(f, sd, &node.typ);
+}
+
+// asm文のoperandを置くregister. 64bit, 32bit, 16bit, 8bitの順の名前.
+const ASM_REGS: [[&str; 4]; 14] = [
+    ["rax", "eax", "ax", "al"],
+    ["rbx", "ebx", "bx", "bl"],
+    ["rcx", "ecx", "cx", "cl"],
+    ["rdx", "edx", "dx", "dl"],
+    ["rsi", "esi", "si", "sil"],
+    ["rdi", "edi", "di", "dil"],
+    ["r8", "r8d", "r8w", "r8b"],
+    ["r9", "r9d", "r9w", "r9b"],
+    ["r10", "r10d", "r10w", "r10b"],
+    ["r11", "r11d", "r11w", "r11b"],
+    ["r12", "r12d", "r12w", "r12b"],
+    ["r13", "r13d", "r13w", "r13b"],
+    ["r14", "r14d", "r14w", "r14b"],
+    ["r15", "r15d", "r15w", "r15b"],
+];
+// `r`の制約のoperandに割り当てるregister. 後ろから使う.
+const ASM_SCRATCH_REGS: [&str; 9] =
+    ["rax", "rdx", "rcx", "rdi", "rsi", "r9", "r8", "r11", "r10"];
+// 関数の呼び出しをまたいで値を保存する必要のあるregister.
+const CALLEE_SAVED_REGS: [&str; 5] = ["rbx", "r12", "r13", "r14", "r15"];
+
+// asm文のoperandの置き場所.
+#[derive(Clone, Copy)]
+enum AsmLoc {
+    Reg(&'static str),
+    // registerに置いたアドレスが指すメモリ.
+    Mem(&'static str),
+    Imm(i32),
+}
+
+// regのsize byteの部分の名前.
+fn asm_reg_name(reg: &str, size: usize) -> &'static str {
+    let row = ASM_REGS.iter().find(|r| r[0] == reg).unwrap();
+    return match size {
+        | 1 => row[3],
+        | 2 => row[2],
+        | 4 => row[1],
+        | _ => row[0],
+    };
+}
+
+// clobberに書かれたregisterを、64bitのregisterの名前にする.
+// `%`は付いていてもよく、`ebx`や`bl`の様な部分の名前は`rbx`になる.
+// `cc`, `memory`とxmm registerは退避しなくてよいのでNoneを返す.
+fn asm_clobber_reg(clobber: &Node) -> Option<&'static str> {
+    let name = clobber.str.trim_start_matches('%');
+    let is_xmm = name
+        .strip_prefix("xmm")
+        .and_then(|n| n.parse::<usize>().ok())
+        .is_some_and(|n| n < 16);
+    if name == "cc" || name == "memory" || is_xmm {
+        return None;
+    }
+    match ASM_REGS.iter().find(|row| row.contains(&name)) {
+        | Some(row) => Some(row[0]),
+        | None => error_at(
+            clobber.pos,
+            format!("asm: unknown register name `{}` in clobber.", name),
+            clobber.str.len() + 2,
+        ),
+    }
+}
+
+// asm文の命令列の`%0`などを、operandの置き場所に置き換える.
+// `%b0`,`%w0`,`%k0`,`%q0`はregisterのsizeを、`%c0`は即値の`$`なしを指定する.
+fn asm_template(template: &str, operands: &[&Node], locs: &[AsmLoc]) -> String {
+    let mut out = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+        if chars.peek() == Some(&'%') {
+            out.push(chars.next().unwrap());
+            continue;
+        }
+        let modifier = match chars.peek() {
+            | Some(m) if "bwkqc".contains(*m) => chars.next(),
+            | _ => None,
+        };
+        let mut num = String::new();
+        while let Some(d) = chars.peek().filter(|d| d.is_ascii_digit()) {
+            num.push(*d);
+            chars.next();
+        }
+        let i: usize = num
+            .parse()
+            .ok()
+            .filter(|i| *i < locs.len())
+            .unwrap_or_else(|| panic!("asm: invalid operand in \"{}\".", template));
+        let size = match modifier {
+            | Some('b') => 1,
+            | Some('w') => 2,
+            | Some('k') => 4,
+            | Some('q') => 8,
+            | _ => operands[i].l.as_ref().unwrap().typ.size(),
+        };
+        match (locs[i], modifier) {
+            | (AsmLoc::Reg(reg), _) => {
+                out.push_str(&format!("%{}", asm_reg_name(reg, size)))
+            }
+            | (AsmLoc::Mem(reg), _) => out.push_str(&format!("(%{})", reg)),
+            | (AsmLoc::Imm(v), Some('c')) => out.push_str(&v.to_string()),
+            | (AsmLoc::Imm(v), _) => out.push_str(&format!("${}", v)),
+        }
+    }
+    return out;
+}
+
+// 左辺値のアドレスをstackにpushする.
+#[allow(unused_must_use)]
+fn gen_lvalue(
+    node: &Node,
+    f: &mut File,
+    lv: &mut FunctionLocalVariable,
+    cl: &mut CodeLabel,
+    sd: &mut StackDepth,
+) {
+    match node.kind {
+        | NodeKind::ND_DEREF => gen(node.l.as_ref().unwrap(), f, lv, cl, sd),
+        | NodeKind::ND_PTR_DEREF => {
+            gen(node.ptr_deref_ident.as_ref().unwrap(), f, lv, cl, sd)
+        }
+        // compound literalは、名前のない変数を初期化してからそのアドレスをpushする.
+        | NodeKind::ND_COMPOUND_LIT => {
+            gen(node.l.as_ref().unwrap(), f, lv, cl, sd);
+            gen_lvalue(node.r.as_ref().unwrap(), f, lv, cl, sd);
+        }
+        // structの値はそのアドレスなので、memberのoffsetを足す.
+        | NodeKind::ND_MEMBER => {
+            gen(node.l.as_ref().unwrap(), f, lv, cl, sd);
+            sd.pop(f, "%rax");
+            writeln!(f, "add ${}, %rax", node.val);
+            sd.push(f, "%rax");
+        }
+        | _ => {
+            gen_load_addr(f, lv, node);
+            sd.push(f, "%rax");
+        }
+    }
+}
+
+// 変数nodeのアドレスを%raxに置く.
+fn gen_load_addr(f: &mut File, lv: &FunctionLocalVariable, node: &Node) {
+    let val = [node.block_str.clone(), String::from(FN_ARG_BLOC_STR)]
+        .iter()
+        .find_map(|block_str| {
+            lv.get_val_offset_by_identid_recursively(blockstr_to_identid(
+                node.str.clone(),
+                block_str.clone(),
+            ))
+        })
+        .unwrap_or_else(|| panic!("sym :{} not found.", node.str));
+    gen_addr(f, &val);
+}
+
+// asm文のcodegen.
+// 出力operandのアドレスをstackに積んでおき、入力operandをregisterに読み込んでから
+// 命令列をそのまま出力する. その後、registerに置かれた出力operandを書き戻す.
+// MEMO: clobberやoperandで使うcallee-savedなregisterは、asm文の前後で退避する.
+#[allow(unused_must_use)]
+fn gen_asm(
+    node: &Node,
+    f: &mut File,
+    lv: &mut FunctionLocalVariable,
+    cl: &mut CodeLabel,
+    sd: &mut StackDepth,
+) {
+    let n_out = node.val as usize;
+    let operands: Vec<&Node> = node
+        .block_stmts
+        .iter()
+        .filter(|n| n.kind == NodeKind::ND_EXPR)
+        .collect();
+    let clobbers: Vec<&str> = node
+        .block_stmts
+        .iter()
+        .filter(|n| n.kind == NodeKind::ND_STR)
+        .filter_map(asm_clobber_reg)
+        .collect();
+    let letters: Vec<char> = operands
+        .iter()
+        .map(|op| asm_constraint_letter(&op.str))
+        .collect();
+
+    // 各operandの置き場所を決める.
+    // 制約でregisterが決まっているものを先に除いて、残りを`r`と`m`に割り当てる.
+    let fixed_reg = |c: char| match c {
+        | 'a' => Some("rax"),
+        | 'b' => Some("rbx"),
+        | 'c' => Some("rcx"),
+        | 'd' => Some("rdx"),
+        | 'S' => Some("rsi"),
+        | 'D' => Some("rdi"),
+        | _ => None,
+    };
+    let mut used: Vec<&str> = letters.iter().filter_map(|c| fixed_reg(*c)).collect();
+    used.extend(clobbers.iter());
+    let mut free: Vec<&'static str> = ASM_SCRATCH_REGS
+        .iter()
+        .filter(|r| !used.contains(r))
+        .cloned()
+        .collect();
+    let mut alloc = || {
+        free.pop()
+            .unwrap_or_else(|| panic!("asm: impossible register constraint in `asm`."))
+    };
+    let mut locs: Vec<AsmLoc> = vec![];
+    for (i, c) in letters.iter().enumerate() {
+        let loc = match c {
+            | 'r' | 'g' => AsmLoc::Reg(alloc()),
+            | 'm' => AsmLoc::Mem(alloc()),
+            | 'i' | 'n' => AsmLoc::Imm(operands[i].l.as_ref().unwrap().val),
+            | d if d.is_ascii_digit() => locs[d.to_digit(10).unwrap() as usize],
+            | _ => AsmLoc::Reg(fixed_reg(*c).unwrap()),
+        };
+        if let (true, AsmLoc::Imm(_)) = (i < n_out, loc) {
+            panic!("asm: output operand must not be an immediate.");
+        }
+        locs.push(loc);
+    }
+    let saved: Vec<&str> = CALLEE_SAVED_REGS
+        .iter()
+        .filter(|r| used.contains(r))
+        .cloned()
+        .collect();
+    for reg in saved.iter() {
+        sd.push(f, &format!("%{}", reg));
+    }
+
+    // 出力operandのアドレス.
+    for op in operands.iter().take(n_out) {
+        gen_lvalue(op.l.as_ref().unwrap(), f, lv, cl, sd);
+    }
+    // registerに読み込む値をstackに積み、最後にまとめてpopする.
+    let mut loads: Vec<&str> = vec![];
+    for (i, op) in operands.iter().enumerate() {
+        let expr = op.l.as_ref().unwrap();
+        // 出力operandのアドレスの、stack topからのoffset.
+        let addr_offset = |pushed: usize| 8 * (n_out - 1 - i + pushed);
+        let reg = match (locs[i], i < n_out) {
+            | (AsmLoc::Mem(reg), true) => {
+                sd.push(f, &format!("{}(%rsp)", addr_offset(loads.len())));
+                reg
+            }
+            | (AsmLoc::Reg(reg), true) if op.str.starts_with('+') => {
+                writeln!(f, "mov {}(%rsp), %rax", addr_offset(loads.len()));
+                push_load(f, sd, &expr.typ);
+                reg
+            }
+            | (AsmLoc::Mem(reg), false) => {
+                gen_lvalue(expr, f, lv, cl, sd);
+                reg
+            }
+            | (AsmLoc::Reg(reg), false) => {
+                gen(expr, f, lv, cl, sd);
+                reg
+            }
+            | _ => continue,
+        };
+        loads.push(reg);
+    }
+    for reg in loads.iter().rev() {
+        sd.pop(f, &format!("%{}", reg));
+    }
+
+    writeln!(f, "{}", asm_template(&node.str, &operands, &locs));
+
+    // registerに置かれた出力operandを、積んでおいたアドレスに書き込む.
+    let reg_outs: Vec<(usize, &str)> = (0..n_out)
+        .filter_map(|i| match locs[i] {
+            | AsmLoc::Reg(reg) => Some((i, reg)),
+            | _ => None,
+        })
+        .collect();
+    for (_, reg) in reg_outs.iter() {
+        sd.push(f, &format!("%{}", reg));
+    }
+    for (k, (i, _)) in reg_outs.iter().enumerate().rev() {
+        sd.pop(f, "%rax");
+        writeln!(f, "mov {}(%rsp), %rdi", 8 * (k + n_out - 1 - i));
+        store(f, &operands[*i].l.as_ref().unwrap().typ);
+    }
+    if n_out > 0 {
+        writeln!(f, "add ${}, %rsp", 8 * n_out);
+        sd.depth -= n_out;
+    }
+    for reg in saved.iter().rev() {
+        sd.pop(f, &format!("%{}", reg));
+    }
+}
+
+// nodeを評価した結果をstackにpushするかどうか.
+// 代入や宣言などの文は、stackに何も積まない.
+fn pushes_value(node: &Node) -> bool {
+    match node.kind {
+        | NodeKind::ND_ASSIGN
+        | NodeKind::ND_DECL
+        | NodeKind::ND_RETURN
+        | NodeKind::ND_TYPEDEF
+        | NodeKind::ND_STATIC_ASSERT
+        | NodeKind::ND_ASM => false,
+        | _ => true,
+    }
+}
+
+// %raxが指すアドレスから値を読み込み、stackにpushする.
+// long doubleは、x87のregisterを通して16byteのままpushする.
+#[allow(unused_must_use)]
+fn push_load(f: &mut File, sd: &mut StackDepth, typ: &Type) {
+    // structは値を読まず、そのアドレスを値として扱う.
+    if typ.is_struct() {
+        sd.push(f, "%rax");
+        return;
+    }
+    if *typ.unqual() == Type::LDOUBLE {
+        writeln!(f, "fldt (%rax){}", volatile_mark(typ));
+        sd.push_x87(f);
+        return;
+    }
+    load(f, typ);
+    sd.push(f, "%rax");
+}
+
+// stack topの値をpopし、その下に積まれているアドレスに書き込む.
+#[allow(unused_must_use)]
+fn pop_store(f: &mut File, sd: &mut StackDepth, typ: &Type) {
+    // structは、stack topのアドレスからsize分をcopyする.
+    if typ.is_struct() {
+        sd.pop(f, "%rsi");
+        sd.pop(f, "%rdi");
+        writeln!(f, "mov ${}, %rcx", typ.size());
+        writeln!(f, "rep movsb{}", volatile_mark(typ));
+        return;
+    }
+    if *typ.unqual() == Type::LDOUBLE {
+        sd.pop_x87(f);
+        sd.pop(f, "%rdi");
+        writeln!(f, "fstpt (%rdi){}", volatile_mark(typ));
+        return;
+    }
+    sd.pop(f, "%rax");
+    sd.pop(f, "%rdi");
+    store(f, typ);
+}
+
+// stack topの値を、その下に積まれているアドレスのstorage unitにあるbit-fieldに書き込む.
+// storage unitを読み、bit-fieldのbitだけを置き換えて書き戻す.
+#[allow(unused_must_use)]
+fn pop_store_bitfield(
+    f: &mut File,
+    sd: &mut StackDepth,
+    typ: &Type,
+    width: usize,
+    bit_offset: usize,
+) {
+    let mask = u64::MAX >> (64 - width);
+    sd.pop(f, "%rdx");
+    sd.pop(f, "%rdi");
+    writeln!(f, "mov ${:#x}, %rcx", mask);
+    writeln!(f, "and %rcx, %rdx");
+    writeln!(f, "shl ${}, %rdx", bit_offset);
+    writeln!(f, "mov %rdi, %rax");
+    load(f, typ);
+    writeln!(f, "mov ${:#x}, %rcx", !(mask << bit_offset));
+    writeln!(f, "and %rcx, %rax");
+    writeln!(f, "or %rdx, %rax");
+    store(f, typ);
+}
+
+// `s.x`のxがbit-fieldであれば、そのmember.
+fn bitfield(node: &Node) -> Option<Member> {
+    if node.kind != NodeKind::ND_MEMBER {
+        return None;
+    }
+    return find_member(&node.l.as_ref().unwrap().typ, &node.str)
+        .filter(|m| m.bit_width.is_some());
+}
+
+// `*p`や`a[i]`、`s.x`で読み書きするobjectのtype.
+// 式のtypeからは型修飾子が取り除かれているので、pointerの指す先のtypeやmemberのtypeから取る.
+fn object_type(node: &Node) -> Type {
+    if node.kind == NodeKind::ND_MEMBER {
+        return node.decl_type.clone();
+    }
+    let ptr = node.l.as_ref().unwrap().typ.decay();
+    return ptr.base().unwrap_or(&node.typ).clone();
+}
+
+// volatileなobjectへの読み書きは、1回ごとに必ずmemoryにaccessする.
+// その命令にcommentで印を付けておく.
+fn volatile_mark(typ: &Type) -> &'static str {
+    if typ.qualifiers().is_volatile {
+        return " # volatile";
+    }
+    return "";
+}
+
+// %raxが指すアドレスから、typeのsize分の値を%raxに読み込む.
+// MEMO: register上の値は、常にtypeに合わせて64bitに符号拡張/ゼロ拡張しておく.
+#[allow(unused_must_use)]
+fn load(f: &mut File, typ: &Type) {
+    let insn = match typ.unqual() {
+        | Type::CHAR => "movsbq (%rax), %rax",
+        | Type::BOOL | Type::UCHAR => "movzbq (%rax), %rax",
+        | Type::SHORT => "movswq (%rax), %rax",
+        | Type::USHORT => "movzwq (%rax), %rax",
+        | Type::INT => "movslq (%rax), %rax",
+        | Type::UINT | Type::FLOAT => "movl (%rax), %eax",
+        | _ => "mov (%rax), %rax",
+    };
+    writeln!(f, "{}{}", insn, volatile_mark(typ));
+}
+
+// %raxの値を、typeのsize分だけ%rdiが指すアドレスに書き込む.
+#[allow(unused_must_use)]
+fn store(f: &mut File, typ: &Type) {
+    let insn = match typ.unqual() {
+        | Type::BOOL | Type::CHAR | Type::UCHAR => "movb %al, (%rdi)",
+        | Type::SHORT | Type::USHORT => "movw %ax, (%rdi)",
+        | Type::INT | Type::UINT | Type::FLOAT => "movl %eax, (%rdi)",
+        | _ => "mov %rax, (%rdi)",
+    };
+    writeln!(f, "{}{}", insn, volatile_mark(typ));
+}
+
+// %raxの値を、fromのtypeからtoのtypeに変換する.
+// MEMO: 整数と浮動小数点数の変換は、64bitの符号付き整数として変換する.
+//       unsigned longは、2^63以上の値を別に扱う.
+#[allow(unused_must_use)]
+fn cast(f: &mut File, cl: &mut CodeLabel, from: &Type, to: &Type) {
+    let (from, to) = (from.unqual(), to.unqual());
+    match (from, to) {
+        | (Type::FLOAT, Type::FLOAT) | (Type::DOUBLE, Type::DOUBLE) => (),
+        // `_Bool`へは、0と比べて1か0にする. 浮動小数点数は符号bitを除いて0かどうかを見る.
+        | (_, Type::BOOL) => {
+            match from {
+                | Type::FLOAT => writeln!(f, "shl $33, %rax"),
+                | Type::DOUBLE => writeln!(f, "shl $1, %rax"),
+                | _ => writeln!(f, "cmp $0, %rax"),
+            };
+            writeln!(f, "setne %al");
+            writeln!(f, "movzbq %al, %rax");
+        }
+        | (Type::FLOAT, Type::DOUBLE) => {
+            writeln!(f, "movd %eax, %xmm0");
+            writeln!(f, "cvtss2sd %xmm0, %xmm0");
+            writeln!(f, "movq %xmm0, %rax");
+        }
+        | (Type::DOUBLE, Type::FLOAT) => {
+            writeln!(f, "movq %rax, %xmm0");
+            writeln!(f, "cvtsd2ss %xmm0, %xmm0");
+            writeln!(f, "movd %xmm0, %eax");
+        }
+        // 2^63以上の値は、2^63を引いてから変換し、最上位bitを戻す.
+        | (Type::FLOAT, Type::ULONG) | (Type::DOUBLE, Type::ULONG) => {
+            cl.cur_index += 1;
+            let i = cl.cur_label_index();
+            if *from == Type::FLOAT {
+                writeln!(f, "movd %eax, %xmm0");
+                writeln!(f, "mov $0x5f000000, %edx");
+                writeln!(f, "movd %edx, %xmm1");
+                writeln!(f, "comiss %xmm1, %xmm0");
+            } else {
+                writeln!(f, "movq %rax, %xmm0");
+                writeln!(f, "movabs $0x43e0000000000000, %rdx");
+                writeln!(f, "movq %rdx, %xmm1");
+                writeln!(f, "comisd %xmm1, %xmm0");
+            }
+            let sfx = if *from == Type::FLOAT { "ss" } else { "sd" };
+            writeln!(f, "jae .L_F2U_BIG{}", i);
+            writeln!(f, "cvtt{}2si %xmm0, %rax", sfx);
+            writeln!(f, "jmp .L_F2U_END{}", i);
+            writeln!(f, ".L_F2U_BIG{}:", i);
+            writeln!(f, "sub{} %xmm1, %xmm0", sfx);
+            writeln!(f, "cvtt{}2si %xmm0, %rax", sfx);
+            writeln!(f, "movabs $0x8000000000000000, %rdx");
+            writeln!(f, "xor %rdx, %rax");
+            writeln!(f, ".L_F2U_END{}:", i);
+        }
+        | (Type::FLOAT, _) | (Type::DOUBLE, _) => {
+            let sfx = if *from == Type::FLOAT { "ss" } else { "sd" };
+            writeln!(f, "movq %rax, %xmm0");
+            writeln!(f, "cvtt{}2si %xmm0, %rax", sfx);
+            extend(f, to);
+        }
+        // 最上位bitが立っていれば、半分にして(切り捨てたbitは残して丸めを合わせる)
+        // 変換してから2倍する.
+        | (Type::ULONG, Type::FLOAT) | (Type::ULONG, Type::DOUBLE) => {
+            cl.cur_index += 1;
+            let i = cl.cur_label_index();
+            let sfx = if *to == Type::FLOAT { "ss" } else { "sd" };
+            writeln!(f, "test %rax, %rax");
+            writeln!(f, "js .L_U2F_BIG{}", i);
+            writeln!(f, "cvtsi2{}q %rax, %xmm0", sfx);
+            writeln!(f, "jmp .L_U2F_END{}", i);
+            writeln!(f, ".L_U2F_BIG{}:", i);
+            writeln!(f, "mov %rax, %rdx");
+            writeln!(f, "shr $1, %rdx");
+            writeln!(f, "and $1, %eax");
+            writeln!(f, "or %rax, %rdx");
+            writeln!(f, "cvtsi2{}q %rdx, %xmm0", sfx);
+            writeln!(f, "add{} %xmm0, %xmm0", sfx);
+            writeln!(f, ".L_U2F_END{}:", i);
+            if *to == Type::FLOAT {
+                writeln!(f, "movd %xmm0, %eax");
+            } else {
+                writeln!(f, "movq %xmm0, %rax");
+            }
+        }
+        | (_, Type::FLOAT) => {
+            writeln!(f, "cvtsi2ssq %rax, %xmm0");
+            writeln!(f, "movd %xmm0, %eax");
+        }
+        | (_, Type::DOUBLE) => {
+            writeln!(f, "cvtsi2sdq %rax, %xmm0");
+            writeln!(f, "movq %xmm0, %rax");
+        }
+        | _ => extend(f, to),
+    }
+}
+
+// stack topの値を、fromのtypeからtoのtypeに変換する. (どちらかがlong doubleの場合)
+// x87のregister stackに読み込んでから、toのtypeで書き戻す.
+#[allow(unused_must_use)]
+fn cast_x87(f: &mut File, sd: &mut StackDepth, from: &Type, to: &Type) {
+    let (from, to) = (from.unqual(), to.unqual());
+    if *from == Type::LDOUBLE {
+        sd.pop_x87(f);
+    } else {
+        let ld = match from {
+            | Type::FLOAT => "flds",
+            | Type::DOUBLE => "fldl",
+            | _ => "fildq",
+        };
+        writeln!(f, "{} (%rsp)", ld);
+        writeln!(f, "add $8, %rsp");
+        sd.depth -= 1;
+    }
+    if *to == Type::LDOUBLE {
+        sd.push_x87(f);
+        return;
+    }
+    // `_Bool`へは、0と比べて1か0にする.(NaNは1)
+    if *to == Type::BOOL {
+        writeln!(f, "fldz");
+        writeln!(f, "fucomip %st(1), %st");
+        writeln!(f, "fstp %st(0)");
+        writeln!(f, "setne %al");
+        writeln!(f, "setp %dl");
+        writeln!(f, "or %dl, %al");
+        writeln!(f, "movzbq %al, %rax");
+        sd.push(f, "%rax");
+        return;
+    }
+    let st = match to {
+        | Type::FLOAT => "fstps",
+        | Type::DOUBLE => "fstpl",
+        | _ => "fisttpq",
+    };
+    writeln!(f, "sub $8, %rsp");
+    writeln!(f, "{} (%rsp)", st);
+    sd.depth += 1;
+    if !to.is_flonum() {
+        sd.pop(f, "%rax");
+        extend(f, to);
+        sd.push(f, "%rax");
+    }
+}
+
+// %raxの値をtypeのsizeに切り詰め、64bitに符号拡張/ゼロ拡張する.
+// 8byteのtypeやvoidの場合は何もしない.
+#[allow(unused_must_use)]
+fn extend(f: &mut File, typ: &Type) {
+    match typ.unqual() {
+        | Type::CHAR => writeln!(f, "movsbq %al, %rax"),
+        | Type::BOOL | Type::UCHAR => writeln!(f, "movzbq %al, %rax"),
+        | Type::SHORT => writeln!(f, "movswq %ax, %rax"),
+        | Type::USHORT => writeln!(f, "movzwq %ax, %rax"),
+        | Type::INT => writeln!(f, "movslq %eax, %rax"),
+        | Type::UINT => writeln!(f, "movl %eax, %eax"),
+        | _ => Ok(()),
+    };
 }
 
 fn create_file(path: &str) -> File {
@@ -1,5 +1,5 @@
 use once_cell::sync::Lazy;
-use std::{ops::Deref, sync::Mutex};
+use std::sync::Mutex;
 
 pub static INPUT: Lazy<Mutex<String>> = Lazy::new(|| Mutex::new(String::new()));
 
@@ -7,20 +7,61 @@ pub fn init_error(input: String) {
     INPUT.lock().unwrap().push_str(input.as_str());
 }
 
+// preprocess後のinputの各行の先頭のoffsetと、その行の元のfile名と行番号.
+// offsetの昇順に並ぶ.
+pub static LINE_MAP: Lazy<Mutex<Vec<(usize, String, i64)>>> =
+    Lazy::new(|| Mutex::new(vec![]));
+
+pub fn add_line_map(offset: usize, file: &str, line_no: i64) {
+    LINE_MAP
+        .lock()
+        .unwrap()
+        .push((offset, file.to_string(), line_no));
+}
+
+// inputのposを`file:line`の形にする.
+pub fn display_location(pos: usize) -> String {
+    let map = LINE_MAP.lock().unwrap();
+    let i = map.partition_point(|(offset, _, _)| *offset <= pos);
+    if i == 0 {
+        return String::from("<unknown>");
+    }
+    let (_, file, line_no) = &map[i - 1];
+    return format!("{}:{}", file, line_no);
+}
+
 // ここのposは, inputからのoffsetとして変換ずみ
+// posの前後10byteを返す. 前に10byteない場合は、inputの先頭から返す.
+// MEMO: 文字の途中で切らない様に、範囲を文字の境界まで広げる.
 pub fn display_around_pos(pos: usize) -> String {
-    let mut p1 = INPUT.lock().unwrap().deref().clone();
-    // p1 -> "aaa.../"
-
-    if pos + 10 < INPUT.lock().unwrap().deref().len() {
-        let _ = p1.split_off(pos + 10);
-    } else {
-        let _ = p1.split_off(INPUT.lock().unwrap().deref().len());
+    let input = INPUT.lock().unwrap();
+    let mut start = pos.saturating_sub(10);
+    let mut end = (pos + 10).min(input.len());
+    while !input.is_char_boundary(start) {
+        start -= 1;
+    }
+    while !input.is_char_boundary(end) {
+        end += 1;
     }
+    return input[start..end].to_string();
+}
 
-    if pos < 9 {
-        return p1;
+// inputのposにあるtokenを指して、errorを表示して終了する.
+pub fn error_at(input_pos: usize, message: String, tok_len: usize) -> ! {
+    // TODO: refactor
+    let str = display_around_pos(input_pos);
+    print!("input pos: {}\n", input_pos);
+    print!("tok len: {}\n", tok_len);
+    print!("Err place: {}\n", display_location(input_pos));
+    print!("{}\n", str);
+
+    for _ in 0..input_pos.min(10) {
+        print!(" ");
+    }
+    for _ in 0..tok_len {
+        print!("^");
     }
-    let p2 = p1.split_off(pos - 10);
-    return p2;
+    println!("");
+    println!("Err message: {}", message);
+    panic!()
 }
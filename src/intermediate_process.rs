@@ -1,8 +1,13 @@
-use std::collections::HashMap;
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    sync::atomic::{self, AtomicUsize},
+};
 
 use crate::{
-    parse::{Function, Node, NodeKind},
-    tokenize::Type,
+    errors::{display_location, error_at},
+    parse::{Function, Node, NodeKind, Program, StorageClass},
+    tokenize::{find_member, literal_bytes, struct_name, Type},
 };
 
 // IdentID is a unique label for Functino's local variable,
@@ -16,16 +21,39 @@ pub const FN_ARG_BLOC_STR: &str = "_1";
 pub struct Variable {
     pub typ: Type,
     pub offset: usize,
+    pub storage: StorageClass,
+    // static, externな変数を参照するためのsymbol. (stack上の変数では空)
+    pub label: String,
+    // stack上の変数のalignment. (static, externな変数では0)
+    pub align: usize,
 }
 impl Variable {
-    fn new(offset: usize, typ: Type) -> Self {
+    fn new(offset: usize, typ: Type, align: usize) -> Self {
         return Variable {
             offset: offset,
             typ: typ,
+            storage: StorageClass::AUTO,
+            label: String::new(),
+            align: align,
         };
     }
 }
 
+// .data/.bssに置く、static変数とfile scopeの変数.
+#[derive(Clone)]
+pub struct StaticData {
+    pub label: String,
+    pub typ: Type,
+    pub align: usize,
+    // 初期値をbyte列にしたもの.(little endian)
+    pub init: Vec<u8>,
+    // 他のfileから参照できるか. (`.globl`を付ける)
+    pub is_global: bool,
+    // 初期値にアドレスを置くoffsetと、そのアドレス(`label`か`label+offset`).
+    // (initのその8byteは0にしておく)
+    pub relocs: Vec<(usize, String)>,
+}
+
 #[derive(Clone)]
 pub struct FunctionLocalVariable {
     // variable table hashmap which holds ident_id - val_offset
@@ -33,35 +61,96 @@ pub struct FunctionLocalVariable {
     // current offset address from rbp.
     // this value will be updated each time ident-node is found.
     current_offset: usize,
+    // 可変長引数の関数で、registerで渡された引数を退避する領域(register save area)の
+    // rbpからのoffset. 可変長引数の関数でなければNone.
+    pub va_area_offset: Option<usize>,
+    // 可変長引数の関数の、固定の引数のtype.
+    // va_startで、可変長引数がどのregister(またはstack)から始まるかを求めるのに使う.
+    pub va_fixed_args: Vec<Type>,
+    // 関数内で宣言されたstatic変数.
+    pub static_data: Vec<StaticData>,
 }
 impl FunctionLocalVariable {
     pub fn new() -> Self {
         return Self {
             val_table: HashMap::new(),
             current_offset: 0,
+            va_area_offset: None,
+            va_fixed_args: Vec::new(),
+            static_data: Vec::new(),
         };
     }
+    // 変数と引数の領域の合計size.
+    pub fn size(&self) -> usize {
+        return self.current_offset;
+    }
+    // register save areaを確保する.
+    // 6つの汎用register(8byte)と8つのxmm register(16byte)を置ける大きさをとる.
+    pub fn reserve_va_area(&mut self, fixed_args: Vec<Type>) {
+        self.current_offset += 6 * 8 + 8 * 16;
+        self.va_area_offset = Some(self.current_offset);
+        self.va_fixed_args = fixed_args;
+    }
     // block_strとsymbolから、idnet_idを作成する.
     // ident_idがすでにident_id_mapに存在していたら(つまり同じscopeにおいて同じシンボルが定義されていたら)、
     // Errを返す.
+    // alignは`_Alignas`で指定されたalignment. (Noneならtypeのalignment)
     pub fn try_new_val_offset(
         &mut self,
         symbol: Symbol,
         typ: Type,
         blcstr: BlockStr,
+        align: Option<usize>,
     ) -> Result<Variable, &str> {
         let ident_id = blockstr_to_identid(symbol.clone(), blcstr.clone());
         match self.get_val_offset_by_identid(ident_id.clone()) {
             // すでに同じsymbolが同じscope内で宣言されている.
             | Some(_) => Err("Already Exist Symbol"),
             | None => {
-                self.current_offset += 8;
-                let v = Variable::new(self.current_offset, typ);
+                // 変数は8byte単位のslotに置く.(va_listの様に8byteより大きいものは複数slot)
+                // long doubleの様に16byte alignが必要なものは、rbpからのoffsetも16の倍数にする.
+                let (mut size, type_align) = match &typ {
+                    | Type::None | Type::Unknown => (8, 8),
+                    | t => ((t.size() + 7) / 8 * 8, t.align().max(8)),
+                };
+                let align = align.unwrap_or(type_align).max(type_align);
+                // rbpは16byteにしかalignされていないので、それより大きいalignmentは
+                // 余分に確保した領域の中で、実行時にアドレスを切り上げて満たす.(codegenのgen_addr)
+                if align > 16 {
+                    size += align - 16;
+                }
+                let slot = align.min(16);
+                self.current_offset =
+                    (self.current_offset + size + slot - 1) / slot * slot;
+                let v = Variable::new(self.current_offset, typ, align);
                 self.val_table.insert(ident_id.clone(), v.clone());
                 return Ok(v);
             }
         }
     }
+    // stack上に領域を持たない、static/externな変数を登録する.
+    pub fn try_new_symbol(
+        &mut self,
+        symbol: Symbol,
+        typ: Type,
+        blcstr: BlockStr,
+        storage: StorageClass,
+        label: String,
+    ) -> Result<Variable, &str> {
+        let ident_id = blockstr_to_identid(symbol, blcstr);
+        if self.get_val_offset_by_identid(ident_id.clone()).is_some() {
+            return Err("Already Exist Symbol");
+        }
+        let v = Variable {
+            offset: 0,
+            typ: typ,
+            storage: storage,
+            label: label,
+            align: 0,
+        };
+        self.val_table.insert(ident_id, v.clone());
+        return Ok(v);
+    }
     pub fn get_val_offset_by_identid(&self, ident_id: IdentID) -> Option<&Variable> {
         return self.val_table.get(&ident_id);
     }
@@ -76,7 +165,8 @@ impl FunctionLocalVariable {
         let depth = identid_to_depth(&ident_id);
 
         let mut current_ident_id = ident_id.clone();
-        for _ in 0..depth {
+        // 最後はblock_strのないfile scopeの変数を探す.
+        for _ in 0..=depth {
             if let Some(val) = self.val_table.get(&current_ident_id) {
                 return Some(val.clone());
             }
@@ -131,17 +221,38 @@ struct ReadNodeArgs {
     cur_block_str: String,
     // current function's all variables. See IdentDir-struct part.
     local_variable: FunctionLocalVariable,
-    // current size to which rsp lower when called this function.
-    val_size: usize,
+    // name and return type of current function.
+    fn_name: String,
+    ret_type: Type,
+    // whether current function is variadic. (used by va_start)
+    is_variadic: bool,
+    // signatures of the functions declared in this program.
+    fn_table: FunctionTable,
 }
 impl ReadNodeArgs {
-    fn new() -> Self {
+    // 関数の外で、file scopeの変数の初期化式を読むためのもの.
+    fn new_file_scope(fn_table: FunctionTable) -> Self {
+        return ReadNodeArgs {
+            index: vec![0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+            depth: 1,
+            cur_block_str: String::from("_1"),
+            local_variable: FunctionLocalVariable::new(),
+            fn_name: String::new(),
+            ret_type: Type::None,
+            is_variadic: false,
+            fn_table: fn_table,
+        };
+    }
+    fn new(f: &Function, fn_table: FunctionTable) -> Self {
         return ReadNodeArgs {
             index: vec![0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0], // TODO: 暫定的な処置
             depth: 1,
             cur_block_str: String::from("_1"),
             local_variable: FunctionLocalVariable::new(),
-            val_size: 0,
+            fn_name: f.fn_name.clone(),
+            ret_type: f.ret_type.clone(),
+            is_variadic: f.is_variadic,
+            fn_table: fn_table,
         };
     }
 }
@@ -193,62 +304,1166 @@ pub type Symbol = String;
 // ·Function内のlocal変数の合計サイズを計算(関数呼び出し時に引き下げるrspの値の計算に使用).
 //
 // MEMO: (将来的には)最適化的なことを行う.
-pub fn intermediate_process(fvec: Vec<Function>) -> Vec<Function> {
+//       その際、Type::QUALでvolatileが付いた変数の読み書きは、回数も順序もsourceの通りに残すこと.
+// 関数のsignature. prototype宣言か関数定義から作られる.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FnSignature {
+    pub ret_type: Type,
+    pub params: Vec<Type>,
+    pub is_variadic: bool,
+}
+
+impl FnSignature {
+    // 関数pointerのtypeから、呼び出しに使うsignatureを作る.
+    fn from_fn_ptr(typ: &Type) -> Option<Self> {
+        match typ {
+            | Type::PTR(base) => match base.as_ref() {
+                | Type::FUNC(ret_type, params, is_variadic) => Some(FnSignature {
+                    ret_type: ret_type.as_ref().clone(),
+                    params: params.clone(),
+                    is_variadic: *is_variadic,
+                }),
+                | _ => None,
+            },
+            | _ => None,
+        }
+    }
+    // 関数のアドレスのtype.
+    fn to_fn_ptr(&self) -> Type {
+        return Type::PTR(Box::new(Type::FUNC(
+            Box::new(self.ret_type.clone()),
+            self.params.clone(),
+            self.is_variadic,
+        )));
+    }
+}
+
+// 関数名とsignatureの対応表.
+// 関数呼び出しの際に、引数の数·typeのcheckと返り値のtypeの決定に使う.
+pub type FunctionTable = HashMap<String, FnSignature>;
+
+// prototype宣言と関数定義から、FunctionTableを作成する.
+// 同じ関数に対してsignatureが異なる宣言があった場合はErrを返す.
+pub fn build_function_table(fvec: &Vec<Function>) -> Result<FunctionTable, String> {
+    let mut table = FunctionTable::new();
+    let mut defined = vec![];
+    for f in fvec.iter() {
+        if !f.is_prototype {
+            if defined.contains(&f.fn_name) {
+                return Err(format!("redefinition of function {}", f.fn_name));
+            }
+            defined.push(f.fn_name.clone());
+        }
+        // 引数と返り値のtypeの型修飾子は、関数のtypeには含めない.
+        let sig = FnSignature {
+            ret_type: f.ret_type.unqual().clone(),
+            params: f
+                .fn_args
+                .iter()
+                .map(|arg| arg.typ.unqual().clone())
+                .collect(),
+            is_variadic: f.is_variadic,
+        };
+        match table.get(&f.fn_name) {
+            | Some(s) if *s != sig => {
+                return Err(format!("conflicting types for function {}", f.fn_name))
+            }
+            | _ => {
+                table.insert(f.fn_name.clone(), sig);
+            }
+        }
+    }
+    return Ok(table);
+}
+
+pub fn intermediate_process(mut program: Program) -> Program {
     let mut fvec_after_processed = vec![];
 
+    // 関数呼び出しをcheckするため、先に全関数のsignatureを集めておく.
+    let fvec = &program.functions;
+    let fn_table = build_function_table(fvec).unwrap_or_else(|e| panic!("Err: {}", e));
+
     for f in fvec.iter() {
+        // prototype宣言はFunctionTableにのみ使い、codeは生成しない.
+        if f.is_prototype {
+            continue;
+        }
         let mut f_clone = f.clone();
 
         // 関数の引数、およびのローカル変数をlocal_variableに格納.
         // identのtypeを辻褄合わせ.
-        set_block_str_and_create_localval_table(&mut f_clone);
+        set_block_str_and_create_localval_table(&mut f_clone, &fn_table);
 
         fvec_after_processed.push(f_clone);
     }
-    return fvec_after_processed;
+    program.global_data = build_global_data(&mut program.globals, &fn_table);
+    program.functions = fvec_after_processed;
+    return program;
+}
+
+// file scopeの変数を、.data/.bssに置くdataにする.
+// externな変数は他のfileで定義されるので、dataを作らない.
+// MEMO: 初期化式から他のfile scopeの変数を参照できる様に、先に全ての変数を登録しておく.
+fn build_global_data(globals: &mut [Node], fn_table: &FunctionTable) -> Vec<StaticData> {
+    let mut arg = ReadNodeArgs::new_file_scope(fn_table.clone());
+    for decl in globals.iter() {
+        let sym = decl.l.as_ref().unwrap().str.clone();
+        let storage = match decl.storage {
+            | StorageClass::EXTERN => StorageClass::EXTERN,
+            | _ => StorageClass::STATIC,
+        };
+        let _ = arg.local_variable.try_new_symbol(
+            sym.clone(),
+            decl.decl_type.clone(),
+            String::new(),
+            storage,
+            sym,
+        );
+    }
+    let mut data = vec![];
+    for decl in globals.iter_mut() {
+        if decl.storage == StorageClass::EXTERN {
+            continue;
+        }
+        let sym = decl.l.as_ref().unwrap().str.clone();
+        let (init, relocs) = match decl.r.as_mut() {
+            | Some(r) => {
+                read_node(r, &mut arg);
+                let ident = decl.l.as_ref().unwrap();
+                let lv = &arg.local_variable;
+                build_static_init(r, &decl.decl_type, ident, lv, &mut data)
+            }
+            | None => (vec![0; decl.decl_type.size()], vec![]),
+        };
+        data.push(StaticData {
+            label: sym,
+            typ: decl.decl_type.clone(),
+            align: decl.decl_align.unwrap_or(decl.decl_type.align()),
+            init: init,
+            is_global: decl.storage == StorageClass::AUTO,
+            relocs: relocs,
+        });
+    }
+    return data;
 }
 
 // Read the all nodes owned by Function and create variable table.
 // In addition, it counts size to which rsp lowered when called this function.
-fn set_block_str_and_create_localval_table(f: &mut Function) {
+fn set_block_str_and_create_localval_table(f: &mut Function, fn_table: &FunctionTable) {
     let mut nodes = f.root_node.fn_blocks.clone();
-    let mut arg = ReadNodeArgs::new();
-
-    for node in nodes.as_mut() as &mut Vec<Node> {
-        read_node(node, &mut arg);
-    }
-    let root_node = Node {
-        kind: NodeKind::ND_BLOCK,
-        fn_blocks: nodes,
-        ..Default::default()
-    };
+    let mut arg = ReadNodeArgs::new(f, fn_table.clone());
 
     // 関数の引数をlocal_variableに詰める
-    let mut local_variable = arg.local_variable.clone();
-    for (_, arg) in f.fn_args.iter().cloned().enumerate() {
+    // MEMO: 関数本体の中で引数のtypeを参照できるように、nodeを読む前に詰めておく.
+    for (_, fn_arg) in f.fn_args.iter().cloned().enumerate() {
         // TODO: 関数の引数はdepth, index共に0とする(後にきちんと仕様としてどこかにまとめる)
         let block_str = String::from(FN_ARG_BLOC_STR);
-        let _ = local_variable
-            .try_new_val_offset(arg.sym.clone(), Type::Unknown, block_str)
+        let _ = arg
+            .local_variable
+            .try_new_val_offset(fn_arg.sym.clone(), fn_arg.typ.clone(), block_str, None)
             .unwrap_or_else(|e| {
                 panic!(
                     "Err: {}: Maybe symbol {} is duplicated in this function.",
-                    e, arg.sym
+                    e, fn_arg.sym
                 )
             });
     }
+    if f.is_variadic {
+        let fixed_args = f.fn_args.iter().map(|a| a.typ.clone()).collect();
+        arg.local_variable.reserve_va_area(fixed_args);
+    }
+
+    for node in nodes.as_mut() as &mut Vec<Node> {
+        read_node(node, &mut arg);
+        add_type(node);
+    }
+    // 初期化前に読まれる可能性のある変数を警告する.
+    let mut checker = InitChecker::new(&f.fn_name, &arg.local_variable);
+    let mut state = Some(HashSet::new());
+    for node in nodes.iter() {
+        checker.check(node, &mut state);
+    }
+    let root_node = Node {
+        kind: NodeKind::ND_BLOCK,
+        fn_blocks: nodes,
+        ..Default::default()
+    };
 
     f.root_node = root_node;
-    f.lv_size = arg.val_size;
-    f.local_variable = local_variable;
+    // 引数の領域はfn_args_sizeとして別に数えている.
+    f.lv_size = arg.local_variable.size() - f.fn_args_size;
+    f.local_variable = arg.local_variable;
     return;
 }
 
+// `__func__`は、関数名で初期化された`static const char __func__[]`が
+// 関数の先頭で宣言されているものとして扱う.
+// MEMO: 使われた時に初めて登録するので、.dataに置かれるのは使った関数の分だけ.
+fn declare_func_name(arg: &mut ReadNodeArgs) {
+    let block_str = String::from(FN_ARG_BLOC_STR);
+    let ident_id = blockstr_to_identid(String::from("__func__"), block_str.clone());
+    let lv = &mut arg.local_variable;
+    if lv.get_val_offset_by_identid(ident_id).is_some() {
+        return;
+    }
+    let mut init = arg.fn_name.clone().into_bytes();
+    init.push(0);
+    let typ = Type::ARRAY(Box::new(Type::CHAR), init.len());
+    let label = format!("{}.__func__", arg.fn_name);
+    lv.static_data.push(StaticData {
+        label: label.clone(),
+        typ: typ.clone(),
+        align: 1,
+        init: init,
+        is_global: false,
+        relocs: vec![],
+    });
+    let _ = lv.try_new_symbol(
+        String::from("__func__"),
+        typ,
+        block_str,
+        StorageClass::STATIC,
+        label,
+    );
+}
+
+// 初期化されていない可能性のある変数(maybe uninitialized)の集合.
+// 変数はrbpからのoffsetで区別する. Noneはreturnの後などの到達しない位置を表す.
+type UninitState = Option<HashSet<usize>>;
+
+// 分岐の合流点では、どちらかの経路で初期化されていなければ初期化されていない可能性がある.
+fn join_uninit(a: UninitState, b: UninitState) -> UninitState {
+    match (a, b) {
+        | (None, s) | (s, None) => s,
+        | (Some(a), Some(b)) => Some(a.union(&b).cloned().collect()),
+    }
+}
+
+// 文の流れに沿って変数の初期化状態を追跡し、初期化前に読まれる可能性のある変数を警告する.
+struct InitChecker<'a> {
+    fn_name: &'a str,
+    local_variable: &'a FunctionLocalVariable,
+    // 同じ変数について何度も警告しない様に.
+    warned: HashSet<usize>,
+}
+impl<'a> InitChecker<'a> {
+    fn new(fn_name: &'a str, local_variable: &'a FunctionLocalVariable) -> Self {
+        return InitChecker {
+            fn_name: fn_name,
+            local_variable: local_variable,
+            warned: HashSet::new(),
+        };
+    }
+    fn var_offset(&self, ident: &Node) -> Option<usize> {
+        return self
+            .local_variable
+            .get_val_offset_by_identid_recursively(blockstr_to_identid(
+                ident.str.clone(),
+                ident.block_str.clone(),
+            ))
+            .map(|v| v.offset);
+    }
+    fn set_initialized(&self, ident: &Node, state: &mut UninitState) {
+        if let (Some(s), Some(offset)) = (state.as_mut(), self.var_offset(ident)) {
+            s.remove(&offset);
+        }
+    }
+    fn check(&mut self, node: &Node, state: &mut UninitState) {
+        match node.kind {
+            | NodeKind::ND_IDENT => {
+                let offset = match self.var_offset(node) {
+                    | Some(offset) => offset,
+                    | None => return,
+                };
+                if let Some(s) = state.as_ref() {
+                    if s.contains(&offset) && self.warned.insert(offset) {
+                        eprintln!(
+                            "{}: warning: `{}` may be used uninitialized in function {}.",
+                            display_location(node.pos),
+                            node.str,
+                            self.fn_name
+                        );
+                    }
+                }
+            }
+            | NodeKind::ND_DECL => {
+                let ident = node.l.as_ref().unwrap();
+                // 可変長配列のsizeを計算する隠れた変数の宣言.
+                for size_decl in node.block_stmts.iter() {
+                    self.check(size_decl, state);
+                }
+                match node.r.as_ref() {
+                    | Some(r) => {
+                        self.check(r, state);
+                        self.set_initialized(ident, state);
+                    }
+                    // va_listはva_startで初期化されるので対象外.
+                    // static, externな変数はstack上にないので対象外.
+                    // 配列とstructは要素やmemberごとに初期化されるので、追跡しない.
+                    | None if node.decl_type != Type::VA_LIST
+                        && !node.decl_type.is_array()
+                        && !node.decl_type.is_struct()
+                        && !node.decl_type.is_vla()
+                        && node.storage == StorageClass::AUTO =>
+                    {
+                        if let (Some(s), Some(offset)) =
+                            (state.as_mut(), self.var_offset(ident))
+                        {
+                            s.insert(offset);
+                        }
+                    }
+                    | None => (),
+                }
+            }
+            | NodeKind::ND_ASSIGN => {
+                self.check(node.r.as_ref().unwrap(), state);
+                let lhs = node.l.as_ref().unwrap();
+                match lhs.kind {
+                    | NodeKind::ND_IDENT => self.set_initialized(lhs, state),
+                    // `a[i] = x`のindexなどは読まれる.
+                    | _ => self.check(lhs, state),
+                }
+            }
+            | NodeKind::ND_INIT_LIST => {
+                for init in node.block_stmts.iter() {
+                    self.check(init, state);
+                }
+            }
+            // asm文の出力operandは、asm文の中で書き込まれる.
+            // ただし`+`の付いたoperandは、書き込む前に読まれる.
+            | NodeKind::ND_ASM => {
+                let n_out = node.val as usize;
+                let operands = node
+                    .block_stmts
+                    .iter()
+                    .filter(|op| op.kind != NodeKind::ND_STR);
+                for (i, op) in operands.clone().enumerate() {
+                    let expr = op.l.as_ref().unwrap();
+                    if i >= n_out
+                        || expr.kind != NodeKind::ND_IDENT
+                        || op.str.starts_with('+')
+                    {
+                        self.check(expr, state);
+                    }
+                }
+                for op in operands.take(n_out) {
+                    let expr = op.l.as_ref().unwrap();
+                    if expr.kind == NodeKind::ND_IDENT {
+                        self.set_initialized(expr, state);
+                    }
+                }
+            }
+            // アドレスを取られた変数は、pointer経由で初期化されうるので初期化済みとみなす.
+            | NodeKind::ND_PTR_REF => {
+                self.set_initialized(node.ptr_ref_ident.as_ref().unwrap(), state);
+            }
+            | NodeKind::ND_PTR_DEREF => {
+                self.check(node.ptr_deref_ident.as_ref().unwrap(), state);
+            }
+            | NodeKind::ND_ADDR => {
+                let mut obj = node.l.as_ref().unwrap();
+                while obj.kind == NodeKind::ND_MEMBER {
+                    obj = obj.l.as_ref().unwrap();
+                }
+                match obj.kind {
+                    | NodeKind::ND_IDENT => self.set_initialized(obj, state),
+                    | _ => self.check(obj, state),
+                }
+            }
+            | NodeKind::ND_RETURN => {
+                if let Some(l) = node.l.as_ref() {
+                    self.check(l, state);
+                }
+                *state = None;
+            }
+            | NodeKind::ND_BLOCK => {
+                for stmt in node.block_stmts.iter() {
+                    self.check(stmt, state);
+                }
+            }
+            | NodeKind::ND_FNCALL => {
+                for arg in node.fn_call_args.iter() {
+                    self.check(arg.val.as_ref().unwrap(), state);
+                }
+                if let Some(callee) = node.l.as_ref() {
+                    self.check(callee, state);
+                }
+            }
+            // 条件を評価した後、いずれかの分岐を実行する.
+            // elseがなければ、どの分岐も実行しない経路もある.
+            | NodeKind::ND_IFSTMT => {
+                let mut after = None;
+                for branch in [&node.if_node, &node.elsif_node].iter() {
+                    if let Some(branch) = branch {
+                        self.check(branch.l.as_ref().unwrap(), state);
+                        let mut s = state.clone();
+                        self.check(branch.r.as_ref().unwrap(), &mut s);
+                        after = join_uninit(after, s);
+                    }
+                }
+                if let Some(else_node) = node.else_node.as_ref() {
+                    self.check(else_node, state);
+                }
+                *state = join_uninit(after, state.take());
+            }
+            // loopの本体は一度も実行されない場合がある.
+            | NodeKind::ND_FOR => {
+                self.check(node.for_node_first_assign.as_ref().unwrap(), state);
+                self.check(node.for_node_second_condition.as_ref().unwrap(), state);
+                let mut s = state.clone();
+                self.check(node.for_node_stmts.as_ref().unwrap(), &mut s);
+                self.check(node.for_node_third_expr.as_ref().unwrap(), &mut s);
+                *state = join_uninit(state.take(), s);
+            }
+            | _ => {
+                if let Some(l) = node.l.as_ref() {
+                    self.check(l, state);
+                }
+                if let Some(r) = node.r.as_ref() {
+                    self.check(r, state);
+                }
+            }
+        }
+    }
+}
+
+// 2つの算術型のoperandに対して、演算を行うtypeを返す.(usual arithmetic conversion)
+// どちらかが浮動小数点型なら、大きい方の浮動小数点型になる.
+pub fn usual_arith_conv(l: &Type, r: &Type) -> Type {
+    for typ in [Type::LDOUBLE, Type::DOUBLE] {
+        if *l.unqual() == typ || *r.unqual() == typ {
+            return typ;
+        }
+    }
+    if l.is_flonum() || r.is_flonum() {
+        return Type::FLOAT;
+    }
+    if !l.is_integer() || !r.is_integer() {
+        return Type::LONG;
+    }
+    // intより小さい型はintに格上げする.
+    let promote = |t: &Type| if t.size() < 4 { Type::INT } else { t.clone() };
+    let (l, r) = (promote(l), promote(r));
+    if l.size() != r.size() {
+        return if l.size() > r.size() { l } else { r };
+    }
+    // 同じsizeならunsignedの方に合わせる.
+    return if l.is_unsigned() { l } else { r };
+}
+
+// nodeが`0`の様なnull pointer定数かどうか.
+fn is_null_pointer_constant(node: &Node) -> bool {
+    match node.kind {
+        | NodeKind::ND_EXPR => is_null_pointer_constant(node.l.as_ref().unwrap()),
+        | NodeKind::ND_NUM => node.val == 0,
+        | _ => false,
+    }
+}
+
+// 式nodeの値を、typeへ暗黙に変換する.
+// typeが異なる場合はND_CASTでwrapし、暗黙に変換できない場合はErrを返す.
+// MEMO: 変換先のtypeの型修飾子は無視する.(値には型修飾子がない)
+fn implicit_conv(node: Box<Node>, to: &Type) -> Result<Box<Node>, String> {
+    // string literalの様な配列の値は、先頭へのpointerになる.
+    let from = node.typ.decay();
+    let to = to.unqual();
+    if from == *to || from == Type::Unknown {
+        return Ok(node);
+    }
+    let convertible = (from.is_arith() && to.is_arith())
+        || (from.is_ptr() && *to == Type::BOOL)
+        || is_compatible_ptr(&from, to)
+        || (to.is_ptr() && is_null_pointer_constant(&node));
+    if !convertible {
+        return Err(format!("expected {:?}, but got {:?}", to, from));
+    }
+    return Ok(Box::new(Node {
+        kind: NodeKind::ND_CAST,
+        typ: to.clone(),
+        l: Some(node),
+        ..Default::default()
+    }));
+}
+
+// 定数式を評価する. 定数でなければNoneを返す.
+pub fn eval_const(node: &Node) -> Option<i64> {
+    return eval_const_typed(node).map(|(v, _)| v);
+}
+
+// 定数式の値と、そのtypeを返す.
+// 二項演算はusual arithmetic conversionで決まるtypeで行い、unsignedなら符号なしで比較・除算する.
+fn eval_const_typed(node: &Node) -> Option<(i64, Type)> {
+    let l = || eval_const_typed(node.l.as_ref().unwrap());
+    let r = || eval_const_typed(node.r.as_ref().unwrap());
+    match node.kind {
+        | NodeKind::ND_NUM => Some((node.val as i64, Type::INT)),
+        | NodeKind::ND_EXPR => l(),
+        // 浮動小数点数の定数も、整数へのcastなら整数の定数になる.
+        | NodeKind::ND_CAST => {
+            let typ = node.typ.unqual().clone();
+            let v = match l() {
+                | Some((v, _)) => v,
+                | None => {
+                    let f = eval_const_flonum(node.l.as_ref()?)?;
+                    if typ == Type::BOOL {
+                        (f != 0.0) as i64
+                    } else if typ.is_unsigned() {
+                        f as u64 as i64
+                    } else {
+                        f as i64
+                    }
+                }
+            };
+            Some((truncate_const(v, &typ), typ))
+        }
+        | NodeKind::ND_ADD
+        | NodeKind::ND_SUB
+        | NodeKind::ND_MUL
+        | NodeKind::ND_DIV
+        | NodeKind::ND_EQ
+        | NodeKind::ND_NEQ
+        | NodeKind::ND_LT
+        | NodeKind::ND_LE
+        | NodeKind::ND_BT
+        | NodeKind::ND_BE => {
+            let ((lv, lt), (rv, rt)) = (l()?, r()?);
+            let typ = usual_arith_conv(&lt, &rt);
+            let (lv, rv) = (truncate_const(lv, &typ), truncate_const(rv, &typ));
+            let unsigned = typ.is_unsigned();
+            let ord = if unsigned {
+                (lv as u64).cmp(&(rv as u64))
+            } else {
+                lv.cmp(&rv)
+            };
+            // 比較の結果はintになる.
+            let cmp = match node.kind {
+                | NodeKind::ND_EQ => Some(ord == Ordering::Equal),
+                | NodeKind::ND_NEQ => Some(ord != Ordering::Equal),
+                | NodeKind::ND_LT => Some(ord == Ordering::Less),
+                | NodeKind::ND_LE => Some(ord != Ordering::Greater),
+                | NodeKind::ND_BT => Some(ord == Ordering::Greater),
+                | NodeKind::ND_BE => Some(ord != Ordering::Less),
+                | _ => None,
+            };
+            if let Some(b) = cmp {
+                return Some((b as i64, Type::INT));
+            }
+            let v = match node.kind {
+                | NodeKind::ND_ADD => lv.wrapping_add(rv),
+                | NodeKind::ND_SUB => lv.wrapping_sub(rv),
+                | NodeKind::ND_MUL => lv.wrapping_mul(rv),
+                | _ if rv == 0 => return None,
+                | _ if unsigned => ((lv as u64) / (rv as u64)) as i64,
+                | _ => lv.wrapping_div(rv),
+            };
+            Some((truncate_const(v, &typ), typ))
+        }
+        | _ => None,
+    }
+}
+
+// 整数の定数を、typeのsizeに切り詰めて符号拡張/ゼロ拡張する.(codegenのextendと同じ)
+fn truncate_const(v: i64, typ: &Type) -> i64 {
+    match typ.unqual() {
+        | Type::BOOL => (v != 0) as i64,
+        | Type::CHAR => v as i8 as i64,
+        | Type::UCHAR => v as u8 as i64,
+        | Type::SHORT => v as i16 as i64,
+        | Type::USHORT => v as u16 as i64,
+        | Type::INT => v as i32 as i64,
+        | Type::UINT => v as u32 as i64,
+        | _ => v,
+    }
+}
+
+// `_Static_assert`の条件を評価する.
+// 条件が定数式でないか、0(偽)であればErrを返す.
+pub fn check_static_assert(node: &Node) -> Result<(), String> {
+    match eval_const(node.l.as_ref().unwrap()) {
+        | None => Err(String::from(
+            "expression in static assertion is not an integer constant expression.",
+        )),
+        | Some(0) => Err(format!("static assertion failed: \"{}\"", node.str)),
+        | Some(_) => Ok(()),
+    }
+}
+
+// asm文のoperandの制約から、operandの置き場所を表す文字を取り出す.
+// `=`(出力)、`+`(入出力)、`&`(early clobber)は読み飛ばし、
+// `"rm"`の様に候補が複数あれば先頭のものを使う.
+pub fn asm_constraint_letter(constraint: &str) -> char {
+    return constraint
+        .chars()
+        .find(|c| !"=+&".contains(*c))
+        .filter(|c| "abcdSDrgmin".contains(*c) || c.is_ascii_digit())
+        .unwrap_or_else(|| panic!("asm: unsupported constraint \"{}\".", constraint));
+}
+
+// 浮動小数点数の定数式を評価する. 定数でなければNoneを返す.
+fn eval_const_flonum(node: &Node) -> Option<f64> {
+    let l = || eval_const_flonum(node.l.as_ref().unwrap());
+    let r = || eval_const_flonum(node.r.as_ref().unwrap());
+    match node.kind {
+        | NodeKind::ND_FNUM => Some(node.fval),
+        | NodeKind::ND_NUM => Some(node.val as f64),
+        | NodeKind::ND_EXPR | NodeKind::ND_CAST => l(),
+        | NodeKind::ND_ADD => Some(l()? + r()?),
+        | NodeKind::ND_SUB => Some(l()? - r()?),
+        | NodeKind::ND_MUL => Some(l()? * r()?),
+        | NodeKind::ND_DIV => Some(l()? / r()?),
+        | _ => None,
+    }
+}
+
+// doubleの値を、x87の80bit拡張倍精度のbyte列(16byteにpaddingしたもの)にする.
+// 80bitの形式は、64bitの仮数部(整数bitを明示する)と符号bit、15bitの指数部からなる.
+// MEMO: doubleの値は全て80bitで正確に表せる.
+pub fn f64_to_f80_bytes(v: f64) -> [u8; 16] {
+    let bits = v.to_bits();
+    let sign = (bits >> 63) as u16;
+    let exp = ((bits >> 52) & 0x7ff) as i64;
+    let frac = bits & ((1 << 52) - 1);
+    let (exp80, mant) = match (exp, frac) {
+        | (0, 0) => (0, 0),
+        // inf, nan.
+        | (0x7ff, _) => (0x7fff, (1 << 63) | (frac << 11)),
+        // doubleの非正規化数は、80bitでは正規化数になる.
+        | (0, _) => {
+            let lz = frac.leading_zeros() as i64;
+            (63 - 1074 - lz + 16383, frac << lz)
+        }
+        | _ => (exp - 1023 + 16383, (1 << 63) | (frac << 11)),
+    };
+    let mut bytes = [0; 16];
+    bytes[..8].copy_from_slice(&mant.to_le_bytes());
+    bytes[8..10].copy_from_slice(&((sign << 15) | exp80 as u16).to_le_bytes());
+    return bytes;
+}
+
+// static変数の初期化式に現れたstring literalに付けるlabelの番号.
+static STATIC_STR_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+// static変数の初期化式から、.dataに置くbyte列と、アドレスを置く位置(relocs)を作る.
+// 初期化式は全て定数(算術型の定数かアドレス定数)でなければならない.
+// identは初期化する変数で、lvはアドレス定数の変数のlabelを探すのに使う.
+// file scopeのcompound literalは、同じtypeの値ならそのbyte列をそのまま置く.
+fn build_static_init(
+    init: &mut Node,
+    typ: &Type,
+    ident: &Node,
+    lv: &FunctionLocalVariable,
+    literals: &mut Vec<StaticData>,
+) -> (Vec<u8>, Vec<(usize, String)>) {
+    let mut bytes = vec![0; typ.size()];
+    let mut relocs = vec![];
+    let not_constant = || -> ! {
+        error_at(
+            ident.pos,
+            format!(
+                "initializer of static variable `{}` is not constant.",
+                ident.str
+            ),
+            ident.str.len(),
+        )
+    };
+    let mut write = |offset: usize,
+                     typ: &Type,
+                     expr: &mut Node,
+                     bit_field: Option<(usize, usize)>| {
+        let mut expr = expr;
+        while expr.kind == NodeKind::ND_EXPR {
+            expr = expr.l.as_mut().unwrap();
+        }
+        add_type(expr);
+        if typ.is_ptr() {
+            if let Some((label, off)) = static_addr(expr, ident, lv, literals) {
+                let addr = match off {
+                    | 0 => label,
+                    | _ => format!("{}{:+}", label, off),
+                };
+                relocs.push((offset, addr));
+                return;
+            }
+        }
+        if expr.kind == NodeKind::ND_COMPOUND_LIT
+            && expr.l.as_ref().unwrap().is_file_scope
+        {
+            let decl = expr.l.as_mut().unwrap();
+            let lit_typ = decl.decl_type.clone();
+            if lit_typ.unqual() != typ.unqual() {
+                not_constant();
+            }
+            if let Some(r) = decl.r.as_mut() {
+                let (init, lit_relocs) =
+                    build_static_init(r, &lit_typ, ident, lv, literals);
+                bytes[offset..offset + init.len()].copy_from_slice(&init);
+                relocs.extend(lit_relocs.into_iter().map(|(o, l)| (offset + o, l)));
+            }
+            return;
+        }
+        let val = match typ.unqual() {
+            | Type::FLOAT => {
+                eval_const_flonum(expr).map(|v| (v as f32).to_le_bytes().to_vec())
+            }
+            | Type::DOUBLE => eval_const_flonum(expr).map(|v| v.to_le_bytes().to_vec()),
+            | Type::LDOUBLE => {
+                eval_const_flonum(expr).map(|v| f64_to_f80_bytes(v).to_vec())
+            }
+            | Type::BOOL => eval_const(expr)
+                .or_else(|| eval_const_flonum(expr).map(|v| (v != 0.0) as i64))
+                .map(|v| vec![(v != 0) as u8]),
+            | _ => eval_const(expr).map(|v| v.to_le_bytes().to_vec()),
+        }
+        .unwrap_or_else(|| not_constant());
+        let size = typ.size();
+        // bit-fieldは、storage unitの他のbitを残してそのbitだけを置き換える.
+        let val = match bit_field {
+            | Some((width, bit_offset)) => {
+                let to_u64 = |b: &[u8]| {
+                    let mut a = [0; 8];
+                    a[..b.len()].copy_from_slice(b);
+                    u64::from_le_bytes(a)
+                };
+                let mask = (u64::MAX >> (64 - width)) << bit_offset;
+                let old = to_u64(&bytes[offset..offset + size]);
+                let new = to_u64(&val[..size]) << bit_offset;
+                ((old & !mask) | (new & mask)).to_le_bytes().to_vec()
+            }
+            | None => val,
+        };
+        bytes[offset..offset + size].copy_from_slice(&val[..size]);
+    };
+    if init.kind == NodeKind::ND_INIT_LIST {
+        for elem in init.block_stmts.iter_mut() {
+            let typ = elem.typ.clone();
+            write(
+                elem.val as usize,
+                &typ,
+                elem.l.as_mut().unwrap(),
+                elem.bit_field,
+            );
+        }
+    } else {
+        write(0, typ, init, None);
+    }
+    return (bytes, relocs);
+}
+
+// static変数の初期化式で、アドレス定数になる式のlabelとそこからのoffsetを返す.
+// (string literal, `&x`, `&a[1]`, `&s.m`, 配列, 関数名と、それらに定数を足したもの)
+// string literalは.dataに置くdataをliteralsに追加する. アドレス定数でなければNoneを返す.
+// MEMO: add_type済みのnodeを渡す.
+fn static_addr(
+    expr: &mut Node,
+    ident: &Node,
+    lv: &FunctionLocalVariable,
+    literals: &mut Vec<StaticData>,
+) -> Option<(String, i64)> {
+    match expr.kind {
+        | NodeKind::ND_EXPR => static_addr(expr.l.as_mut().unwrap(), ident, lv, literals),
+        // pointerへのcastは、アドレスを変えない.
+        | NodeKind::ND_CAST if expr.typ.is_ptr() => {
+            static_addr(expr.l.as_mut().unwrap(), ident, lv, literals)
+        }
+        | NodeKind::ND_FN_ADDR => Some((expr.str.clone(), 0)),
+        | NodeKind::ND_STR => {
+            let n = STATIC_STR_COUNT.fetch_add(1, atomic::Ordering::Relaxed);
+            let label = format!(".L.static_str{}", n);
+            let mut init = literal_bytes(&expr.str);
+            init.push(0);
+            literals.push(StaticData {
+                label: label.clone(),
+                typ: expr.typ.clone(),
+                align: 1,
+                init: init,
+                is_global: false,
+                relocs: vec![],
+            });
+            Some((label, 0))
+        }
+        // 配列は、先頭の要素へのpointerになる.
+        | NodeKind::ND_IDENT | NodeKind::ND_COMPOUND_LIT => {
+            let is_array = match expr.kind {
+                | NodeKind::ND_IDENT => {
+                    let ident_id =
+                        blockstr_to_identid(expr.str.clone(), expr.block_str.clone());
+                    lv.get_val_offset_by_identid_recursively(ident_id)
+                        .is_some_and(|val| val.typ.is_array())
+                }
+                | _ => expr.l.as_ref().unwrap().decl_type.is_array(),
+            };
+            if !is_array {
+                return None;
+            }
+            static_lvalue_addr(expr, ident, lv, literals)
+        }
+        | NodeKind::ND_PTR_REF => {
+            let obj = expr.ptr_ref_ident.as_mut().unwrap();
+            static_lvalue_addr(obj, ident, lv, literals)
+        }
+        | NodeKind::ND_ADDR => {
+            static_lvalue_addr(expr.l.as_mut().unwrap(), ident, lv, literals)
+        }
+        // pointerと整数の定数の加減算は、要素のsize単位でoffsetをずらす.
+        | NodeKind::ND_ADD | NodeKind::ND_SUB => {
+            let (l, r) = (expr.l.as_mut().unwrap(), expr.r.as_mut().unwrap());
+            let (l_ptr, r_ptr) = (l.typ.decay().is_ptr(), r.typ.decay().is_ptr());
+            let (ptr, idx, sign) = match (l_ptr, r_ptr) {
+                | (true, false) if expr.kind == NodeKind::ND_SUB => (l, r, -1),
+                | (true, false) => (l, r, 1),
+                | (false, true) if expr.kind == NodeKind::ND_ADD => (r, l, 1),
+                | _ => return None,
+            };
+            let size = ptr.typ.decay().base()?.size() as i64;
+            let n = eval_const(idx)?;
+            let (label, off) = static_addr(ptr, ident, lv, literals)?;
+            Some((label, off + sign * n * size))
+        }
+        | _ => None,
+    }
+}
+
+// static変数の初期化式で、lvalueが指すobjectのアドレスが定数なら、そのlabelとoffsetを返す.
+// static, externな変数と、file scopeのcompound literalのアドレスは定数になる.
+fn static_lvalue_addr(
+    node: &mut Node,
+    ident: &Node,
+    lv: &FunctionLocalVariable,
+    literals: &mut Vec<StaticData>,
+) -> Option<(String, i64)> {
+    match node.kind {
+        | NodeKind::ND_EXPR => {
+            static_lvalue_addr(node.l.as_mut().unwrap(), ident, lv, literals)
+        }
+        | NodeKind::ND_IDENT => {
+            let ident_id = blockstr_to_identid(node.str.clone(), node.block_str.clone());
+            match lv.get_val_offset_by_identid_recursively(ident_id) {
+                | Some(val) if val.storage != StorageClass::AUTO => Some((val.label, 0)),
+                | _ => None,
+            }
+        }
+        | NodeKind::ND_MEMBER => {
+            let obj = node.l.as_mut().unwrap();
+            let (label, off) = static_lvalue_addr(obj, ident, lv, literals)?;
+            Some((label, off + node.val as i64))
+        }
+        | NodeKind::ND_DEREF => {
+            static_addr(node.l.as_mut().unwrap(), ident, lv, literals)
+        }
+        | NodeKind::ND_PTR_DEREF => {
+            let ptr = node.ptr_deref_ident.as_mut().unwrap();
+            static_addr(ptr, ident, lv, literals)
+        }
+        | NodeKind::ND_COMPOUND_LIT if node.l.as_ref().unwrap().is_file_scope => {
+            let decl = node.l.as_mut().unwrap();
+            let label = decl.l.as_ref().unwrap().str.clone();
+            let typ = decl.decl_type.clone();
+            let (init, relocs) = match decl.r.as_mut() {
+                | Some(r) => build_static_init(r, &typ, ident, lv, literals),
+                | None => (vec![0; typ.size()], vec![]),
+            };
+            literals.push(StaticData {
+                label: label.clone(),
+                typ: typ.clone(),
+                align: typ.align(),
+                init: init,
+                is_global: false,
+                relocs: relocs,
+            });
+            Some((label, 0))
+        }
+        | _ => None,
+    }
+}
+
+// `(*fp)(x)`や`(*t[0])(x)`の様に、関数pointerをderefした式はpointerそのものとして扱う.
+fn strip_fn_deref(node: Box<Node>) -> Box<Node> {
+    match node.kind {
+        | NodeKind::ND_EXPR => strip_fn_deref(node.l.unwrap()),
+        | NodeKind::ND_PTR_DEREF
+            if FnSignature::from_fn_ptr(&node.ptr_deref_ident.as_ref().unwrap().typ)
+                .is_some() =>
+        {
+            node.ptr_deref_ident.unwrap()
+        }
+        | NodeKind::ND_DEREF
+            if FnSignature::from_fn_ptr(&node.l.as_ref().unwrap().typ).is_some() =>
+        {
+            node.l.unwrap()
+        }
+        | _ => node,
+    }
+}
+
+// pointer同士の暗黙の変換ができるかどうか.
+// 指す先のtypeが(型修飾子を除いて)同じか、どちらかが`void *`なら変換できる.
+fn is_compatible_ptr(from: &Type, to: &Type) -> bool {
+    let (from_base, to_base) = match (from, to) {
+        | (Type::PTR(f), Type::PTR(t)) => (f, t),
+        | _ => return false,
+    };
+    return from_base.unqual() == to_base.unqual()
+        || *from_base.unqual() == Type::VOID
+        || *to_base.unqual() == Type::VOID;
+}
+
+// pointerの代入や初期化、引数の受け渡しで、指す先の型修飾子が外れていないかcheckする.
+// 外れる変換(`const char *` -> `char *`)なら、posの位置を示して警告を出す.
+fn check_ptr_qualifiers(value: &mut Node, to: &Type, pos: usize) {
+    add_type(value);
+    let (from, to) = (value.typ.decay(), to.unqual());
+    let (fq, tq) = match (&from, to) {
+        | (Type::PTR(f), Type::PTR(t)) if is_compatible_ptr(&from, to) => {
+            (f.qualifiers(), t.qualifiers())
+        }
+        | _ => return,
+    };
+    if fq.is_subset_of(&tq) {
+        return;
+    }
+    let discarded: Vec<&str> = [
+        (fq.is_const && !tq.is_const, "const"),
+        (fq.is_volatile && !tq.is_volatile, "volatile"),
+        (fq.is_restrict && !tq.is_restrict, "restrict"),
+    ]
+    .iter()
+    .filter(|(d, _)| *d)
+    .map(|(_, name)| *name)
+    .collect();
+    eprintln!(
+        "{}: warning: conversion discards `{}` qualifier from pointer target type.",
+        display_location(pos),
+        discarded.join(" ")
+    );
+}
+
+// 代入や初期化の値を、代入先のtypeに合わせる.
+// 算術型同士は暗黙に変換し、pointerは指す先の型修飾子をcheckする.(posは警告で示す位置)
+fn assign_conv(value: &mut Box<Node>, to: &Type, pos: usize) {
+    check_ptr_qualifiers(value, to, pos);
+    // structは、同じtypeのstructの値だけを代入できる.
+    if (value.typ.is_struct() || to.is_struct()) && value.typ.unqual() != to.unqual() {
+        let name = |t: &Type| match t.unqual() {
+            | Type::STRUCT(id) => format!("`{}`", struct_name(*id)),
+            | t => format!("{:?}", t),
+        };
+        panic!(
+            "incompatible types when assigning to {} from {}.",
+            name(to),
+            name(&value.typ)
+        );
+    }
+    if is_value_conv(&value.typ, to) {
+        let v = std::mem::take(value);
+        *value = implicit_conv(v, to).unwrap();
+    }
+}
+
+// 代入や返り値で、値を代入先のtypeに変換するかどうか.
+// 算術型同士と、pointerから`_Bool`(NULLでなければ1)への変換.
+fn is_value_conv(from: &Type, to: &Type) -> bool {
+    return (from.is_arith() && to.is_arith())
+        || (from.decay().is_ptr() && *to.unqual() == Type::BOOL);
+}
+
+// voidの値を、値として使っていないかcheckする.
+fn check_not_void(node: &Option<Box<Node>>) {
+    if let Some(n) = node {
+        if n.typ == Type::VOID {
+            panic!("void value not ignored as it ought to be.");
+        }
+    }
+}
+
+// 浮動小数点型の演算では、両方のoperandを演算するtypeに変換しておく.
+// MEMO: 整数型は64bitに拡張してregisterに置くので変換しなくてよいが、
+//       浮動小数点数はbit表現が異なるので変換が必要.
+fn conv_flonum_operands(node: &mut Node) {
+    let typ =
+        usual_arith_conv(&node.l.as_ref().unwrap().typ, &node.r.as_ref().unwrap().typ);
+    if !typ.is_flonum() {
+        return;
+    }
+    for operand in [&mut node.l, &mut node.r] {
+        *operand = Some(implicit_conv(operand.take().unwrap(), &typ).unwrap());
+    }
+}
+
+// 式のnodeにtypeを付与する.
+// ND_IDENTのtypeは、read_node()で変数tableから付与されている前提.
+// 文のnodeは子nodeを辿るだけで、自身にはtypeを付与しない.
+pub fn add_type(node: &mut Node) {
+    for child in [
+        &mut node.l,
+        &mut node.r,
+        &mut node.if_node,
+        &mut node.elsif_node,
+        &mut node.else_node,
+        &mut node.for_node_first_assign,
+        &mut node.for_node_second_condition,
+        &mut node.for_node_third_expr,
+        &mut node.for_node_stmts,
+    ] {
+        if let Some(c) = child.as_mut() {
+            add_type(c);
+        }
+    }
+    for stmt in node.block_stmts.iter_mut() {
+        add_type(stmt);
+    }
+    for arg in node.fn_call_args.iter_mut() {
+        if let Some(v) = arg.val.as_mut() {
+            add_type(v);
+        }
+    }
+
+    match node.kind {
+        | NodeKind::ND_NUM => node.typ = Type::INT,
+        // 定義が見つからなかった関数はintを返すものとする.
+        | NodeKind::ND_FNCALL => {
+            for arg in node.fn_call_args.iter() {
+                check_not_void(&arg.val);
+            }
+            if node.typ == Type::None {
+                node.typ = Type::INT;
+            }
+        }
+        | NodeKind::ND_EXPR => node.typ = node.l.as_ref().unwrap().typ.clone(),
+        | NodeKind::ND_COMPOUND_LIT => node.typ = node.r.as_ref().unwrap().typ.clone(),
+        | NodeKind::ND_ASSIGN => {
+            check_not_void(&node.r);
+            node.typ = node.l.as_ref().unwrap().typ.clone();
+        }
+        | NodeKind::ND_DECL => check_not_void(&node.r),
+        | NodeKind::ND_RETURN | NodeKind::ND_IFCOND => check_not_void(&node.l),
+        | NodeKind::ND_FOR => check_not_void(&node.for_node_second_condition),
+        | NodeKind::ND_ADD | NodeKind::ND_SUB => {
+            check_not_void(&node.l);
+            check_not_void(&node.r);
+            let l_typ = node.l.as_ref().unwrap().typ.decay();
+            let r_typ = node.r.as_ref().unwrap().typ.decay();
+            node.typ = match (l_typ.is_ptr(), r_typ.is_ptr()) {
+                | (true, false) => l_typ,
+                | (false, true) => r_typ,
+                | (true, true) => Type::LONG,
+                | _ => usual_arith_conv(&l_typ, &r_typ),
+            };
+            conv_flonum_operands(node);
+        }
+        | NodeKind::ND_MUL | NodeKind::ND_DIV => {
+            check_not_void(&node.l);
+            check_not_void(&node.r);
+            node.typ = usual_arith_conv(
+                &node.l.as_ref().unwrap().typ,
+                &node.r.as_ref().unwrap().typ,
+            );
+            conv_flonum_operands(node);
+        }
+        // castのtypeはparse時に付与されているので、変換できるかだけcheckする.
+        | NodeKind::ND_CAST => {
+            let from = node.l.as_ref().unwrap().typ.decay();
+            if from == Type::Unknown || node.typ == Type::VOID {
+                return;
+            }
+            if !from.is_scalar() || !(node.typ.is_scalar() || node.typ == Type::VOID) {
+                panic!("invalid cast from {:?} to {:?}", from, node.typ);
+            }
+        }
+        | NodeKind::ND_EQ
+        | NodeKind::ND_NEQ
+        | NodeKind::ND_BT
+        | NodeKind::ND_BE
+        | NodeKind::ND_LT
+        | NodeKind::ND_LE => {
+            check_not_void(&node.l);
+            check_not_void(&node.r);
+            conv_flonum_operands(node);
+            node.typ = Type::INT;
+        }
+        | NodeKind::ND_VA_START
+        | NodeKind::ND_VA_ARG
+        | NodeKind::ND_VA_END
+        | NodeKind::ND_VA_COPY => {
+            // va_copyは、copy先とcopy元の両方がva_list.
+            for ap in node.l.iter().chain(node.r.iter()) {
+                if ap.typ != Type::PTR(Box::new(Type::VA_LIST)) {
+                    panic!("expected va_list, but got {:?}", ap.typ);
+                }
+            }
+            if node.kind == NodeKind::ND_VA_ARG && !node.typ.is_scalar() {
+                panic!("va_arg of type {:?} is not supported.", node.typ);
+            }
+        }
+        | NodeKind::ND_PTR_REF => {
+            let ident = node.ptr_ref_ident.as_mut().unwrap();
+            add_type(ident);
+            if node.typ == Type::None {
+                node.typ = Type::PTR(Box::new(ident.typ.clone()));
+            }
+        }
+        | NodeKind::ND_DEREF => {
+            let ptr = node.l.as_ref().unwrap().typ.decay();
+            node.typ = match ptr.base() {
+                | Some(base) if *base.unqual() == Type::VOID => {
+                    panic!("dereferencing `void *` pointer.")
+                }
+                | Some(base) => base.unqual().clone(),
+                | None => panic!("subscripted value is neither array nor pointer."),
+            };
+        }
+        // memberのtypeはread_nodeでdecl_typeに付与されている.
+        | NodeKind::ND_MEMBER => node.typ = node.decl_type.unqual().clone(),
+        | NodeKind::ND_PTR_DEREF => {
+            let ident = node.ptr_deref_ident.as_mut().unwrap();
+            add_type(ident);
+            node.typ = match &ident.typ {
+                | Type::PTR(base) if *base.unqual() == Type::VOID => {
+                    panic!("dereferencing `void *` pointer: {}", ident.str)
+                }
+                | Type::PTR(base) => base.unqual().clone(),
+                | _ => panic!("invalid pointer dereference: {}", ident.str),
+            };
+        }
+        | _ => (),
+    }
+}
+
+// lvalueの式が指すobjectのtype. 式のtypeと違い、型修飾子を残す.
+// MEMO: add_type済みのnodeを渡す.
+fn object_type(node: &Node, arg: &ReadNodeArgs) -> Type {
+    match node.kind {
+        | NodeKind::ND_IDENT => {
+            let ident_id = blockstr_to_identid(node.str.clone(), node.block_str.clone());
+            match arg
+                .local_variable
+                .get_val_offset_by_identid_recursively(ident_id)
+            {
+                | Some(val) => val.typ,
+                | None => node.typ.clone(),
+            }
+        }
+        | NodeKind::ND_DEREF => {
+            node.l.as_ref().unwrap().typ.decay().base().unwrap().clone()
+        }
+        | NodeKind::ND_PTR_DEREF => {
+            let ident = node.ptr_deref_ident.as_ref().unwrap();
+            ident.typ.base().unwrap().clone()
+        }
+        | NodeKind::ND_MEMBER => node.decl_type.clone(),
+        | NodeKind::ND_COMPOUND_LIT => node.l.as_ref().unwrap().decl_type.clone(),
+        | NodeKind::ND_EXPR => object_type(node.l.as_ref().unwrap(), arg),
+        | _ => node.typ.clone(),
+    }
+}
+
 fn read_node(node: &mut Node, arg: &mut ReadNodeArgs) {
     /*
        idnet node.
     */
     if node.kind == NodeKind::ND_IDENT {
+        if node.str == "__func__" {
+            declare_func_name(arg);
+        }
         // そのidentが作成されたblockを示す、block_strを入れる.
         // TODO: 多分使わなくなる
         node.block_str = arg.cur_block_str.clone();
@@ -258,12 +1473,17 @@ fn read_node(node: &mut Node, arg: &mut ReadNodeArgs) {
             .local_variable
             .get_val_offset_by_identid_recursively(ident_id.clone())
         {
-            node.typ = val.typ
+            // va_list(配列)は、式の中では先頭へのpointerになる.
+            // 変数の値のtypeは、型修飾子を取り除いたものになる.
+            node.typ = match val.typ.unqual() {
+                | Type::VA_LIST => Type::PTR(Box::new(Type::VA_LIST)),
+                | t => t.decay(),
+            };
+        } else if let Some(sig) = arg.fn_table.get(&node.str) {
+            // 変数でない関数名は、その関数のアドレスになる.
+            node.kind = NodeKind::ND_FN_ADDR;
+            node.typ = sig.to_fn_ptr();
         }
-
-        // TODO: 型を導入する時に改善/
-        arg.val_size += 8;
-
         return;
     }
     if node.kind == NodeKind::ND_BLOCK {
@@ -283,31 +1503,274 @@ fn read_node(node: &mut Node, arg: &mut ReadNodeArgs) {
         arg.cur_block_str = build_block_str(arg.depth, &arg.index);
         return;
     }
-    if node.kind == NodeKind::ND_NUM {
+    if node.kind == NodeKind::ND_NUM
+        || node.kind == NodeKind::ND_FNUM
+        || node.kind == NodeKind::ND_TYPEDEF
+        || node.kind == NodeKind::ND_STR
+    {
+        return;
+    }
+    // block scopeの_Static_assertは、sizeofなどを置き換えてから評価する.
+    if node.kind == NodeKind::ND_STATIC_ASSERT {
+        read_node(node.l.as_mut().unwrap(), arg);
+        if let Err(e) = check_static_assert(node) {
+            error_at(node.pos, e, "_Static_assert".len());
+        }
+        return;
+    }
+    // _Genericは、制御式のtypeに合う関連付けの式に置き換える.
+    // 制御式は値として読まれるので、配列はpointerに、型修飾子は取り除いたtypeで比べる.
+    if node.kind == NodeKind::ND_GENERIC {
+        let ctrl = node.l.as_mut().unwrap();
+        read_node(ctrl, arg);
+        add_type(ctrl);
+        let typ = ctrl.typ.decay().unqual().clone();
+        let assoc = node
+            .block_stmts
+            .iter()
+            .find(|a| a.decl_type != Type::None && *a.decl_type.unqual() == typ)
+            .or_else(|| node.block_stmts.iter().find(|a| a.decl_type == Type::None))
+            .unwrap_or_else(|| {
+                error_at(
+                    node.pos,
+                    format!("_Generic: no association matches type {:?}.", typ),
+                    "_Generic".len(),
+                )
+            })
+            .clone();
+        *node = assoc;
+        read_node(node, arg);
+        return;
+    }
+    // asm文の出力operandとメモリの制約を持つoperandは左辺値、即値の制約は定数に限る.
+    if node.kind == NodeKind::ND_ASM {
+        let n_out = node.val as usize;
+        for (i, operand) in node.block_stmts.iter_mut().enumerate() {
+            if operand.kind != NodeKind::ND_EXPR {
+                continue;
+            }
+            let constraint = operand.str.clone();
+            let c = asm_constraint_letter(&constraint);
+            if i < n_out && !constraint.starts_with(['=', '+']) {
+                panic!("asm: output operand constraint lacks `=`.");
+            }
+            // operandの式を包むND_EXPRは外しておく.
+            while operand.l.as_ref().unwrap().kind == NodeKind::ND_EXPR {
+                operand.l = operand.l.take().unwrap().l;
+            }
+            let expr = operand.l.as_mut().unwrap();
+            read_node(expr, arg);
+            add_type(expr);
+            match c {
+                | 'i' | 'n' => {
+                    let v = eval_const(expr).unwrap_or_else(|| {
+                        panic!("asm: impossible constraint \"{}\".", constraint)
+                    });
+                    *expr = Box::new(Node {
+                        kind: NodeKind::ND_NUM,
+                        val: v as i32,
+                        typ: Type::INT,
+                        ..Default::default()
+                    });
+                }
+                | d if d.is_ascii_digit() => {
+                    if i < n_out || d.to_digit(10).unwrap() as usize >= n_out {
+                        panic!("asm: matching constraint references invalid operand.");
+                    }
+                }
+                | _ => (),
+            }
+            let is_lvalue =
+                expr.kind == NodeKind::ND_IDENT || expr.kind == NodeKind::ND_DEREF;
+            if (i < n_out || c == 'm') && !is_lvalue {
+                panic!("asm: lvalue required in asm operand \"{}\".", constraint);
+            }
+        }
+        return;
+    }
+    if node.kind == NodeKind::ND_INIT_LIST {
+        for init in node.block_stmts.iter_mut() {
+            read_node(&mut init.l.as_mut().unwrap(), arg);
+        }
+        return;
+    }
+    // file scopeのcompound literalは変数を宣言せず、build_static_initで.dataに置く.
+    if node.kind == NodeKind::ND_COMPOUND_LIT && node.l.as_ref().unwrap().is_file_scope {
+        if let Some(init) = node.l.as_mut().unwrap().r.as_mut() {
+            read_node(init, arg);
+        }
         return;
     }
     if node.kind == NodeKind::ND_PTR_REF {
         read_node(&mut node.ptr_ref_ident.as_mut().unwrap(), arg);
+        let ident = node.ptr_ref_ident.as_ref().unwrap();
+        // `&fn`は`fn`と同じく関数のアドレス.
+        if ident.kind == NodeKind::ND_FN_ADDR {
+            *node = *node.ptr_ref_ident.take().unwrap();
+            return;
+        }
+        // `&x`のtypeは、xの型修飾子も含めたtypeへのpointer.
+        let ident_id = blockstr_to_identid(ident.str.clone(), ident.block_str.clone());
+        if let Some(val) = arg
+            .local_variable
+            .get_val_offset_by_identid_recursively(ident_id)
+        {
+            node.typ = Type::PTR(Box::new(val.typ));
+        }
+        return;
+    }
+    // sizeof exprは、exprのtypeのsizeに置き換える.
+    // 配列の変数(compound literalを含む)は、pointerになる前のtypeのsizeになる.
+    if node.kind == NodeKind::ND_SIZEOF {
+        let mut expr = node.l.as_mut().unwrap();
+        read_node(expr, arg);
+        add_type(expr);
+        while expr.kind == NodeKind::ND_EXPR || expr.kind == NodeKind::ND_COMPOUND_LIT {
+            expr = match expr.kind {
+                | NodeKind::ND_EXPR => expr.l.as_mut().unwrap(),
+                | _ => expr.r.as_mut().unwrap(),
+            };
+        }
+        let ident_id = blockstr_to_identid(expr.str.clone(), expr.block_str.clone());
+        let typ = match arg
+            .local_variable
+            .get_val_offset_by_identid_recursively(ident_id)
+        {
+            | Some(val) if expr.kind == NodeKind::ND_IDENT => val.typ,
+            | _ => expr.typ.clone(),
+        };
+        // 可変長配列のsizeは、実行時に計算した隠れた変数の値になる.
+        if let Type::VLA(_, size_var) = typ.unqual() {
+            *node = Node {
+                kind: NodeKind::ND_IDENT,
+                str: size_var.clone(),
+                ..Default::default()
+            };
+            read_node(node, arg);
+            return;
+        }
+        *node = Node {
+            kind: NodeKind::ND_NUM,
+            val: typ.size() as i32,
+            ..Default::default()
+        };
         return;
     }
     if node.kind == NodeKind::ND_PTR_DEREF {
         read_node(&mut node.ptr_deref_ident.as_mut().unwrap(), arg);
         return;
     }
+    // `&`のtypeは、左辺値の型修飾子も含めたtypeへのpointer.
+    if node.kind == NodeKind::ND_ADDR {
+        let obj = node.l.as_mut().unwrap();
+        read_node(obj, arg);
+        add_type(obj);
+        let is_lvalue = matches!(
+            obj.kind,
+            NodeKind::ND_DEREF
+                | NodeKind::ND_PTR_DEREF
+                | NodeKind::ND_MEMBER
+                | NodeKind::ND_COMPOUND_LIT
+        );
+        if !is_lvalue {
+            error_at(
+                node.pos,
+                String::from("lvalue required as unary `&` operand."),
+                1,
+            );
+        }
+        let typ = object_type(obj, arg);
+        if obj.kind == NodeKind::ND_MEMBER
+            && find_member(&obj.l.as_ref().unwrap().typ, &obj.str)
+                .unwrap()
+                .bit_width
+                .is_some()
+        {
+            error_at(
+                node.pos,
+                format!("cannot take address of bit-field `{}`.", obj.str),
+                1,
+            );
+        }
+        node.typ = Type::PTR(Box::new(typ));
+        return;
+    }
+
+    if node.kind == NodeKind::ND_RETURN {
+        if node.l.is_none() {
+            return;
+        }
+        if arg.ret_type == Type::VOID {
+            panic!("`return` with a value, in function returning void.");
+        }
+        read_node(&mut node.l.as_mut().unwrap(), arg);
+
+        // 算術型(とpointerから`_Bool`)の返り値は、関数の返り値のtypeに変換する.
+        add_type(node.l.as_mut().unwrap());
+        check_not_void(&node.l);
+        if is_value_conv(&node.l.as_ref().unwrap().typ, &arg.ret_type) {
+            node.l = Some(implicit_conv(node.l.take().unwrap(), &arg.ret_type).unwrap());
+        }
+        return;
+    }
 
     /*
        nodes that have next node in left side.
     */
     if node.kind == NodeKind::ND_EXPR
         || node.kind == NodeKind::ND_STMT
-        || node.kind == NodeKind::ND_RETURN
         || node.kind == NodeKind::ND_IFCOND
         || node.kind == NodeKind::ND_ELSE
         || node.kind == NodeKind::ND_STMT2
+        || node.kind == NodeKind::ND_CAST
+        || node.kind == NodeKind::ND_VA_ARG
+        || node.kind == NodeKind::ND_VA_END
+        || node.kind == NodeKind::ND_DEREF
     {
         read_node(&mut node.l.as_mut().unwrap(), arg);
         return;
     }
+    // `s.x`のtypeは、memberのtypeにstructの型修飾子を加えたもの.
+    // offsetはvalに入れる.
+    if node.kind == NodeKind::ND_MEMBER {
+        let obj = node.l.as_mut().unwrap();
+        read_node(obj, arg);
+        add_type(obj);
+        let typ = object_type(obj, arg);
+        let member = match typ.unqual() {
+            | Type::STRUCT(id) => find_member(&typ, &node.str).unwrap_or_else(|| {
+                let msg = if typ.is_incomplete() {
+                    format!("invalid use of incomplete type `{}`.", struct_name(*id))
+                } else {
+                    format!("no member named `{}` in `{}`.", node.str, struct_name(*id))
+                };
+                error_at(node.pos, msg, node.str.len())
+            }),
+            | _ => error_at(
+                node.pos,
+                format!(
+                    "request for member `{}` in something not a structure or union.",
+                    node.str
+                ),
+                node.str.len(),
+            ),
+        };
+        node.val = member.offset as i32;
+        node.decl_type = member.typ.qualify(typ.qualifiers());
+        return;
+    }
+    if node.kind == NodeKind::ND_VA_START {
+        if !arg.is_variadic {
+            panic!("`va_start` used in function with fixed arguments.");
+        }
+        read_node(&mut node.l.as_mut().unwrap(), arg);
+        return;
+    }
+    if node.kind == NodeKind::ND_VA_COPY {
+        read_node(node.l.as_mut().unwrap(), arg);
+        read_node(node.r.as_mut().unwrap(), arg);
+        return;
+    }
 
     /*
        irregular nodes that don't have next node neither in left nor right.
@@ -337,52 +1800,274 @@ fn read_node(node: &mut Node, arg: &mut ReadNodeArgs) {
             // 引数のそれぞれのNodeを展開する(ここでblock_strも付与される)
             read_node(&mut v.val.as_mut().unwrap(), arg)
         }
-        // TODO: ここでTypeを詰める
+        // 関数名が変数を指している場合は、その関数pointerを通して呼び出す.
+        let ident_id =
+            blockstr_to_identid(node.fn_name.clone(), arg.cur_block_str.clone());
+        if node.l.is_none()
+            && arg
+                .local_variable
+                .get_val_offset_by_identid_recursively(ident_id)
+                .is_some()
+        {
+            node.l = Some(Box::new(Node {
+                kind: NodeKind::ND_IDENT,
+                str: node.fn_name.clone(),
+                ..Default::default()
+            }));
+        }
+        let sig = match node.l.as_mut() {
+            | Some(callee) => {
+                read_node(callee, arg);
+                add_type(callee);
+                let callee = strip_fn_deref(node.l.take().unwrap());
+                let sig = FnSignature::from_fn_ptr(&callee.typ).unwrap_or_else(|| {
+                    panic!("called object is not a function: {:?}", callee.typ)
+                });
+                node.l = Some(callee);
+                sig
+            }
+            // prototype宣言も関数定義もない関数は、引数のcheckはせずintを返すものとする.
+            | None => match arg.fn_table.get(&node.fn_name) {
+                | Some(sig) => sig.clone(),
+                | None => return,
+            },
+        };
+        // 可変長引数の関数は、固定の引数以上の数の引数を受け取れる.
+        if node.fn_call_args.len() < sig.params.len()
+            || (!sig.is_variadic && node.fn_call_args.len() != sig.params.len())
+        {
+            panic!(
+                "wrong number of arguments to function {}: expected {}, but got {}",
+                node.fn_name,
+                sig.params.len(),
+                node.fn_call_args.len()
+            );
+        }
+        // 引数はそれぞれ仮引数のtypeに変換する.
+        // `...`に対応する引数は変換せずにそのまま渡す.
+        let fn_name = node.fn_name.clone();
+        for (i, v) in node.fn_call_args.iter_mut().enumerate() {
+            add_type(v.val.as_mut().unwrap());
+            check_not_void(&v.val);
+            if v.val.as_ref().unwrap().typ.is_struct() {
+                panic!("passing struct by value is not supported: {}", fn_name);
+            }
+            // `...`に渡すfloatは、doubleに格上げする.(default argument promotion)
+            if i >= sig.params.len() {
+                if *v.val.as_ref().unwrap().typ.unqual() == Type::FLOAT {
+                    let promoted = implicit_conv(v.val.take().unwrap(), &Type::DOUBLE);
+                    v.val = Some(promoted.unwrap());
+                }
+                v.typ = v.val.as_ref().unwrap().typ.decay();
+                continue;
+            }
+            check_ptr_qualifiers(v.val.as_mut().unwrap(), &sig.params[i], v.pos);
+            v.val = Some(implicit_conv(v.val.take().unwrap(), &sig.params[i]).unwrap_or_else(
+                |e| {
+                    panic!(
+                        "incompatible type for argument {} of {}: {}",
+                        i + 1,
+                        fn_name,
+                        e
+                    )
+                },
+            ));
+            v.typ = sig.params[i].clone();
+        }
+        node.typ = sig.ret_type;
         return;
     }
 
     /*
         read binary_node.
     */
+    // `a[i] = x`や`s.x = y`の様な、pointerの指す先やmemberへの代入.
+    if node.kind == NodeKind::ND_ASSIGN
+        && matches!(
+            node.l.as_ref().unwrap().kind,
+            NodeKind::ND_DEREF | NodeKind::ND_MEMBER
+        )
+    {
+        read_node(&mut node.l.as_mut().unwrap(), arg);
+        read_node(&mut node.r.as_mut().unwrap(), arg);
+        let lhs = node.l.as_mut().unwrap();
+        add_type(lhs);
+        let target = object_type(lhs, arg);
+        if target.is_const() {
+            error_at(
+                node.pos,
+                String::from("assignment of read-only location."),
+                1,
+            );
+        }
+        assign_conv(node.r.as_mut().unwrap(), &target, node.pos);
+        return;
+    }
     if node.kind == NodeKind::ND_ASSIGN {
         let block_str = build_block_str(arg.depth, &arg.index);
         let ident_id =
             blockstr_to_identid(node.l.as_ref().unwrap().str.clone(), block_str);
-        // Err checkのため
-        let _ = arg
+        let val = arg
             .local_variable
             .get_val_offset_by_identid_recursively(ident_id)
             .unwrap_or_else(|| {
                 println!("variable not found. {}", node.l.as_ref().unwrap().str);
                 panic!("");
             });
+        if val.typ.is_const() {
+            error_at(
+                node.pos,
+                format!(
+                    "assignment of read-only variable `{}`.",
+                    node.l.as_ref().unwrap().str
+                ),
+                1,
+            );
+        }
 
         read_node(&mut node.l.as_mut().unwrap(), arg);
         read_node(&mut node.r.as_mut().unwrap(), arg);
+        assign_conv(node.r.as_mut().unwrap(), &val.typ, node.pos);
         return;
     }
 
     if node.kind == NodeKind::ND_DECL {
-        let block_str = build_block_str(arg.depth, &arg.index);
+        // file scopeの変数は、どのblockの変数よりも外側(block_strなし)に置く.
+        let block_str = if node.is_file_scope {
+            String::new()
+        } else {
+            build_block_str(arg.depth, &arg.index)
+        };
         // TODO: declnにblockstrがひっついている構造
         node.block_str = block_str.clone();
+        let sym = node.l.as_ref().unwrap().str.clone();
         // Err checkのため
-        let _ = match arg.local_variable.try_new_val_offset(
-            node.l.as_ref().unwrap().str.clone(),
-            Type::Unknown,
-            block_str,
-        ) {
-            | Ok(v) => v,
-            | Err(_) => {
-                panic!("Symbol duplicated.")
+        let _ = match node.storage {
+            | StorageClass::AUTO => arg.local_variable.try_new_val_offset(
+                sym.clone(),
+                node.decl_type.clone(),
+                block_str.clone(),
+                node.decl_align,
+            ),
+            // file scopeの変数は、その名前のlabelで参照する.(実体はbuild_global_dataで作る)
+            | StorageClass::STATIC if node.is_file_scope => {
+                arg.local_variable.try_new_symbol(
+                    sym.clone(),
+                    node.decl_type.clone(),
+                    block_str.clone(),
+                    StorageClass::STATIC,
+                    sym.clone(),
+                )
             }
-        };
+            // static変数は.dataに置き、関数名とblock_strでfile内で一意なlabelを付ける.
+            | StorageClass::STATIC => {
+                let label = format!("{}.{}{}", arg.fn_name, sym, block_str);
+                let (init, relocs) = match node.r.as_mut() {
+                    | Some(r) => {
+                        read_node(r, arg);
+                        let mut data =
+                            std::mem::take(&mut arg.local_variable.static_data);
+                        let ident = node.l.as_ref().unwrap();
+                        let lv = &arg.local_variable;
+                        let init =
+                            build_static_init(r, &node.decl_type, ident, lv, &mut data);
+                        arg.local_variable.static_data = data;
+                        init
+                    }
+                    | None => (vec![0; node.decl_type.size()], vec![]),
+                };
+                arg.local_variable.static_data.push(StaticData {
+                    label: label.clone(),
+                    typ: node.decl_type.clone(),
+                    align: node.decl_align.unwrap_or(node.decl_type.align()),
+                    init: init,
+                    is_global: false,
+                    relocs: relocs,
+                });
+                arg.local_variable.try_new_symbol(
+                    sym.clone(),
+                    node.decl_type.clone(),
+                    block_str.clone(),
+                    StorageClass::STATIC,
+                    label,
+                )
+            }
+            | StorageClass::EXTERN => {
+                if node.r.is_some() {
+                    panic!("extern variable {} has an initializer.", sym);
+                }
+                arg.local_variable.try_new_symbol(
+                    sym.clone(),
+                    node.decl_type.clone(),
+                    block_str.clone(),
+                    StorageClass::EXTERN,
+                    sym.clone(),
+                )
+            }
+        }
+        .unwrap_or_else(|_| panic!("Symbol duplicated."));
+        for size_decl in node.block_stmts.iter_mut() {
+            read_node(size_decl, arg);
+        }
+        // static変数の初期化式は.dataに置くので、codeは生成しない.
+        if node.storage != StorageClass::AUTO {
+            node.r = None;
+            read_node(&mut node.l.as_mut().unwrap(), arg);
+            return;
+        }
         read_node(&mut node.l.as_mut().unwrap(), arg);
-        read_node(&mut node.r.as_mut().unwrap(), arg);
-
+        let pos = node.l.as_ref().unwrap().pos;
+        if let Some(r) = node.r.as_mut() {
+            read_node(r, arg);
+            if r.kind == NodeKind::ND_INIT_LIST {
+                // 初期化式の要素は、それぞれの要素のtypeに代入する.
+                for init in r.block_stmts.iter_mut() {
+                    let typ = init.typ.clone();
+                    assign_conv(init.l.as_mut().unwrap(), &typ, pos);
+                }
+            } else {
+                assign_conv(r, &node.decl_type, pos);
+            }
+        }
         return;
     }
     read_node(&mut node.l.as_mut().unwrap(), arg);
     read_node(&mut node.r.as_mut().unwrap(), arg);
+    if node.kind == NodeKind::ND_ADD || node.kind == NodeKind::ND_SUB {
+        scale_vla_offset(node, arg);
+    }
     return;
 }
+
+// 可変長配列の要素を指すpointerの加減算では、要素のsizeが実行時に決まる.
+// 整数側のoperandに、要素のsizeを持つ隠れた変数を掛けておく.
+// (codegenでは、VLAを指すpointerの加減算のscaleは行わない)
+fn scale_vla_offset(node: &mut Node, arg: &mut ReadNodeArgs) {
+    add_type(node.l.as_mut().unwrap());
+    add_type(node.r.as_mut().unwrap());
+    let size_var = |n: &Node| match n.typ.decay().base().map(|t| t.unqual().clone()) {
+        | Some(Type::VLA(_, size_var)) => Some(size_var),
+        | _ => None,
+    };
+    let (ptr_size, idx) = match (
+        size_var(node.l.as_ref().unwrap()),
+        size_var(node.r.as_ref().unwrap()),
+    ) {
+        | (Some(s), None) => (s, &mut node.r),
+        | (None, Some(s)) if node.kind == NodeKind::ND_ADD => (s, &mut node.l),
+        | (None, None) => return,
+        | _ => panic!("invalid operands of pointers to variable length arrays."),
+    };
+    let mut size = Box::new(Node {
+        kind: NodeKind::ND_IDENT,
+        str: ptr_size,
+        ..Default::default()
+    });
+    read_node(&mut size, arg);
+    *idx = Some(Box::new(Node {
+        kind: NodeKind::ND_MUL,
+        l: idx.take(),
+        r: Some(size),
+        ..Default::default()
+    }));
+}
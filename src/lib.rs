@@ -2,4 +2,5 @@ pub mod codegen;
 pub mod errors;
 pub mod intermediate_process;
 pub mod parse;
+pub mod preprocess;
 pub mod tokenize;
@@ -5,14 +5,16 @@ mod codegen;
 mod errors;
 mod intermediate_process;
 mod parse;
+mod preprocess;
 mod tokenize;
 use codegen::codegen;
 use intermediate_process::intermediate_process;
 use parse::{debug_functions, parse};
+use preprocess::{Preprocessor, DEFAULT_SYSTEM_INCLUDE_PATHS, RCC_INCLUDE_PATH};
 use tokenize::{debug_tokens, new_token_reader, tokenize, Token};
 
 use clap::{App, Arg};
-use std::{fs, io};
+use std::{fs, io, path::PathBuf};
 
 fn main() -> () {
     let matches = App::new("rcc")
@@ -31,9 +33,38 @@ fn main() -> () {
                 .short('d')
                 .help("print debug info to stdout."),
         )
+        .arg(
+            Arg::new("include")
+                .short('I')
+                .takes_value(true)
+                .multiple_occurrences(true)
+                .help("add directory to include search path."),
+        )
+        .arg(
+            Arg::new("define")
+                .short('D')
+                .takes_value(true)
+                .multiple_occurrences(true)
+                .help("define macro as `NAME` or `NAME=value`."),
+        )
+        .arg(
+            Arg::new("undef")
+                .short('U')
+                .takes_value(true)
+                .multiple_occurrences(true)
+                .help("undefine macro."),
+        )
+        .arg(
+            Arg::new("isystem")
+                .long("isystem")
+                .takes_value(true)
+                .multiple_occurrences(true)
+                .help("set directory to system include search path."),
+        )
         .get_matches();
 
     let mut source_input: String;
+    let mut source_path = String::from("<stdin>");
     let mut debug_flag = false;
 
     // sourceを取得
@@ -47,6 +78,7 @@ fn main() -> () {
             .value_of("SOURCE")
             .unwrap_or_else(|| panic!("Source is not specified."))
             .to_string();
+        source_path = path.clone();
         source_input = fs::read_to_string(path)?;
     }
     // debug flagの確認
@@ -54,6 +86,44 @@ fn main() -> () {
         debug_flag = true
     }
 
+    // #includeや#defineなどの指令を処理する.
+    // system include pathを指定しなければ、defaultのdirectoryから探す.
+    // rccに付属するheaderは、どちらの場合も最初に探す.
+    let paths = |name| {
+        matches
+            .values_of(name)
+            .map_or(vec![], |v| v.map(PathBuf::from).collect::<Vec<_>>())
+    };
+    let mut system_paths = paths("isystem");
+    if system_paths.is_empty() {
+        system_paths = DEFAULT_SYSTEM_INCLUDE_PATHS
+            .iter()
+            .map(PathBuf::from)
+            .collect();
+    }
+    system_paths.insert(0, PathBuf::from(RCC_INCLUDE_PATH));
+    let mut preprocessor = Preprocessor::new(paths("include"), system_paths);
+    // `-D`と`-U`はコマンドラインで指定された順に処理する.
+    let mut macro_opts = vec![];
+    for name in ["define", "undef"] {
+        if let (Some(values), Some(indices)) =
+            (matches.values_of(name), matches.indices_of(name))
+        {
+            for (value, index) in values.zip(indices) {
+                macro_opts.push((index, name, value));
+            }
+        }
+    }
+    macro_opts.sort_by_key(|(index, _, _)| *index);
+    for (_, name, value) in macro_opts {
+        if name == "define" {
+            preprocessor.define(value);
+        } else {
+            preprocessor.undef(value);
+        }
+    }
+    source_input = preprocessor.preprocess(&source_input, &source_path);
+
     // 末尾に終端文字を入れておく
     source_input.push('\0');
 
@@ -63,14 +133,14 @@ fn main() -> () {
 
     let mut token_reader = new_token_reader(token);
 
-    let mut functions = parse(&mut token_reader);
+    let mut program = parse(&mut token_reader);
 
-    debug_functions(debug_flag, &functions);
+    debug_functions(debug_flag, &program.functions);
 
-    functions = intermediate_process(functions);
+    program = intermediate_process(program);
 
     // generate assembly
-    codegen(functions);
+    codegen(program);
 
     ()
 }
@@ -1,11 +1,27 @@
 use crate::{
-    intermediate_process::FunctionLocalVariable,
-    tokenize::{TokenKind, TokenReader, Type},
+    intermediate_process::{
+        check_static_assert, eval_const, FunctionLocalVariable, StaticData,
+    },
+    tokenize::{
+        define_struct, get_struct_def, literal_bytes, Member, Token, TokenKind,
+        TokenReader, Type,
+    },
 };
 
+// parseの結果. 関数と、file scopeで宣言された変数.
+pub struct Program {
+    pub functions: Vec<Function>,
+    // file scopeの変数の宣言(ND_DECL). 同じ名前の宣言は1つにまとめてある.
+    pub globals: Vec<Node>,
+    // globalsを.data/.bssに置くdata. (intermediate_processで作る)
+    pub global_data: Vec<StaticData>,
+}
+
 #[derive(Clone)]
 pub struct Function {
     pub fn_name: String,
+    // 返り値のtype
+    pub ret_type: Type,
     // Root Function Node
     pub root_node: Node,
     // local変数だけのサイズ
@@ -16,22 +32,38 @@ pub struct Function {
     pub fn_args: Vec<FnArgs>,
     // 関数の引数だけのサイズ
     pub fn_args_size: usize,
+    // bodyを持たないprototype宣言かどうか.
+    pub is_prototype: bool,
+    // 引数の最後に`...`を持つ可変長引数の関数かどうか.
+    pub is_variadic: bool,
+    // staticな関数は、他のfileから参照できない.
+    pub is_static: bool,
 }
 impl Function {
     // parse_function の段階で判明しているものは引数に渡している
-    pub fn new(root_node: Node, fn_name: String, fn_args: Vec<FnArgs>) -> Function {
+    pub fn new(
+        root_node: Node,
+        fn_name: String,
+        ret_type: Type,
+        fn_args: Vec<FnArgs>,
+    ) -> Function {
         let mut args_size = 0;
-        for (_, arg) in fn_args.iter().cloned().enumerate() {
-            args_size += arg.typ.size();
+        // MEMO: 引数は型のsizeによらず、それぞれ8byteのslotに置かれる.
+        for _ in fn_args.iter() {
+            args_size += 8;
         }
         return Function {
             fn_name: fn_name,
+            ret_type: ret_type,
             fn_args: fn_args,
             fn_args_size: args_size,
             root_node: root_node,
             // TODO: calc lv from nodes.
             lv_size: 0,
             local_variable: FunctionLocalVariable::new(),
+            is_prototype: false,
+            is_variadic: false,
+            is_static: false,
         };
     }
 }
@@ -45,13 +77,16 @@ pub struct FnArgs {
     pub sym: String,
     pub typ: Type,
     pub val: Option<Box<Node>>,
+    // 診断で使う、引数の式のinputの先頭からの位置.
+    pub pos: usize,
 }
 impl FnArgs {
-    pub fn new_for_caller(typ: Type, val: Option<Box<Node>>) -> Self {
+    pub fn new_for_caller(typ: Type, val: Option<Box<Node>>, pos: usize) -> Self {
         return Self {
             sym: String::from(""), // Not use
             typ: typ,
             val: val,
+            pos: pos,
         };
     }
     pub fn new_for_callee(sym: String, typ: Type) -> Self {
@@ -59,6 +94,7 @@ impl FnArgs {
             sym: sym,
             typ: typ,
             val: None, // Not use
+            pos: 0,    // Not use
         };
     }
 }
@@ -71,8 +107,12 @@ pub struct Node {
     pub r: Option<Box<Node>>,
     // for num node. (should be 0 in other node.)
     pub val: i32,
+    // for floating point num node.
+    pub fval: f64,
     // for ident node. (should be "" in other node.)
     pub str: String,
+    // 診断で使う、inputの先頭からの位置.
+    pub pos: usize,
 
     // for if stmt
     pub if_node: Option<Box<Node>>,
@@ -101,6 +141,15 @@ pub struct Node {
 
     // 変数宣言nodeのtype
     pub decl_type: Type,
+    // 変数宣言nodeのstorage class
+    pub storage: StorageClass,
+    // `_Alignas`で指定された変数宣言nodeのalignment.
+    pub decl_align: Option<usize>,
+    // bit-fieldを初期化するND_INITの、bit幅とstorage unitの中でのbit位置.
+    pub bit_field: Option<(usize, usize)>,
+    // file scopeで定義された変数を、関数の中から参照するための宣言か.
+    // 実体はProgramのglobalsとして、.data/.bssに置く.
+    pub is_file_scope: bool,
 
     // &によるpointer 参照用
     pub ptr_ref_ident: Option<Box<Node>>,
@@ -123,7 +172,9 @@ impl Default for Node {
             l: None,
             r: None,
             val: 0,
+            fval: 0.0,
             str: String::new(),
+            pos: 0,
             if_node: None,
             elsif_node: None,
             else_node: None,
@@ -140,6 +191,10 @@ impl Default for Node {
             block_str: String::new(),
             ident_id: String::new(),
             decl_type: Type::None,
+            storage: StorageClass::AUTO,
+            decl_align: None,
+            bit_field: None,
+            is_file_scope: false,
             ptr_ref_ident: None,
             ptr_deref_ident: None,
             fn_type: Type::None,
@@ -150,11 +205,21 @@ impl Default for Node {
     }
 }
 
+// 変数の置き場所.
+// AUTOはstack上, STATICは.data上, EXTERNは他のfileで定義された変数.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StorageClass {
+    AUTO,
+    STATIC,
+    EXTERN,
+}
+
 #[allow(non_camel_case_types)]
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub enum NodeKind {
     ND_ROOT,
     ND_NUM,
+    ND_FNUM,
     ND_ADD,
     ND_SUB,
     ND_MUL,
@@ -182,6 +247,26 @@ pub enum NodeKind {
     ND_DECL,
     ND_PTR_REF,
     ND_PTR_DEREF,
+    ND_TYPEDEF,
+    ND_SIZEOF,
+    ND_CAST,
+    ND_STR,
+    ND_VA_START,
+    ND_VA_ARG,
+    ND_VA_END,
+    ND_VA_COPY,
+    ND_FN_ADDR,
+    ND_DEREF,
+    // `&s.x`や`&a[i]`の様な、識別子以外の左辺値のアドレス. lは左辺値の式.
+    ND_ADDR,
+    // `s.x`. lはstructの式、strはmemberの名前.
+    ND_MEMBER,
+    ND_INIT_LIST,
+    ND_INIT,
+    ND_COMPOUND_LIT,
+    ND_STATIC_ASSERT,
+    ND_GENERIC,
+    ND_ASM,
 }
 fn gen_expr(expr_node: Option<Box<Node>>, _: &mut TokenReader) -> Option<Box<Node>> {
     let node = Some(Box::new(Node {
@@ -212,6 +297,18 @@ fn gen_num_node(tok: &mut TokenReader) -> Option<Box<Node>> {
     );
 }
 
+// 浮動小数点数のliteralは、suffixで決まったtypeを持つ.
+fn gen_fnum_node(tok: &mut TokenReader, typ: Type) -> Option<Box<Node>> {
+    let node = Some(Box::new(Node {
+        kind: NodeKind::ND_FNUM,
+        typ: typ,
+        fval: tok.cur_tok().char.parse().unwrap(),
+        ..Default::default()
+    }));
+    tok.next();
+    return node;
+}
+
 fn gen_stmt(tok: &mut TokenReader, node: Option<Box<Node>>) -> Option<Box<Node>> {
     let node = Some(Box::new(Node {
         kind: NodeKind::ND_STMT,
@@ -229,6 +326,7 @@ fn gen_ident_node_with_unknown_typ(tok: &mut TokenReader) -> Option<Box<Node>> {
         kind: NodeKind::ND_IDENT,
         typ: Type::Unknown,
         str: String::from(tok.cur_tok().char),
+        pos: tok.cur_tok().input_pos(),
         ..Default::default()
     }));
     // MEMO: curを";"にして戻る.
@@ -241,6 +339,7 @@ fn gen_ident_node_with_type(tok: &mut TokenReader, typ: Type) -> Option<Box<Node
         kind: NodeKind::ND_IDENT,
         typ: typ,
         str: String::from(tok.cur_tok().char),
+        pos: tok.cur_tok().input_pos(),
         ..Default::default()
     }));
     // MEMO: curを";"にして戻る.
@@ -313,7 +412,8 @@ fn parse_fn_call(tok: &mut TokenReader, fn_name: String) -> Option<Box<Node>> {
         // return foo(&a);
         // let arg_node = parse_equality(tok).unwrap().as_ref().clone();
 
-        let arg = FnArgs::new_for_caller(Type::INT, parse_equality(tok));
+        let pos = tok.cur_input_pos();
+        let arg = FnArgs::new_for_caller(Type::INT, parse_equality(tok), pos);
         args.push(arg);
     }
 
@@ -322,39 +422,517 @@ fn parse_fn_call(tok: &mut TokenReader, fn_name: String) -> Option<Box<Node>> {
     return gen_fn_call_node(fn_name, args);
 }
 
+// deref = "*" unary
+// 識別子のderefはND_PTR_DEREF、`*t[0]`の様にそれ以外の式のderefはND_DEREFにする.
 fn gen_deref_node(tok: &mut TokenReader) -> Option<Box<Node>> {
-    let ident_node = gen_ident_node_with_unknown_typ(tok);
+    let operand = parse_unary(tok);
+    if operand.as_ref().unwrap().kind == NodeKind::ND_IDENT {
+        return Some(Box::new(Node {
+            kind: NodeKind::ND_PTR_DEREF,
+            ptr_deref_ident: operand,
+            ..Default::default()
+        }));
+    }
     return Some(Box::new(Node {
-        kind: NodeKind::ND_PTR_DEREF,
-        ptr_deref_ident: ident_node,
+        kind: NodeKind::ND_DEREF,
+        l: operand,
         ..Default::default()
     }));
 }
 
+// ref = "&" unary
+// 識別子のアドレスはND_PTR_REF、`&(struct P){1, 2}`の様にそれ以外の左辺値はND_ADDRにする.
 fn gen_ref_node(tok: &mut TokenReader) -> Option<Box<Node>> {
-    let ident_node = gen_ident_node_with_unknown_typ(tok);
+    let pos = tok.cur_input_pos();
+    let mut operand = parse_unary(tok).unwrap();
+    while operand.kind == NodeKind::ND_EXPR {
+        operand = operand.l.unwrap();
+    }
+    if operand.kind == NodeKind::ND_IDENT {
+        return Some(Box::new(Node {
+            kind: NodeKind::ND_PTR_REF,
+            ptr_ref_ident: Some(operand),
+            ..Default::default()
+        }));
+    }
+    return Some(Box::new(Node {
+        kind: NodeKind::ND_ADDR,
+        l: Some(operand),
+        pos: pos,
+        ..Default::default()
+    }));
+}
+
+fn gen_num_node_with_val(val: i32) -> Option<Box<Node>> {
+    return Some(Box::new(Node {
+        kind: NodeKind::ND_NUM,
+        val: val,
+        ..Default::default()
+    }));
+}
+
+// fn_ptr_declarator = "(" "*" &ident? array_dims ")" fn_ptr_params
+// 関数pointerの宣言子を読み、返り値のtypeから関数pointerのtypeを作る.
+// `int (*t[2])(int)`の様に名前の後に要素数があれば、関数pointerの配列になる.
+// 宣言子の名前も返す.(castの様に名前を省略した場合は空文字列)
+fn parse_fn_ptr_declarator(tok: &mut TokenReader, ret_type: Type) -> (Type, String) {
+    if !tok.expect("(") || tok.get_next_tok().char != "*" {
+        tok.error(
+            tok.cur_input_pos(),
+            String::from("expect `(*`, but not."),
+            tok.cur_tok_len(),
+        );
+    }
+    tok.next_nth_tok(2);
+    let mut name = String::new();
+    if tok.cur_tok().kind == TokenKind::IDENT {
+        name = tok.cur_tok().char;
+        tok.next();
+    }
+    // 要素数は関数pointerのtypeが決まってから読むので、位置だけ覚えて読み飛ばす.
+    let dims_pos = tok.cur;
+    while tok.expect("[") {
+        while !tok.expect("]") {
+            tok.next();
+        }
+        tok.next();
+    }
+    if !tok.expect(")") || tok.get_next_tok().char != "(" {
+        tok.error(
+            tok.cur_input_pos(),
+            String::from("expect `)(`, but not."),
+            tok.cur_tok_len(),
+        );
+    }
+    tok.next();
+    let fn_ptr = parse_fn_ptr_params(tok, ret_type);
+    let end_pos = tok.cur;
+    tok.cur = dims_pos;
+    let t = parse_array_dims(tok, fn_ptr, &mut vec![]);
+    tok.cur = end_pos;
+    return (t, name);
+}
+
+// fn_ptr_params = "(" ( "void" | type ( "," type )* ( "," "..." )? )? ")"
+// 引数のtypeのlistを読み、返り値のtypeから関数pointerのtypeを作る.
+// MEMO: 引数の名前は書けるが、読み飛ばす.
+fn parse_fn_ptr_params(tok: &mut TokenReader, ret_type: Type) -> Type {
+    if !tok.expect("(") {
+        tok.error(
+            tok.cur_input_pos(),
+            String::from("expect `(`, but not."),
+            tok.cur_tok_len(),
+        );
+    }
+    tok.next();
+
+    // `(void)`は引数なし.
+    if tok.cur_tok().kind == TokenKind::TYPE(Type::VOID) && tok.get_next_tok().char == ")"
+    {
+        tok.next();
+    }
+    let mut params = vec![];
+    let mut is_variadic = false;
+    while tok.cur_tok().char != ")" {
+        if tok.expect("...") {
+            is_variadic = true;
+            tok.next();
+            break;
+        }
+        let typ = match tok.try_get_type().unwrap_or_else(|e| panic!("Err: {}", e)) {
+            | Type::VA_LIST => Type::PTR(Box::new(Type::VA_LIST)),
+            | t => t,
+        };
+        // 引数のtypeの型修飾子は、関数のtypeには含めない.
+        params.push(typ.unqual().clone());
+        if tok.cur_tok().kind == TokenKind::IDENT {
+            tok.next();
+        }
+        if tok.expect(",") {
+            tok.next();
+        }
+    }
+    if !tok.expect(")") {
+        tok.error(
+            tok.cur_input_pos(),
+            String::from("expect `)`, but not."),
+            tok.cur_tok_len(),
+        );
+    }
+    tok.next();
+    return Type::PTR(Box::new(Type::FUNC(
+        Box::new(ret_type.unqual().clone()),
+        params,
+        is_variadic,
+    )));
+}
+
+// "(" type ")" を読んでtypeを返す.
+fn parse_paren_type(tok: &mut TokenReader) -> Type {
+    if !tok.expect("(") {
+        tok.error(
+            tok.cur_input_pos(),
+            String::from("expect `(`, but not."),
+            tok.cur_tok_len(),
+        );
+    }
+    let mut t = tok
+        .next_tok()
+        .try_get_type()
+        .unwrap_or_else(|e| panic!("Err: {}", e));
+    if tok.expect("(") {
+        t = parse_fn_ptr_declarator(tok, t).0;
+    } else {
+        let pos = tok.cur_input_pos();
+        let mut vla_sizes = vec![];
+        t = parse_array_dims(tok, t, &mut vla_sizes);
+        if !vla_sizes.is_empty() {
+            tok.error(
+                pos,
+                String::from("variable length array type name is not supported."),
+                1,
+            );
+        }
+    }
+    if !tok.expect(")") {
+        tok.error(
+            tok.cur_input_pos(),
+            String::from("expect `)`, but not."),
+            tok.cur_tok_len(),
+        );
+    }
+    tok.next();
+    return t;
+}
+
+// generic = "_Generic" "(" equality ( "," ( type | "default" ) ":" equality )+ ")"
+// どの式を選ぶかは、制御式のtypeが決まるintermediate_processで決める.
+// 関連付けはND_EXPRとしてblock_stmtsに詰め、そのtypeをdecl_typeに持つ.(defaultはNone)
+fn parse_generic(tok: &mut TokenReader) -> Option<Box<Node>> {
+    // 関連付けが見つからない時のerrorは、`_Generic`の位置に出す.
+    let pos = tok.cur_input_pos();
+    if !tok.next_tok().expect("(") {
+        tok.error(
+            tok.cur_input_pos(),
+            String::from("expect `(`, but not."),
+            tok.cur_tok_len(),
+        );
+    }
+    let ctrl = parse_equality(tok.next_tok());
+    let mut assocs = vec![];
+    while tok.expect(",") {
+        let t = if tok.next_tok().cur_tok().char == "default" {
+            tok.next();
+            Type::None
+        } else {
+            tok.try_get_type().unwrap_or_else(|e| panic!("Err: {}", e))
+        };
+        if !tok.expect(":") {
+            tok.error(
+                tok.cur_input_pos(),
+                String::from("expect `:`, but not."),
+                tok.cur_tok_len(),
+            );
+        }
+        assocs.push(Node {
+            kind: NodeKind::ND_EXPR,
+            l: parse_equality(tok.next_tok()),
+            decl_type: t,
+            ..Default::default()
+        });
+    }
+    if assocs.is_empty() || !tok.expect(")") {
+        tok.error(
+            tok.cur_input_pos(),
+            String::from("expect `,` or `)`, but not."),
+            tok.cur_tok_len(),
+        );
+    }
+    tok.next();
+    return Some(Box::new(Node {
+        kind: NodeKind::ND_GENERIC,
+        l: ctrl,
+        block_stmts: assocs,
+        pos: pos,
+        ..Default::default()
+    }));
+}
+
+// sizeof = "sizeof" "(" type ")" | "sizeof" compound_literal
+//        | "sizeof" "(" assign_or_equality ")" postfix | "sizeof" unary
+fn parse_sizeof(tok: &mut TokenReader) -> Option<Box<Node>> {
+    // `(`の次がtypeであれば、type nameのsizeof.
+    // `sizeof (type){...}`は、compound literalのsizeof.
+    if tok.expect("(") && tok.is_typename_nth(1) {
+        let t = parse_paren_type(tok);
+        if !tok.expect("{") {
+            return gen_num_node_with_val(t.size() as i32);
+        }
+        return Some(Box::new(Node {
+            kind: NodeKind::ND_SIZEOF,
+            l: parse_compound_literal(tok, t),
+            ..Default::default()
+        }));
+    }
+    // `sizeof(x = 5)`の様に、括弧の中は代入でもよい.
+    // 代入のtypeは代入先のtypeで、sizeofの中の式は評価されない.
+    if tok.expect("(") {
+        let node = parse_assign_or_equality(tok.next_tok());
+        if !tok.expect(")") {
+            tok.error(
+                tok.cur_input_pos(),
+                String::from("expect `)`, but not."),
+                tok.cur_tok_len(),
+            );
+        }
+        tok.next();
+        return Some(Box::new(Node {
+            kind: NodeKind::ND_SIZEOF,
+            l: parse_postfix(tok, node),
+            ..Default::default()
+        }));
+    }
+    // exprのsizeofは、exprのtypeが決まるintermediate_processで数値に置き換える.
+    return Some(Box::new(Node {
+        kind: NodeKind::ND_SIZEOF,
+        l: parse_unary(tok),
+        ..Default::default()
+    }));
+}
+
+// cast = "(" type ")" unary | compound_literal postfix
+fn parse_cast(tok: &mut TokenReader) -> Option<Box<Node>> {
+    let t = parse_paren_type(tok);
+    if tok.expect("{") {
+        let node = parse_compound_literal(tok, t);
+        return parse_postfix(tok, node);
+    }
+    // castの結果は値なので、型修飾子は意味を持たない.
+    let t = t.unqual().clone();
+    return Some(Box::new(Node {
+        kind: NodeKind::ND_CAST,
+        typ: t,
+        l: parse_unary(tok),
+        ..Default::default()
+    }));
+}
+
+// compound_literal = "(" type ")" initializer
+// 名前のない変数の宣言(l)と、その変数の参照(r)の組にする.
+// file scopeのliteralは、file scopeの変数と同じく.dataに置く.
+// MEMO: 変数名は、識別子に使えない`.`から始めて、literalのtokenの位置で一意にする.
+fn parse_compound_literal(tok: &mut TokenReader, t: Type) -> Option<Box<Node>> {
+    let name = format!(".compound_literal{}", tok.cur);
+    let is_file_scope = tok.typedef_table.is_file_scope();
+    let (t, init_list) = parse_init_list(tok, t);
+    let ident = Node {
+        kind: NodeKind::ND_IDENT,
+        typ: t.clone(),
+        str: name,
+        ..Default::default()
+    };
+    let decl = Node {
+        kind: NodeKind::ND_DECL,
+        l: Some(Box::new(ident.clone())),
+        r: init_list,
+        decl_type: t,
+        storage: if is_file_scope {
+            StorageClass::STATIC
+        } else {
+            StorageClass::AUTO
+        },
+        is_file_scope: is_file_scope,
+        ..Default::default()
+    };
+    return Some(Box::new(gen_binary_node(
+        NodeKind::ND_COMPOUND_LIT,
+        Some(Box::new(decl)),
+        Some(Box::new(ident)),
+    )));
+}
+
+// paren = "(" equality ")"
+fn parse_paren(tok: &mut TokenReader) -> Option<Box<Node>> {
+    let node = parse_equality(tok.next_tok());
+    if !tok.expect(")") {
+        tok.error(
+            tok.cur_input_pos(),
+            String::from("expect `)`, but not."),
+            tok.cur_tok_len(),
+        );
+    }
+    tok.next();
+    return node;
+}
+
+// string literalは`char *`として扱う.
+fn gen_str_node(tok: &mut TokenReader) -> Option<Box<Node>> {
+    let str = tok.cur_tok().char;
+    let node = Some(Box::new(Node {
+        kind: NodeKind::ND_STR,
+        typ: Type::ARRAY(Box::new(Type::CHAR), literal_bytes(&str).len() + 1),
+        str: str,
+        ..Default::default()
+    }));
+    tok.next();
+    return node;
+}
+
+// va_builtin = "va_start" "(" equality "," &ident ")"
+//            | "va_arg" "(" equality "," type ")"
+//            | "va_end" "(" equality ")"
+//            | "va_copy" "(" equality "," equality ")"
+// MEMO: `__builtin_`から始まる名前でも呼べる.
+fn parse_va_builtin(tok: &mut TokenReader) -> Option<Box<Node>> {
+    let name = tok.cur_tok().char;
+    let kind = match name.trim_start_matches("__builtin_") {
+        | "va_start" => NodeKind::ND_VA_START,
+        | "va_arg" => NodeKind::ND_VA_ARG,
+        | "va_copy" => NodeKind::ND_VA_COPY,
+        | _ => NodeKind::ND_VA_END,
+    };
+    let ap = parse_equality(tok.next_nth_tok(2));
+    let mut typ = Type::VOID;
+    let mut src = None;
+    if kind != NodeKind::ND_VA_END {
+        if !tok.expect(",") {
+            tok.error(
+                tok.cur_input_pos(),
+                format!("expect `,` in {}, but not.", name),
+                tok.cur_tok_len(),
+            );
+        }
+        tok.next();
+        if kind == NodeKind::ND_VA_ARG {
+            typ = tok
+                .try_get_type()
+                .unwrap_or_else(|e| panic!("Err: {}", e))
+                .unqual()
+                .clone();
+        } else if kind == NodeKind::ND_VA_COPY {
+            src = parse_equality(tok);
+        } else {
+            // 最後の固定引数は、引数の位置をcodegenで求めるので読み飛ばすだけ.
+            parse_equality(tok);
+        }
+    }
+    if !tok.expect(")") {
+        tok.error(
+            tok.cur_input_pos(),
+            format!("expect `)` in {}, but not.", name),
+            tok.cur_tok_len(),
+        );
+    }
+    tok.next();
     return Some(Box::new(Node {
-        kind: NodeKind::ND_PTR_REF,
-        ptr_ref_ident: ident_node,
+        kind: kind,
+        typ: typ,
+        l: ap,
+        r: src,
         ..Default::default()
     }));
 }
 
-// unary = &num | &ident | fn_call | ref | deref
+fn is_va_builtin(name: &str) -> bool {
+    match name.trim_start_matches("__builtin_") {
+        | "va_start" | "va_arg" | "va_end" | "va_copy" => true,
+        | _ => false,
+    }
+}
+
+// postfix = ( "[" equality "]" | "." &ident | "->" &ident | "(" fn_call_args )*
+// `a[i]`は`*(a + i)`、`p->x`は`(*p).x`として扱う.
+// `t[0](x)`や`(*fp)(x)`の様に、式の後に`(`が続く場合は関数pointerの呼び出し.
+fn parse_postfix(
+    tok: &mut TokenReader,
+    mut node: Option<Box<Node>>,
+) -> Option<Box<Node>> {
+    loop {
+        if tok.expect("[") {
+            let index = parse_equality(tok.next_tok());
+            if !tok.expect("]") {
+                tok.error(
+                    tok.cur_input_pos(),
+                    String::from("expect `]`, but not."),
+                    tok.cur_tok_len(),
+                );
+            }
+            tok.next();
+            node = Some(Box::new(Node {
+                kind: NodeKind::ND_DEREF,
+                l: Some(Box::new(gen_binary_node(NodeKind::ND_ADD, node, index))),
+                ..Default::default()
+            }));
+        } else if tok.expect(".") || tok.expect("->") {
+            if tok.expect("->") {
+                node = Some(Box::new(Node {
+                    kind: NodeKind::ND_DEREF,
+                    l: node,
+                    ..Default::default()
+                }));
+            }
+            tok.next();
+            if tok.cur_tok().kind != TokenKind::IDENT {
+                tok.error(
+                    tok.cur_input_pos(),
+                    String::from("expect member name, but not."),
+                    tok.cur_tok_len(),
+                );
+            }
+            node = Some(Box::new(Node {
+                kind: NodeKind::ND_MEMBER,
+                l: node,
+                str: tok.cur_tok().char,
+                pos: tok.cur_input_pos(),
+                ..Default::default()
+            }));
+            tok.next();
+        } else if tok.expect("(") {
+            let mut call = parse_fn_call(tok.next_tok(), String::new());
+            call.as_mut().unwrap().l = node;
+            node = call;
+        } else {
+            return node;
+        }
+    }
+}
+
+// unary = &num | &str | &ident postfix | fn_call postfix | va_builtin | ref | deref
+//       | sizeof | "_Alignof" "(" type ")" | generic | cast | paren postfix
 fn parse_unary(tok: &mut TokenReader) -> Option<Box<Node>> {
-    if tok.cur_tok().kind == TokenKind::NUM {
+    if tok.cur_tok().kind == TokenKind::STR {
+        return gen_str_node(tok);
+    } else if tok.cur_tok().kind == TokenKind::GENERIC {
+        return parse_generic(tok);
+    } else if tok.cur_tok().kind == TokenKind::SIZEOF {
+        return parse_sizeof(tok.next_tok());
+    } else if tok.cur_tok().kind == TokenKind::ALIGNOF {
+        let t = parse_paren_type(tok.next_tok());
+        return gen_num_node_with_val(t.align() as i32);
+    } else if tok.cur_tok().char == "(" && tok.is_typename_nth(1) {
+        return parse_cast(tok);
+    } else if tok.cur_tok().char == "(" {
+        let node = parse_paren(tok);
+        return parse_postfix(tok, node);
+    } else if tok.cur_tok().kind == TokenKind::NUM {
         return gen_num_node(tok);
+    } else if let TokenKind::FNUM(typ) = tok.cur_tok().kind {
+        return gen_fnum_node(tok, typ);
     } else if tok.cur_tok().char == "&" {
         return gen_ref_node(tok.next_tok());
     } else if tok.cur_tok().char == "*" {
         return gen_deref_node(tok.next_tok());
     } else if tok.cur_tok().kind == TokenKind::IDENT {
-        if tok.get_next_tok().char == "(" {
+        if tok.get_next_tok().char == "(" && is_va_builtin(&tok.cur_tok().char) {
+            return parse_va_builtin(tok);
+        } else if tok.get_next_tok().char == "(" {
             // 呼び出し先で、`(`の次を読める様に.
             let fn_name = tok.cur_tok().char;
-            return parse_fn_call(tok.next_nth_tok(2), fn_name);
+            let call = parse_fn_call(tok.next_nth_tok(2), fn_name);
+            return parse_postfix(tok, call);
         } else {
-            return gen_ident_node_with_unknown_typ(tok);
+            let node = gen_ident_node_with_unknown_typ(tok);
+            return parse_postfix(tok, node);
         }
     } else {
         tok.error(
@@ -426,14 +1004,15 @@ fn parse_expr(tok: &mut TokenReader) -> Option<Box<Node>> {
 }
 
 // assign = &ident ( "=" equality )*
+// MEMO: 代入のnodeのposは`=`の位置.
 fn parse_assign(tok: &mut TokenReader) -> Option<Box<Node>> {
     let mut node = gen_ident_node_with_unknown_typ(tok);
     if tok.expect("=") {
-        node = Some(Box::new(gen_binary_node(
-            NodeKind::ND_ASSIGN,
-            node,
-            parse_equality(tok.next_tok()),
-        )));
+        let pos = tok.cur_input_pos();
+        node = Some(Box::new(Node {
+            pos: pos,
+            ..gen_binary_node(NodeKind::ND_ASSIGN, node, parse_equality(tok.next_tok()))
+        }));
     } else {
     }
     return node;
@@ -624,12 +1203,57 @@ fn parse_forstmt(tok: &mut TokenReader) -> Option<Box<Node>> {
     return Some(node);
 }
 
-// return = "return" equality
+// return = "return" ( equality )?
 fn parse_return(tok: &mut TokenReader) -> Option<Box<Node>> {
-    let node = gen_return_node(parse_equality(tok.next_tok()));
+    // 値を返さないreturn.
+    if tok.next_tok().expect(";") {
+        return gen_return_node(None);
+    }
+    let node = gen_return_node(parse_equality(tok));
+    return node;
+}
+
+// assign_or_equality = assign | deref_assign | equality
+fn parse_assign_or_equality(tok: &mut TokenReader) -> Option<Box<Node>> {
+    if tok.cur_tok().kind == TokenKind::IDENT && tok.get_next_tok().char == "=" {
+        return parse_assign(tok);
+    }
+    let node = parse_equality(tok);
+    // `a[i] = x`や`s.x = y`、`(*p).x = y`の様な、配列の要素やmemberへの代入.
+    if tok.expect("=") {
+        return parse_deref_assign(tok, node);
+    }
     return node;
 }
 
+// deref_assign = ( deref | member ) "=" equality
+// 左辺はparse_equalityで読まれているので、ND_EXPRを外して代入先にする.
+fn parse_deref_assign(
+    tok: &mut TokenReader,
+    lhs: Option<Box<Node>>,
+) -> Option<Box<Node>> {
+    let mut lhs = lhs.unwrap();
+    while lhs.kind == NodeKind::ND_EXPR {
+        lhs = lhs.l.unwrap();
+    }
+    if lhs.kind != NodeKind::ND_DEREF && lhs.kind != NodeKind::ND_MEMBER {
+        tok.error(
+            tok.cur_input_pos(),
+            String::from("lvalue required as left operand of assignment."),
+            tok.cur_tok_len(),
+        );
+    }
+    let pos = tok.cur_input_pos();
+    return Some(Box::new(Node {
+        pos: pos,
+        ..gen_binary_node(
+            NodeKind::ND_ASSIGN,
+            Some(lhs),
+            parse_equality(tok.next_tok()),
+        )
+    }));
+}
+
 // equality = expr ( "==" expr | "!=" expr | "<=" expr | ">=" expr | ">" expr | "<" expr )*
 fn parse_equality(tok: &mut TokenReader) -> Option<Box<Node>> {
     let mut node = parse_expr(tok);
@@ -652,52 +1276,919 @@ fn parse_equality(tok: &mut TokenReader) -> Option<Box<Node>> {
     return node;
 }
 
-// declare = type ( * )? &ident "=" equality
+// declare = ( "static" | "extern" | alignas )* type ( * )?
+//           ( &ident array_dims | fn_ptr_declarator ) ( "=" ( equality | initializer ) )?
+//         | struct_type
+// MEMO: 宣言子のない`struct tag;`は、tagだけの宣言になる.
 // MEMO: typeより後ろはassign式と同じだが、コードジェネレータの都合で、
 // declareの中にassignを入れるようなことはしない.
 fn parse_declare(tok: &mut TokenReader) -> Option<Box<Node>> {
-    let t = tok.try_get_type().unwrap_or_else(|e| panic!("Err: {}", e));
-    if t.is_ptr() {
-        // pointerだったら tokenを(*)の分1つ進めておく
+    let mut storage = StorageClass::AUTO;
+    let mut align = None;
+    loop {
+        match tok.cur_tok().kind {
+            | TokenKind::STATIC => storage = StorageClass::STATIC,
+            | TokenKind::EXTERN => storage = StorageClass::EXTERN,
+            | TokenKind::ALIGNAS => {
+                align = Some(parse_alignas(tok.next_tok()));
+                continue;
+            }
+            | _ => break,
+        }
         tok.next();
     }
+    let mut t = tok.try_get_type().unwrap_or_else(|e| panic!("Err: {}", e));
+    let mut vla_sizes = vec![];
+    // tagの宣言はtypedefと同じく、codeを生成しない.
+    if t.is_struct() && tok.expect(";") {
+        return Some(Box::new(Node {
+            kind: NodeKind::ND_TYPEDEF,
+            decl_type: t,
+            ..Default::default()
+        }));
+    }
+    if t == Type::VOID {
+        tok.error(
+            tok.cur_input_pos(),
+            String::from("variable declared void."),
+            tok.cur_tok_len(),
+        );
+    }
 
-    // cur -> &type
-    let ident_node = gen_ident_node_with_type(tok.next_tok(), t.clone());
+    // cur -> &ident (関数pointerの場合は`(`)
+    let ident_node = if tok.expect("(") {
+        let (fn_ptr, name) = parse_fn_ptr_declarator(tok, t);
+        t = fn_ptr;
+        Some(Box::new(Node {
+            kind: NodeKind::ND_IDENT,
+            typ: t.clone(),
+            str: name,
+            ..Default::default()
+        }))
+    } else {
+        let mut node = gen_ident_node_with_type(tok, t.clone());
+        t = parse_array_dims(tok, t, &mut vla_sizes);
+        node.as_mut().unwrap().typ = t.clone();
+        node
+    };
+    // 不完全型の変数は、領域の大きさが分からないので定義できない.
+    // (extern変数は、他のfileで定義される)
+    if t.is_incomplete() && storage != StorageClass::EXTERN {
+        let ident = ident_node.as_ref().unwrap();
+        tok.error(
+            ident.pos,
+            format!("storage size of `{}` isn't known.", ident.str),
+            ident.str.len(),
+        );
+    }
+    // `_Alignas`では、typeより緩いalignmentは指定できない.
+    if let Some(align) = align {
+        if t.is_vla() || align < t.align() {
+            tok.error(
+                tok.cur_input_pos(),
+                format!("requested alignment {} is not allowed for {:?}.", align, t),
+                tok.cur_tok_len(),
+            );
+        }
+    }
+    // 同名のtypedefがあった場合、このscopeではこの変数で隠す.
+    tok.typedef_table
+        .push_var(ident_node.as_ref().unwrap().str.clone());
+    // 可変長配列は、sizeを計算する隠れた変数の宣言を先に行ってから領域を確保する.
+    if t.is_vla() {
+        if storage != StorageClass::AUTO || tok.expect("=") {
+            tok.error(
+                tok.cur_input_pos(),
+                String::from(
+                    "variable length array must be an auto variable without initializer.",
+                ),
+                tok.cur_tok_len(),
+            );
+        }
+        return Some(Box::new(Node {
+            kind: NodeKind::ND_DECL,
+            l: ident_node,
+            decl_type: t,
+            block_stmts: vla_sizes,
+            ..Default::default()
+        }));
+    }
+    // 初期化式のない宣言は、領域を確保するだけ.
     if !tok.expect("=") {
-        panic!("");
+        return Some(Box::new(Node {
+            kind: NodeKind::ND_DECL,
+            l: ident_node,
+            decl_type: t,
+            storage: storage,
+            decl_align: align,
+            ..Default::default()
+        }));
     }
-    let equality_node = parse_equality(tok.next_tok());
+    tok.next();
+    // `{`から始まる初期化式と、char配列のstring literalでの初期化.
+    let is_str_init = t.is_array() && tok.cur_tok().kind == TokenKind::STR;
+    let init_node = if tok.expect("{") || is_str_init {
+        let (typ, init_list) = parse_init_list(tok, t);
+        t = typ;
+        init_list
+    } else {
+        parse_equality(tok)
+    };
 
     return Some(Box::new(Node {
         kind: NodeKind::ND_DECL,
         l: ident_node,
-        r: equality_node,
+        r: init_node,
         decl_type: t,
+        storage: storage,
+        decl_align: align,
         ..Default::default()
     }));
 }
 
-// stmt = ( declare | assign | return | equality ) ";"
-fn parse_stmt(tok: &mut TokenReader) -> Option<Box<Node>> {
-    let mut node: Option<Box<Node>>;
-    match tok.cur_tok().kind {
-        | TokenKind::RETURN => {
-            node = parse_return(tok);
+// struct_body = "{" ( static_assert ";"
+//                   | alignas* type ( member_declarator | &ident? ":" equality ) ";" )* "}"
+// member_declarator = ( &ident array_dims | fn_ptr_declarator ) ( ":" equality )?
+// memberを読んで、structかunionの定義を完成させる.
+// `:`の後ろはbit-fieldの幅で、整数の定数式でなければならない.
+// structの最後のmemberは、要素数のない配列(flexible array member)にできる.
+pub fn parse_struct_body(tok: &mut TokenReader, id: usize) {
+    tok.next();
+    let mut members: Vec<Member> = vec![];
+    // flexible array memberの位置. その後ろにmemberがあればerrorにする.
+    let mut flexible: Option<(usize, String)> = None;
+    while !tok.expect("}") {
+        if tok.cur_tok().kind == TokenKind::STATICASSERT {
+            parse_static_assert_decl(tok);
+            continue;
         }
-        | TokenKind::TYPE(_) => {
-            node = parse_declare(tok);
+        if let Some((pos, name)) = flexible {
+            tok.error(
+                pos,
+                format!("flexible array member `{}` not at end of struct.", name),
+                name.len(),
+            );
         }
-        // equality or assign
-        | TokenKind::IDENT => {
-            if tok.get_next_tok().char == "=" {
-                node = parse_assign(tok);
-            } else {
-                node = parse_equality(tok);
+        let mut align = None;
+        while tok.cur_tok().kind == TokenKind::ALIGNAS {
+            align = Some(parse_alignas(tok.next_tok()));
+        }
+        let mut t = tok.try_get_type().unwrap_or_else(|e| panic!("Err: {}", e));
+        let pos = tok.cur_input_pos();
+        let mut name = String::new();
+        if tok.expect("(") {
+            let (fn_ptr, fn_ptr_name) = parse_fn_ptr_declarator(tok, t);
+            t = fn_ptr;
+            name = fn_ptr_name;
+        } else if tok.cur_tok().kind == TokenKind::IDENT {
+            name = tok.cur_tok().char;
+            tok.next();
+            if tok.expect("[") && tok.get_next_tok().char == "]" {
+                flexible = Some((pos, name.clone()));
             }
+            t = parse_array_dims(tok, t, &mut vec![]);
+        } else if !tok.expect(":") {
+            tok.error(
+                pos,
+                String::from("expect member name, but not."),
+                tok.cur_tok_len(),
+            );
         }
+        let mut bit_width = None;
+        if tok.expect(":") {
+            tok.next();
+            let width_pos = tok.cur_input_pos();
+            let width = parse_equality(tok);
+            let display_name = if name.is_empty() {
+                String::from("<anonymous>")
+            } else {
+                name.clone()
+            };
+            if !t.is_integer() {
+                tok.error(
+                    pos,
+                    format!("bit-field `{}` has invalid type.", display_name),
+                    name.len(),
+                );
+            }
+            let width = eval_const(width.as_ref().unwrap()).unwrap_or_else(|| {
+                tok.error(
+                    width_pos,
+                    format!(
+                        "bit-field `{}` width not an integer constant.",
+                        display_name
+                    ),
+                    1,
+                )
+            });
+            if width < 0 || width as usize > t.size() * 8 {
+                tok.error(
+                    width_pos,
+                    format!("width of `{}` exceeds its type.", display_name),
+                    1,
+                );
+            }
+            if width == 0 && !name.is_empty() {
+                tok.error(
+                    width_pos,
+                    format!("zero width for bit-field `{}`.", name),
+                    1,
+                );
+            }
+            bit_width = Some(width as usize);
+        }
+        if *t.unqual() == Type::VOID || t.is_incomplete() {
+            tok.error(
+                pos,
+                format!("field `{}` has incomplete type.", name),
+                name.len(),
+            );
+        }
+        if t.is_vla() {
+            tok.error(
+                pos,
+                format!("field `{}` has variable length array type.", name),
+                name.len(),
+            );
+        }
+        if !name.is_empty() && members.iter().any(|m| m.name == name) {
+            tok.error(pos, format!("duplicate member `{}`.", name), name.len());
+        }
+        // `_Alignas`では、typeより緩いalignmentは指定できない.
+        if let Some(a) = align {
+            if bit_width.is_some() {
+                tok.error(
+                    pos,
+                    format!("alignment specified for bit-field `{}`.", name),
+                    name.len(),
+                );
+            }
+            if a < t.align() {
+                tok.error(
+                    pos,
+                    format!("requested alignment {} is not allowed for `{}`.", a, name),
+                    name.len(),
+                );
+            }
+        }
+        if !tok.expect(";") {
+            tok.error(
+                tok.cur_input_pos(),
+                String::from("expect `;`, but not."),
+                tok.cur_tok_len(),
+            );
+        }
+        tok.next();
+        members.push(Member {
+            name: name,
+            align: align.unwrap_or(t.align()),
+            typ: t,
+            offset: 0,
+            bit_width: bit_width,
+            bit_offset: 0,
+        });
+    }
+    tok.next();
+    // flexible array memberは、他に名前のあるmemberを持つstructにだけ置ける.
+    if let Some((pos, name)) = flexible {
+        if get_struct_def(id).is_union {
+            tok.error(
+                pos,
+                format!("flexible array member `{}` in union.", name),
+                name.len(),
+            );
+        }
+        if !members.iter().any(|m| !m.name.is_empty() && m.name != name) {
+            tok.error(
+                pos,
+                format!(
+                    "flexible array member `{}` in a struct with no named members.",
+                    name
+                ),
+                name.len(),
+            );
+        }
+    }
+    define_struct(id, members);
+}
+
+// alignas = "_Alignas" "(" ( &num | type ) ")"
+// alignmentは2の累乗でなければならない.
+fn parse_alignas(tok: &mut TokenReader) -> usize {
+    if tok.is_typename_nth(1) {
+        return parse_paren_type(tok).align();
+    }
+    if !tok.expect("(") || tok.get_next_tok().kind != TokenKind::NUM {
+        tok.error(
+            tok.cur_input_pos(),
+            String::from("expect `(` and number, but not."),
+            tok.cur_tok_len(),
+        );
+    }
+    let align = tok.next_tok().cur_tok().value as usize;
+    if !align.is_power_of_two() {
+        tok.error(
+            tok.cur_input_pos(),
+            String::from("requested alignment is not a positive power of 2."),
+            tok.cur_tok_len(),
+        );
+    }
+    if !tok.next_tok().expect(")") {
+        tok.error(
+            tok.cur_input_pos(),
+            String::from("expect `)`, but not."),
+            tok.cur_tok_len(),
+        );
+    }
+    tok.next();
+    return align;
+}
+
+// array_dims = ( "[" ( &num | equality )? "]" )*
+// `int a[2][3]`は、ARRAY(ARRAY(INT, 3), 2)になる.
+// 要素数が整数の定数式でない次元と、それを要素に含む次元は可変長配列(VLA)になる.
+// VLAのsizeを計算する隠れた変数の宣言は、内側の次元から順にvla_sizesに詰める.
+fn parse_array_dims(
+    tok: &mut TokenReader,
+    base: Type,
+    vla_sizes: &mut Vec<Node>,
+) -> Type {
+    if !tok.expect("[") {
+        return base;
+    }
+    let pos = tok.cur;
+    tok.next();
+    let mut len = 0;
+    let mut len_expr = None;
+    if tok.cur_tok().kind == TokenKind::NUM && tok.get_next_tok().char == "]" {
+        len = tok.cur_tok().value as usize;
+        tok.next();
+    } else if !tok.expect("]") {
+        len_expr = parse_equality(tok);
+        // 整数の定数式なら、VLAではなく要素数の決まった配列になる.
+        if let Some(v) = eval_const(len_expr.as_ref().unwrap()) {
+            len = v as usize;
+            len_expr = None;
+        }
+    }
+    if !tok.expect("]") {
+        tok.error(
+            tok.cur_input_pos(),
+            String::from("expect `]`, but not."),
+            tok.cur_tok_len(),
+        );
+    }
+    tok.next();
+    let elem = parse_array_dims(tok, base, vla_sizes);
+    if len_expr.is_none() && !elem.is_vla() {
+        return Type::ARRAY(Box::new(elem), len);
+    }
+
+    // size = 要素数 * 要素のsize. 要素がVLAなら、そのsizeも隠れた変数から読む.
+    let gen_ident = |name: &String| {
+        Some(Box::new(Node {
+            kind: NodeKind::ND_IDENT,
+            str: name.clone(),
+            ..Default::default()
+        }))
+    };
+    let len_expr = len_expr.or_else(|| gen_num_node_with_val(len as i32));
+    let elem_size = match &elem {
+        | Type::VLA(_, size_var) => gen_ident(size_var),
+        | t => gen_num_node_with_val(t.size() as i32),
+    };
+    let name = format!(".vla_size{}", pos);
+    vla_sizes.push(Node {
+        kind: NodeKind::ND_DECL,
+        l: gen_ident(&name),
+        r: gen_equality_node(NodeKind::ND_MUL, len_expr, elem_size),
+        decl_type: Type::LONG,
+        ..Default::default()
+    });
+    return Type::VLA(Box::new(elem), name);
+}
+
+// 初期化式を読んでND_INIT_LISTにする.
+// 要素数を省略した配列のtypeは、初期化式の要素数で決めたものを返す.
+fn parse_init_list(tok: &mut TokenReader, t: Type) -> (Type, Option<Box<Node>>) {
+    let mut inits = vec![];
+    let count = parse_initializer(tok, &t, 0, &mut inits);
+    let t = match t.unqual() {
+        | Type::ARRAY(elem, 0) => {
+            Type::ARRAY(elem.clone(), count).qualify(t.qualifiers())
+        }
+        | _ => t,
+    };
+    let node = Node {
+        kind: NodeKind::ND_INIT_LIST,
+        block_stmts: inits,
+        ..Default::default()
+    };
+    return (t, Some(Box::new(node)));
+}
+
+fn gen_init_node(offset: usize, typ: &Type, expr: Option<Box<Node>>) -> Node {
+    return Node {
+        kind: NodeKind::ND_INIT,
+        val: offset as i32,
+        typ: typ.unqual().clone(),
+        l: expr,
+        ..Default::default()
+    };
+}
+
+// initializer = equality | &str | struct_initializer
+//             | "{" ( designation? initializer ( "," designation? initializer )* ","? )? "}"
+// designation = ( "[" &num "]" | "." &ident )+ "="
+// 初期化式を、変数の先頭からのoffsetとscalar値の組(ND_INIT)に分解してinitsに詰める.
+// 初期化されなかった部分は、0で埋められる.
+// 配列の場合は、初期化した要素数を返す.
+// MEMO: 内側の配列の`{}`は省略できる.(その場合は要素数か、次のdesignatorまで読む)
+// MEMO: `[2][1] =`の様に続くdesignatorは、`{}`を省略した要素の初期化式として読む.
+fn parse_initializer(
+    tok: &mut TokenReader,
+    typ: &Type,
+    offset: usize,
+    inits: &mut Vec<Node>,
+) -> usize {
+    let (elem, len) = match typ.unqual() {
+        | Type::ARRAY(elem, len) => (elem.as_ref(), *len),
+        | Type::STRUCT(id) => {
+            // 同じstructのcompound literalは、`{}`を省略した初期化式でなくその値で初期化する.
+            if tok.expect("(") && tok.is_typename_nth(1) && !defines_struct(tok, 1) {
+                let mut lookahead = tok.clone();
+                let lit_typ = parse_paren_type(&mut lookahead);
+                if lit_typ.unqual() == typ.unqual() && lookahead.expect("{") {
+                    inits.push(gen_init_node(offset, typ, parse_equality(tok)));
+                    return 1;
+                }
+            }
+            parse_struct_initializer(tok, *id, offset, inits);
+            return 1;
+        }
+        // scalarは`{}`で囲んでもよい.
         | _ => {
-            node = parse_equality(tok);
+            if tok.expect("{") {
+                parse_initializer(tok.next_tok(), typ, offset, inits);
+                if tok.expect(",") {
+                    tok.next();
+                }
+                if !tok.expect("}") {
+                    tok.error(
+                        tok.cur_input_pos(),
+                        String::from("expect `}`, but not."),
+                        tok.cur_tok_len(),
+                    );
+                }
+                tok.next();
+            } else {
+                inits.push(gen_init_node(offset, typ, parse_equality(tok)));
+            }
+            return 1;
+        }
+    };
+
+    // char配列は、string literalで初期化できる.(`{}`で囲んでもよい)
+    if elem.size() == 1
+        && tok.expect("{")
+        && tok.get_next_tok().kind == TokenKind::STR
+        && tok.tokens[tok.cur + 2].char == "}"
+    {
+        let count = parse_initializer(tok.next_tok(), typ, offset, inits);
+        tok.next();
+        return count;
+    }
+    // 要素数は、string literalの配列のtypeの要素数(終端文字を含む)になる.
+    // 要素数がちょうど文字数の場合は、終端文字を置かない.
+    if tok.cur_tok().kind == TokenKind::STR && elem.size() == 1 {
+        let str_node = gen_str_node(tok).unwrap();
+        let mut count = match str_node.typ {
+            | Type::ARRAY(_, n) => n,
+            | _ => unreachable!(),
+        };
+        if len > 0 {
+            count = count.min(len);
+        }
+        let bytes = literal_bytes(&str_node.str)
+            .into_iter()
+            .chain(std::iter::once(0));
+        for (i, b) in bytes.take(count).enumerate() {
+            let val = gen_num_node_with_val(b as i32);
+            inits.push(gen_init_node(offset + i, elem, val));
+        }
+        return count;
+    }
+
+    let has_brace = tok.expect("{");
+    if has_brace {
+        tok.next();
+    }
+    let (mut idx, mut count) = (0, 0);
+    let mut first = true;
+    loop {
+        if tok.expect("}") {
+            break;
+        }
+        if !first {
+            if !tok.expect(",") {
+                break;
+            }
+            // `{}`を省略した内側の配列は、要素数だけ読んだら残りを外側に任せる.
+            if !has_brace && (idx >= len || is_designator(&tok.get_next_tok())) {
+                break;
+            }
+            tok.next();
+            if tok.expect("}") {
+                break;
+            }
+        }
+        let can_designate = has_brace || first;
+        first = false;
+
+        if can_designate && tok.expect("[") {
+            if tok.get_next_tok().kind != TokenKind::NUM {
+                tok.error(
+                    tok.cur_input_pos(),
+                    String::from("array designator must be a number."),
+                    tok.cur_tok_len(),
+                );
+            }
+            idx = tok.get_next_tok().value as usize;
+            tok.next_nth_tok(2);
+            if !tok.expect("]") {
+                tok.error(
+                    tok.cur_input_pos(),
+                    String::from("expect `]`, but not."),
+                    tok.cur_tok_len(),
+                );
+            }
+            expect_designation_end(tok.next_tok());
+        }
+        if len > 0 && idx >= len {
+            tok.error(
+                tok.cur_input_pos(),
+                String::from("excess elements in array initializer."),
+                tok.cur_tok_len(),
+            );
+        }
+        parse_initializer(tok, elem, offset + idx * elem.size(), inits);
+        idx += 1;
+        count = count.max(idx);
+    }
+    if has_brace {
+        if !tok.expect("}") {
+            tok.error(
+                tok.cur_input_pos(),
+                String::from("expect `}`, but not."),
+                tok.cur_tok_len(),
+            );
+        }
+        tok.next();
+    }
+    return count;
+}
+
+// struct_initializer = "{" ( designation? initializer ( "," designation? initializer )* ","? )? "}"
+// designation = ( "." &ident | "[" &num "]" )+ "="
+// memberを宣言順に初期化する. 指定したmemberの後は、その次のmemberから続ける.
+// unionは、指定がなければ最初のmemberだけを初期化する.
+// MEMO: 内側のstructの`{}`は省略できる.(その場合はmemberの数か、次のdesignatorまで読む)
+fn parse_struct_initializer(
+    tok: &mut TokenReader,
+    id: usize,
+    offset: usize,
+    inits: &mut Vec<Node>,
+) {
+    let def = get_struct_def(id);
+    // 名前のないbit-fieldは初期化しない.
+    let members: Vec<Member> = def
+        .members
+        .unwrap()
+        .into_iter()
+        .filter(|m| !m.name.is_empty())
+        .collect();
+    let limit = if def.is_union { 1 } else { members.len() };
+    let has_brace = tok.expect("{");
+    if has_brace {
+        tok.next();
+    }
+    let mut idx = 0;
+    let mut first = true;
+    loop {
+        if tok.expect("}") {
+            break;
+        }
+        if !first {
+            if !tok.expect(",") {
+                break;
+            }
+            // `{}`を省略した内側のstructは、memberの数だけ読んだら残りを外側に任せる.
+            if !has_brace && (idx >= limit || is_designator(&tok.get_next_tok())) {
+                break;
+            }
+            tok.next();
+            if tok.expect("}") {
+                break;
+            }
+        }
+        let can_designate = has_brace || first;
+        first = false;
+
+        let mut designated = false;
+        if can_designate && tok.expect(".") {
+            let name = tok.get_next_tok().char;
+            idx = members
+                .iter()
+                .position(|m| m.name == name)
+                .unwrap_or_else(|| {
+                    tok.error(
+                        tok.get_next_tok().input_pos(),
+                        format!("unknown member `{}` in initializer.", name),
+                        name.len(),
+                    )
+                });
+            expect_designation_end(tok.next_nth_tok(2));
+            designated = true;
+        }
+        if !designated && idx >= limit {
+            tok.error(
+                tok.cur_input_pos(),
+                String::from("excess elements in struct initializer."),
+                tok.cur_tok_len(),
+            );
+        }
+        let m = &members[idx];
+        if m.typ.is_array() && m.typ.size() == 0 {
+            tok.error(
+                tok.cur_input_pos(),
+                format!("initialization of flexible array member `{}`.", m.name),
+                tok.cur_tok_len(),
+            );
+        }
+        let start = inits.len();
+        parse_initializer(tok, &m.typ, offset + m.offset, inits);
+        if let Some(width) = m.bit_width {
+            for init in inits[start..].iter_mut() {
+                init.bit_field = Some((width, m.bit_offset));
+            }
+        }
+        idx += 1;
+    }
+    if has_brace {
+        if !tok.expect("}") {
+            tok.error(
+                tok.cur_input_pos(),
+                String::from("expect `}`, but not."),
+                tok.cur_tok_len(),
+            );
+        }
+        tok.next();
+    }
+}
+
+// tokenがdesignatorの始まり(`.`か`[`)かどうか.
+fn is_designator(tok: &Token) -> bool {
+    return tok.char == "." || tok.char == "[";
+}
+
+// designatorの後の`=`を読む.
+// `.a.b =`や`[2][1] =`の様にdesignatorが続く場合は、残りを要素の初期化式として読むので
+// そのままにする.
+fn expect_designation_end(tok: &mut TokenReader) {
+    if is_designator(&tok.cur_tok()) {
+        return;
+    }
+    if !tok.expect("=") {
+        tok.error(
+            tok.cur_input_pos(),
+            String::from("expect `=`, but not."),
+            tok.cur_tok_len(),
+        );
+    }
+    tok.next();
+}
+
+// static_assert = "_Static_assert" "(" equality ( "," &str )? ")"
+// MEMO: nodeのposは`_Static_assert`の位置.
+// 条件は定数式で、file scopeではparse時に、block scopeではintermediate_processで評価する.
+fn parse_static_assert(tok: &mut TokenReader) -> Option<Box<Node>> {
+    let pos = tok.cur_input_pos();
+    if !tok.next_tok().expect("(") {
+        tok.error(
+            tok.cur_input_pos(),
+            String::from("expect `(`, but not."),
+            tok.cur_tok_len(),
+        );
+    }
+    let cond = parse_equality(tok.next_tok());
+    let mut msg = String::new();
+    if tok.expect(",") {
+        if tok.next_tok().cur_tok().kind != TokenKind::STR {
+            tok.error(
+                tok.cur_input_pos(),
+                String::from("expect string literal, but not."),
+                tok.cur_tok_len(),
+            );
+        }
+        msg = tok.cur_tok().char;
+        tok.next();
+    }
+    if !tok.expect(")") {
+        tok.error(
+            tok.cur_input_pos(),
+            String::from("expect `)`, but not."),
+            tok.cur_tok_len(),
+        );
+    }
+    tok.next();
+    return Some(Box::new(Node {
+        kind: NodeKind::ND_STATIC_ASSERT,
+        l: cond,
+        str: msg,
+        pos: pos,
+        ..Default::default()
+    }));
+}
+
+// file scopeやstructのmemberの並びにある`_Static_assert`は、読んだ時点で評価する.
+fn parse_static_assert_decl(tok: &mut TokenReader) {
+    let node = parse_static_assert(tok).unwrap();
+    if let Err(e) = check_static_assert(&node) {
+        tok.error(node.pos, e, "_Static_assert".len());
+    }
+    if !tok.expect(";") {
+        tok.error(
+            tok.cur_input_pos(),
+            String::from("expect ';', but not found."),
+            tok.cur_tok_len(),
+        );
+    }
+    tok.next();
+}
+
+// typedef = "typedef" type ( &ident | fn_ptr_declarator )
+fn parse_typedef(tok: &mut TokenReader) -> Option<Box<Node>> {
+    let mut t = tok
+        .next_tok()
+        .try_get_type()
+        .unwrap_or_else(|e| panic!("Err: {}", e));
+    let mut name = String::new();
+    if tok.expect("(") {
+        let (fn_ptr, fn_ptr_name) = parse_fn_ptr_declarator(tok, t);
+        t = fn_ptr;
+        name = fn_ptr_name;
+    } else if tok.cur_tok().kind == TokenKind::IDENT {
+        name = tok.cur_tok().char;
+        tok.next();
+    }
+    if name.is_empty() {
+        tok.error(
+            tok.cur_input_pos(),
+            String::from("expect typedef name, but not."),
+            tok.cur_tok_len(),
+        );
+    }
+    tok.typedef_table.push_typedef(name.clone(), t.clone());
+
+    // typedefはcodeを生成しないが、stmtとして扱うためにnodeを返す.
+    return Some(Box::new(Node {
+        kind: NodeKind::ND_TYPEDEF,
+        str: name,
+        decl_type: t,
+        ..Default::default()
+    }));
+}
+
+// asm = "asm" qualifier* "(" &str+ ( ":" asm_operands? ( ":" asm_operands?
+//       ( ":" ( &str ( "," &str )* )? )? )? )? ")"
+// asm_operands = &str "(" equality ")" ( "," &str "(" equality ")" )*
+// operandはND_EXPR(strに制約、lに式)として出力、入力の順にblock_stmtsに詰め、
+// その後ろにclobberをND_STRとして続ける. valは出力operandの数.
+// MEMO: operandのないbasic asmでは`%`を置き換えないので、`%%`にしておく.
+fn parse_asm(tok: &mut TokenReader) -> Option<Box<Node>> {
+    // `volatile`などの修飾子は、codeの生成に影響しない.
+    while tok.cur_tok().kind == TokenKind::QUALIFIER {
+        tok.next();
+    }
+    if !tok.expect("(") {
+        tok.error(
+            tok.cur_input_pos(),
+            String::from("expect `(`, but not."),
+            tok.cur_tok_len(),
+        );
+    }
+    if tok.next_tok().cur_tok().kind != TokenKind::STR {
+        tok.error(
+            tok.cur_input_pos(),
+            String::from("expect string literal, but not."),
+            tok.cur_tok_len(),
+        );
+    }
+    // 隣り合った文字列literalは連結する.
+    let mut template = String::new();
+    while tok.cur_tok().kind == TokenKind::STR {
+        template.push_str(&tok.cur_tok().char);
+        tok.next();
+    }
+    let mut node = Box::new(Node {
+        kind: NodeKind::ND_ASM,
+        ..Default::default()
+    });
+    if !tok.expect(":") {
+        template = template.replace('%', "%%");
+    }
+    // 出力operand、入力operand、clobberの順に`:`で区切られている.
+    for section in 0..3 {
+        if !tok.expect(":") {
+            break;
+        }
+        tok.next();
+        while tok.cur_tok().kind == TokenKind::STR {
+            let s = tok.cur_tok().char;
+            if section == 2 {
+                node.block_stmts.push(Node {
+                    kind: NodeKind::ND_STR,
+                    str: s,
+                    pos: tok.cur_input_pos(),
+                    ..Default::default()
+                });
+            } else {
+                if !tok.next_tok().expect("(") {
+                    tok.error(
+                        tok.cur_input_pos(),
+                        String::from("expect `(`, but not."),
+                        tok.cur_tok_len(),
+                    );
+                }
+                let expr = parse_equality(tok.next_tok());
+                if !tok.expect(")") {
+                    tok.error(
+                        tok.cur_input_pos(),
+                        String::from("expect `)`, but not."),
+                        tok.cur_tok_len(),
+                    );
+                }
+                node.block_stmts.push(Node {
+                    kind: NodeKind::ND_EXPR,
+                    l: expr,
+                    str: s,
+                    ..Default::default()
+                });
+                if section == 0 {
+                    node.val += 1;
+                }
+            }
+            tok.next();
+            if !tok.expect(",") {
+                break;
+            }
+            tok.next();
+        }
+    }
+    if !tok.expect(")") {
+        tok.error(
+            tok.cur_input_pos(),
+            String::from("expect `)`, but not."),
+            tok.cur_tok_len(),
+        );
+    }
+    tok.next();
+    node.str = template;
+    return Some(node);
+}
+
+// stmt = ( typedef | static_assert | asm | declare | assign | return | equality ) ";"
+fn parse_stmt(tok: &mut TokenReader) -> Option<Box<Node>> {
+    let mut node: Option<Box<Node>>;
+    match tok.cur_tok().kind {
+        | TokenKind::RETURN => {
+            node = parse_return(tok);
+        }
+        | TokenKind::TYPEDEF => {
+            node = parse_typedef(tok);
+        }
+        | TokenKind::STATICASSERT => {
+            node = parse_static_assert(tok);
+        }
+        | TokenKind::ASM => {
+            node = parse_asm(tok.next_tok());
+        }
+        | TokenKind::TYPE(_)
+        | TokenKind::QUALIFIER
+        | TokenKind::STRUCT
+        | TokenKind::UNION
+        | TokenKind::STATIC
+        | TokenKind::EXTERN
+        | TokenKind::ALIGNAS => {
+            node = parse_declare(tok);
+        }
+        // typedefで定義されたtype名から始まる場合は変数宣言.
+        | TokenKind::IDENT if tok.is_typename() => {
+            node = parse_declare(tok);
+        }
+        // equality or assign
+        | _ => {
+            node = parse_assign_or_equality(tok);
         }
     };
 
@@ -722,17 +2213,19 @@ fn parse_block(tok: &mut TokenReader) -> Option<Box<Node>> {
         ..Default::default()
     });
     let mut c = 0;
-    loop {
+    // block内で定義されたtypedefは、block内でのみ有効.
+    tok.typedef_table.enter_scope();
+    // MEMO: `{}`の様な空のblockも許可する.
+    while tok.cur_tok().char != "}" {
         let _node = parse_stmts(tok).unwrap().as_ref().clone();
         stmts.push(_node);
         c += 1;
-        if tok.cur_tok().char == "}" {
-            node.block_stmts = stmts;
-            node.block_stmts_len = c;
-            tok.next();
-            return Some(node);
-        }
     }
+    node.block_stmts = stmts;
+    node.block_stmts_len = c;
+    tok.typedef_table.leave_scope();
+    tok.next();
+    return Some(node);
 }
 
 // stmts2 = block | stmt
@@ -767,19 +2260,35 @@ fn parse_stmts(tok: &mut TokenReader) -> Option<Box<Node>> {
     return node;
 }
 
-// function = int ident "(" ( &type &ident "," )* ")" block
+// function = type ident "(" ( type &ident? "," )* ( "..." )? ")" ( block | ";" )
+// MEMO: `;`で終わる場合はprototype宣言で、引数名は省略できる.
 fn parse_function(tok: &mut TokenReader) -> Function {
-    let t = match tok.cur_tok().kind {
-        | TokenKind::TYPE(t) => t,
-        | _ => tok.error(
+    // `extern`は関数では何も意味を持たない.
+    let is_static = tok.cur_tok().kind == TokenKind::STATIC;
+    if is_static || tok.cur_tok().kind == TokenKind::EXTERN {
+        tok.next();
+    }
+    let mut t = match tok.try_get_type() {
+        | Ok(t) => t,
+        | Err(_) => tok.error(
             tok.cur_tok().input_pos(),
             String::from("Expected Type!!"),
             tok.cur_tok().len(),
         ),
     };
+    // `int (*get(void))(int)`の様に、関数pointerを返す関数.
+    // 返り値のtypeは、引数の後の`)`に続く引数のlistで決まる.
+    let returns_fn_ptr = tok.expect("(") && tok.get_next_tok().char == "*";
+    if returns_fn_ptr {
+        tok.next_nth_tok(2);
+    }
 
-    let fn_ident_node = gen_ident_node_with_type(tok.next_tok(), t);
+    let fn_ident_node = gen_ident_node_with_type(tok, t.clone());
     let fn_name = fn_ident_node.unwrap().as_ref().str.clone();
+    // structの値は、引数や返り値のレジスタの割り当てに対応していない.
+    if t.is_struct() {
+        panic!("returning struct by value is not supported: {}", fn_name);
+    }
 
     if tok.cur_tok().char != "(" {
         tok.error(
@@ -790,23 +2299,69 @@ fn parse_function(tok: &mut TokenReader) -> Function {
     }
     tok.next();
 
+    // 引数はfunction内のscopeに属する.
+    tok.typedef_table.enter_scope();
+
+    // `(void)`は引数なし.
+    if tok.cur_tok().kind == TokenKind::TYPE(Type::VOID) && tok.get_next_tok().char == ")"
+    {
+        tok.next();
+    }
+
     // let mut func_args: HashMap<String, Type> = HashMap::new();
     let mut func_args = vec![];
+    let mut is_variadic = false;
     while tok.cur_tok().char != ")" {
-        let typ;
+        let mut typ;
         let sym;
 
-        typ = tok.try_get_type().unwrap_or_else(|e| panic!("Err: {}", e));
-        if typ.is_ptr() {
+        // `...`は引数の最後にのみ置ける.
+        if tok.expect("...") {
+            is_variadic = true;
             tok.next();
+            if !tok.expect(")") {
+                tok.error(
+                    tok.cur_input_pos(),
+                    String::from("expect `)` after `...`, but not."),
+                    tok.cur_tok_len(),
+                );
+            }
+            break;
         }
-        tok.next();
-        if let TokenKind::IDENT = tok.cur_tok().kind {
+
+        typ = match tok.try_get_type().unwrap_or_else(|e| panic!("Err: {}", e)) {
+            // 配列であるva_listの引数は、pointerとして受け取る.
+            | Type::VA_LIST => Type::PTR(Box::new(Type::VA_LIST)),
+            | t => t,
+        };
+        if typ.is_struct() {
+            tok.error(
+                tok.cur_input_pos(),
+                String::from("passing struct by value is not supported."),
+                tok.cur_tok_len(),
+            );
+        }
+        if tok.expect("(") {
+            // 関数pointerの引数.
+            let (fn_ptr, name) = parse_fn_ptr_declarator(tok, typ);
+            typ = fn_ptr;
+            sym = name;
+            tok.typedef_table.push_var(sym.clone());
+        } else if let TokenKind::IDENT = tok.cur_tok().kind {
             sym = tok.cur_tok().char;
+            tok.typedef_table.push_var(sym.clone());
+            tok.next();
         } else {
-            panic!("aaaaaaaa")
+            // prototype宣言では引数名を省略できる.
+            sym = String::new();
+        }
+        // 配列の引数は、要素へのpointerとして受け取る.
+        if tok.expect("[") {
+            typ = match parse_array_dims(tok, typ, &mut vec![]) {
+                | Type::ARRAY(elem, _) | Type::VLA(elem, _) => Type::PTR(elem),
+                | t => t,
+            };
         }
-        tok.next();
 
         let arg = FnArgs::new_for_callee(sym, typ);
 
@@ -819,7 +2374,46 @@ fn parse_function(tok: &mut TokenReader) -> Function {
         }
     }
 
-    tok.next_nth_tok(2); // foo(){ -> この次を指す
+    tok.next();
+    if returns_fn_ptr {
+        if !tok.expect(")") {
+            tok.error(
+                tok.cur_input_pos(),
+                String::from("expect `)`, but not."),
+                tok.cur_tok_len(),
+            );
+        }
+        t = parse_fn_ptr_params(tok.next_tok(), t);
+    }
+
+    // prototype宣言
+    if tok.expect(";") {
+        tok.next();
+        tok.typedef_table.leave_scope();
+        let n = Node {
+            kind: NodeKind::ND_ROOT,
+            fn_name: fn_name.clone(),
+            fn_callee_args: func_args.clone(),
+            ..Default::default()
+        };
+        let mut function = Function::new(n, fn_name, t, func_args);
+        function.is_prototype = true;
+        function.is_variadic = is_variadic;
+        function.is_static = is_static;
+        return function;
+    }
+
+    if !tok.expect("{") {
+        tok.error(
+            tok.cur_input_pos(),
+            String::from("expect `{` or `;`, but not."),
+            tok.cur_tok_len(),
+        );
+    }
+    if let Some(arg) = func_args.iter().find(|arg| arg.sym.is_empty()) {
+        panic!("parameter name omitted in definition of {}: {:?}", fn_name, arg.typ);
+    }
+    tok.next(); // foo(){ -> この次を指す
 
     // MEMO: 純正のNodeを返すように.
     // MEMO: コード(Nodeが何もない時に、unwrap_or_elseがErrorになりそう.)
@@ -827,6 +2421,7 @@ fn parse_function(tok: &mut TokenReader) -> Function {
         .unwrap_or_else(|| panic!("No program input!!"))
         .as_ref()
         .clone();
+    tok.typedef_table.leave_scope();
 
     let n = Node {
         kind: NodeKind::ND_ROOT,
@@ -835,23 +2430,165 @@ fn parse_function(tok: &mut TokenReader) -> Function {
         fn_callee_args: func_args.clone(),
         ..Default::default()
     };
-    let function = Function::new(n, fn_name.clone(), func_args.clone());
+    let mut function = Function::new(n, fn_name.clone(), t, func_args.clone());
+    function.is_variadic = is_variadic;
+    function.is_static = is_static;
     return function;
 }
 
-// program = functions*
-fn parse_program(tok: &mut TokenReader) -> Vec<Function> {
+// file scopeの宣言が、関数の宣言(prototype宣言か関数定義)かどうか.
+fn is_function_decl(tok: &TokenReader) -> bool {
+    let mut t = tok.clone();
+    if t.cur_tok().kind == TokenKind::STATIC || t.cur_tok().kind == TokenKind::EXTERN {
+        t.next();
+    }
+    if defines_struct(&t, 0) || t.try_get_type().is_err() {
+        return false;
+    }
+    // 関数pointerを返す関数.
+    if t.expect("(") && t.get_next_tok().char == "*" {
+        t.next_nth_tok(2);
+    }
+    return t.cur_tok().kind == TokenKind::IDENT && t.get_next_tok().char == "(";
+}
+
+// curからoffset番目のtokenが、`struct tag {`の様にstructを定義するtypeの始まりかどうか.
+// MEMO: structの定義は全体で共有されるので、cloneしたTokenReaderで先読みしてはいけない.
+fn defines_struct(tok: &TokenReader, offset: usize) -> bool {
+    let kind = tok.get_next_nth_tok(offset).kind;
+    if kind != TokenKind::STRUCT && kind != TokenKind::UNION {
+        return false;
+    }
+    let next = tok.get_next_nth_tok(offset + 1);
+    return next.char == "{"
+        || (next.kind == TokenKind::IDENT
+            && tok.get_next_nth_tok(offset + 2).char == "{");
+}
+
+// program = ( function | typedef ";" | static_assert ";" | declare ";" )*
+// MEMO: functionにはprototype宣言も含まれる.
+// MEMO: file scopeの変数は、以降の関数の先頭で宣言されたものとして扱う.
+fn parse_program(tok: &mut TokenReader) -> Program {
     let mut func_vec: Vec<Function> = vec![];
+    let mut globals: Vec<Node> = vec![];
+    // 関数の先頭に置く、file scopeの変数を参照するための宣言.
+    let mut ref_decls: Vec<Node> = vec![];
     // continue read until EOF token found.
     while tok.cur_tok().kind != TokenKind::EOF {
-        func_vec.push(parse_function(tok))
+        // file scopeの_Static_assertは、ここで評価する.
+        if tok.cur_tok().kind == TokenKind::STATICASSERT {
+            parse_static_assert_decl(tok);
+            continue;
+        }
+        // file scopeのtypedef.
+        if tok.cur_tok().kind == TokenKind::TYPEDEF {
+            parse_typedef(tok);
+            if !tok.expect(";") {
+                tok.error(
+                    tok.cur_input_pos(),
+                    String::from("expect ';', but not found."),
+                    tok.cur_tok_len(),
+                );
+            }
+            tok.next();
+            continue;
+        }
+        // file scopeの変数宣言.
+        if !is_function_decl(tok) {
+            let pos = tok.cur_input_pos();
+            let decl = parse_declare(tok).unwrap();
+            if !tok.expect(";") {
+                tok.error(
+                    tok.cur_input_pos(),
+                    String::from("expect ';', but not found."),
+                    tok.cur_tok_len(),
+                );
+            }
+            tok.next();
+            // `struct tag;`は、tagを宣言するだけ.
+            if decl.kind == NodeKind::ND_TYPEDEF {
+                continue;
+            }
+            let name = decl.l.as_ref().unwrap().str.clone();
+            merge_global(&mut globals, *decl).unwrap_or_else(|e| tok.error(pos, e, 1));
+            // 以降の関数からは、関数の先頭で宣言されたものとして参照する.
+            let is_name = |n: &Node| n.l.as_ref().unwrap().str == name;
+            let decl = globals.iter().find(|g| is_name(g)).unwrap();
+            ref_decls.retain(|d| !is_name(d.l.as_ref().unwrap()));
+            ref_decls.push(Node {
+                kind: NodeKind::ND_STMT,
+                l: Some(Box::new(global_ref_decl(decl))),
+                ..Default::default()
+            });
+            continue;
+        }
+        let mut function = parse_function(tok);
+        let mut blocks = ref_decls.clone();
+        blocks.append(&mut function.root_node.fn_blocks);
+        function.root_node.fn_blocks = blocks;
+        func_vec.push(function)
+    }
+    return Program {
+        functions: func_vec,
+        globals: globals,
+        global_data: vec![],
+    };
+}
+
+// file scopeの変数の宣言を、globalsに加える.
+// 同じ名前の宣言は1つにまとめ、初期化式を持つ宣言(定義)と`static`を優先する.
+// 初期化式を持つ宣言が2つ以上あればErrを返す.
+fn merge_global(globals: &mut Vec<Node>, decl: Node) -> Result<(), String> {
+    let name = decl.l.as_ref().unwrap().str.clone();
+    let prev = match globals
+        .iter_mut()
+        .find(|g| g.l.as_ref().unwrap().str == name)
+    {
+        | Some(prev) => prev,
+        | None => {
+            globals.push(decl);
+            return Ok(());
+        }
+    };
+    if prev.decl_type != decl.decl_type {
+        return Err(format!("conflicting types for `{}`.", name));
+    }
+    if prev.r.is_some() && decl.r.is_some() {
+        return Err(format!("redefinition of `{}`.", name));
+    }
+    let storage = match (prev.storage, decl.storage) {
+        | (StorageClass::STATIC, _) | (_, StorageClass::STATIC) => StorageClass::STATIC,
+        | (StorageClass::EXTERN, s) | (s, StorageClass::EXTERN) => s,
+        | _ => StorageClass::AUTO,
+    };
+    if decl.r.is_some() {
+        *prev = decl;
     }
-    return func_vec;
+    prev.storage = storage;
+    return Ok(());
+}
+
+// file scopeの変数を関数の中から参照するための宣言.
+// externな変数は他のfileで定義されたものとして、それ以外はこのfileのsymbolとして参照する.
+fn global_ref_decl(decl: &Node) -> Node {
+    let is_extern = decl.storage == StorageClass::EXTERN;
+    return Node {
+        kind: NodeKind::ND_DECL,
+        l: decl.l.clone(),
+        decl_type: decl.decl_type.clone(),
+        storage: if is_extern {
+            StorageClass::EXTERN
+        } else {
+            StorageClass::STATIC
+        },
+        is_file_scope: !is_extern,
+        ..Default::default()
+    };
 }
 
-// generate several nodes, and return Function.
+// generate several nodes, and return Program.
 // node = program
-pub fn parse(tok: &mut TokenReader) -> Vec<Function> {
+pub fn parse(tok: &mut TokenReader) -> Program {
     // TODO: ini tok要る?
     consume_initial_tok(tok);
     return parse_program(tok);
@@ -890,8 +2627,20 @@ pub fn debug_nodes(nodes: &Vec<Node>) {
 pub fn read_node(node: &Node, depth: &mut usize) {
     print_node_info(node, depth);
 
-    // for ND_NUM & ND_IDENT.
-    if node.kind == NodeKind::ND_NUM || node.kind == NodeKind::ND_IDENT {
+    // for ND_NUM & ND_FNUM & ND_IDENT & ND_TYPEDEF & ND_STR & ND_FN_ADDR & ND_INIT_LIST.
+    if node.kind == NodeKind::ND_NUM
+        || node.kind == NodeKind::ND_FNUM
+        || node.kind == NodeKind::ND_INIT_LIST
+        || node.kind == NodeKind::ND_FN_ADDR
+        || node.kind == NodeKind::ND_IDENT
+        || node.kind == NodeKind::ND_TYPEDEF
+        || node.kind == NodeKind::ND_STR
+    {
+        return;
+    }
+
+    // `return;`
+    if node.kind == NodeKind::ND_RETURN && node.l.is_none() {
         return;
     }
 
@@ -899,6 +2648,16 @@ pub fn read_node(node: &Node, depth: &mut usize) {
     if node.kind == NodeKind::ND_EXPR
         || node.kind == NodeKind::ND_STMT
         || node.kind == NodeKind::ND_RETURN
+        || node.kind == NodeKind::ND_SIZEOF
+        || node.kind == NodeKind::ND_CAST
+        || node.kind == NodeKind::ND_VA_START
+        || node.kind == NodeKind::ND_VA_ARG
+        || node.kind == NodeKind::ND_VA_END
+        || node.kind == NodeKind::ND_DEREF
+        || node.kind == NodeKind::ND_ADDR
+        || node.kind == NodeKind::ND_INIT
+        || node.kind == NodeKind::ND_STATIC_ASSERT
+        || node.kind == NodeKind::ND_GENERIC
     {
         *depth += 1;
         read_node(node.l.as_ref().unwrap(), depth);
@@ -968,6 +2727,15 @@ pub fn read_node(node: &Node, depth: &mut usize) {
         return;
     }
 
+    if node.kind == NodeKind::ND_ASM {
+        *depth += 1;
+        for operand in node.block_stmts.iter() {
+            read_node(operand, depth);
+        }
+        *depth -= 1;
+        return;
+    }
+
     /*
         read binary_node.
     */
@@ -983,9 +2751,15 @@ fn print_node_info(node: &Node, depth: &mut usize) {
         | NodeKind::ND_NUM => {
             println!("kind: {:?}, val: {}", node.kind, node.val);
         }
+        | NodeKind::ND_FNUM => {
+            println!("kind: {:?}, fval: {}", node.kind, node.fval);
+        }
         | NodeKind::ND_IDENT => {
             println!("kind: {:?}, str: {}", node.kind, node.str)
         }
+        | NodeKind::ND_STR => {
+            println!("kind: {:?}, str: {:?}", node.kind, node.str)
+        }
         | NodeKind::ND_FNCALL => {
             println!("kind: {:?}, fn_name: {}", node.kind, node.fn_name)
         }
@@ -0,0 +1,1255 @@
+use crate::errors::add_line_map;
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fs,
+    path::{Path, PathBuf},
+};
+
+// includeのnestの上限.(gccと同じ)
+const MAX_INCLUDE_DEPTH: usize = 200;
+
+// `#include <...>`を探すdefaultのdirectory.
+pub const DEFAULT_SYSTEM_INCLUDE_PATHS: [&str; 3] = [
+    "/usr/local/include",
+    "/usr/include/x86_64-linux-gnu",
+    "/usr/include",
+];
+
+// rccに付属する`stddef.h`などのheaderを置いたdirectory. system include pathより先に探す.
+pub const RCC_INCLUDE_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/include");
+
+// 複数文字のpunctuator. 長いものから順に試す.
+const PUNCTS: [&str; 23] = [
+    "<<=", ">>=", "...", "##", "->", "++", "--", "<<", ">>", "<=", ">=", "==", "!=",
+    "&&", "||", "+=", "-=", "*=", "/=", "%=", "&=", "|=", "^=",
+];
+
+// 行の継続(`\`+改行)と注釈を取り除いた、論理的な1行.
+struct Line {
+    // 行が始まる、元のfileでの行番号.(1始まり)
+    line_no: usize,
+    text: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum PPTokenKind {
+    IDENT,
+    NUM,
+    // 文字列と文字のliteral.
+    STR,
+    PUNCT,
+}
+
+// macroの展開で使うtoken.
+#[derive(Clone, Debug)]
+struct PPToken {
+    kind: PPTokenKind,
+    text: String,
+    // 直前に空白があるか.
+    space: bool,
+    line_no: usize,
+    // このtokenを生んだmacroの名前.(hide set)
+    // これらのmacroは、このtokenからは再び展開しない.
+    hideset: HashSet<String>,
+}
+impl PPToken {
+    fn is_punct(&self, s: &str) -> bool {
+        return self.kind == PPTokenKind::PUNCT && self.text == s;
+    }
+    // `##`でつなぐ引数が空の時に置く、空のtoken.(placemarker)
+    fn placemarker() -> Self {
+        return PPToken {
+            kind: PPTokenKind::PUNCT,
+            text: String::new(),
+            space: false,
+            line_no: 0,
+            hideset: HashSet::new(),
+        };
+    }
+}
+
+#[derive(Clone)]
+struct Macro {
+    // 関数形式macroの引数名. 可変長引数は`__VA_ARGS__`として末尾に置く.
+    // object-likeなmacroではNone.
+    params: Option<Vec<String>>,
+    is_variadic: bool,
+    body: Vec<PPToken>,
+}
+
+// 処理中のfileの状態.
+struct FileState {
+    path: PathBuf,
+    // `__FILE__`やエラーで表示するfile名. `#line`で変えられる.
+    name: String,
+    line_no: usize,
+    // `#line`で指定した行番号と、実際の行番号の差.
+    line_offset: i64,
+}
+
+// `#if`から`#endif`までの、条件付きのgroupの状態.
+struct Cond {
+    // 今の分岐を読んでいるか.
+    active: bool,
+    // これまでの分岐のどれかを読んだか. 読んでいれば、以降の分岐は読まない.
+    taken: bool,
+    in_else: bool,
+    // `#if`の行番号.(エラー表示用)
+    line_no: usize,
+}
+
+// 定義済みのmacro.
+// MEMO: 整数のsuffixはtokenizeできないので、`__STDC_VERSION__`には`L`を付けない.
+const PREDEFINED_MACROS: [&str; 11] = [
+    "__STDC__ 1",
+    "__STDC_VERSION__ 201112",
+    "__STDC_HOSTED__ 1",
+    "__x86_64__ 1",
+    "__x86_64 1",
+    "__linux__ 1",
+    "__unix__ 1",
+    "__LP64__ 1",
+    "__rcc__ 1",
+    // 展開する時に値を決める.
+    "__FILE__",
+    "__LINE__",
+];
+
+// tokenizeの前に、`#`で始まる指令を処理してsourceを書き換える.
+pub struct Preprocessor {
+    // `-I`で指定されたdirectory.
+    include_paths: Vec<PathBuf>,
+    system_include_paths: Vec<PathBuf>,
+    // `#pragma once`が書かれたfile.
+    pragma_once: HashSet<PathBuf>,
+    // 全体がinclude guardで囲まれたfileと、そのguardのmacro名.
+    include_guards: HashMap<PathBuf, String>,
+    macros: HashMap<String, Macro>,
+    // 処理中のfile. 末尾が現在のfile.
+    file_stack: Vec<FileState>,
+}
+impl Preprocessor {
+    pub fn new(include_paths: Vec<PathBuf>, system_include_paths: Vec<PathBuf>) -> Self {
+        let mut pp = Preprocessor {
+            include_paths: include_paths,
+            system_include_paths: system_include_paths,
+            pragma_once: HashSet::new(),
+            include_guards: HashMap::new(),
+            macros: HashMap::new(),
+            file_stack: vec![],
+        };
+        for def in PREDEFINED_MACROS.iter() {
+            pp.define_macro(def);
+        }
+        return pp;
+    }
+
+    // pathはinputのfile名で、`#include "..."`はそのdirectoryから探す.
+    pub fn preprocess(&mut self, input: &str, path: &str) -> String {
+        let mut out = String::new();
+        self.process_file(input, Path::new(path), &mut out);
+        return out;
+    }
+
+    // `-D NAME=value`. valueを省略した場合は1になる.
+    pub fn define(&mut self, def: &str) {
+        let def = match def.split_once('=') {
+            | Some((name, value)) => format!("{} {}", name, value),
+            | None => format!("{} 1", def),
+        };
+        self.define_macro(&def);
+    }
+
+    // `-U NAME`.
+    pub fn undef(&mut self, name: &str) {
+        self.macros.remove(name);
+    }
+
+    fn cur_line(&self) -> usize {
+        return self.file_stack.last().map_or(0, |f| f.line_no);
+    }
+
+    // `#line`を反映した、line_noの行番号.
+    fn presumed_line(&self, line_no: usize) -> i64 {
+        return line_no as i64 + self.file_stack.last().map_or(0, |f| f.line_offset);
+    }
+
+    fn error(&self, message: String) -> ! {
+        self.error_at(self.cur_line(), message);
+    }
+
+    fn error_at(&self, line_no: usize, message: String) -> ! {
+        match self.file_stack.last() {
+            | Some(f) => {
+                panic!("{}:{}: {}", f.name, self.presumed_line(line_no), message)
+            }
+            | None => panic!("{}", message),
+        }
+    }
+
+    fn process_file(&mut self, input: &str, path: &Path, out: &mut String) {
+        if self.file_stack.len() >= MAX_INCLUDE_DEPTH {
+            self.error(format!(
+                "#include nested depth {} exceeds maximum.",
+                MAX_INCLUDE_DEPTH
+            ));
+        }
+        self.file_stack.push(FileState {
+            path: path.to_path_buf(),
+            name: path.display().to_string(),
+            line_no: 0,
+            line_offset: 0,
+        });
+
+        let lines = split_lines(input);
+        if let Some(guard) = find_include_guard(&lines) {
+            self.include_guards.insert(file_key(path), guard);
+        }
+        // 指令でない行はtokenにして溜めておき、次の指令かfileの終わりでまとめて展開する.
+        // MEMO: 関数形式macroの呼び出しは、複数の行にまたがることがある.
+        let mut pending = vec![];
+        let mut conds: Vec<Cond> = vec![];
+        for line in lines.iter() {
+            self.file_stack.last_mut().unwrap().line_no = line.line_no;
+            let skipping = conds.iter().any(|c| !c.active);
+            match parse_directive(&line.text) {
+                | Some((name, arg)) if is_cond_directive(name) => {
+                    self.flush(&mut pending, out);
+                    self.process_cond(name, arg, &mut conds);
+                }
+                // 読まない分岐の中では、他の指令も無視する.
+                | _ if skipping => (),
+                | Some((name, arg)) => {
+                    self.flush(&mut pending, out);
+                    self.process_directive(name, arg, out);
+                }
+                | None => pending.extend(lex(&line.text, line.line_no)),
+            }
+        }
+        self.flush(&mut pending, out);
+        if let Some(cond) = conds.last() {
+            self.error_at(cond.line_no, String::from("unterminated #if."));
+        }
+        self.file_stack.pop();
+    }
+
+    // `#if`,`#ifdef`,`#ifndef`,`#elif`,`#else`,`#endif`.
+    // 読まない分岐の中の条件は評価しない.
+    fn process_cond(&mut self, name: &str, arg: &str, conds: &mut Vec<Cond>) {
+        let line_no = self.cur_line();
+        if name.starts_with("if") {
+            let outer_active = conds.iter().all(|c| c.active);
+            let active = outer_active
+                && match name {
+                    | "if" => self.eval_if(arg),
+                    | "ifdef" => self.is_defined(arg, name),
+                    | _ => !self.is_defined(arg, name),
+                };
+            conds.push(Cond {
+                active: active,
+                taken: active,
+                in_else: false,
+                line_no: line_no,
+            });
+            return;
+        }
+        if conds.is_empty() {
+            self.error(format!("#{} without #if.", name));
+        }
+        let outer_active = conds[..conds.len() - 1].iter().all(|c| c.active);
+        let cond = conds.last_mut().unwrap();
+        if cond.in_else && name != "endif" {
+            self.error(format!("#{} after #else.", name));
+        }
+        match name {
+            | "elif" => {
+                cond.active = outer_active && !cond.taken && self.eval_if(arg);
+                cond.taken |= cond.active;
+            }
+            | "else" => {
+                cond.active = outer_active && !cond.taken;
+                cond.taken = true;
+                cond.in_else = true;
+            }
+            | _ => {
+                conds.pop();
+            }
+        }
+    }
+
+    fn is_defined(&self, arg: &str, directive: &str) -> bool {
+        match lex(arg, 0).first() {
+            | Some(tok) if tok.kind == PPTokenKind::IDENT => {
+                return self.macros.contains_key(&tok.text)
+            }
+            | _ => {
+                self.error(format!("no macro name given in #{} directive.", directive))
+            }
+        }
+    }
+
+    fn process_directive(&mut self, name: &str, arg: &str, out: &mut String) {
+        match name {
+            // `#`だけの行は何もしない.
+            | "" => (),
+            | "include" => self.include(arg, out),
+            | "define" => self.define_macro(arg),
+            | "undef" => {
+                let toks = lex(arg, 0);
+                match toks.first() {
+                    | Some(tok) if tok.kind == PPTokenKind::IDENT => {
+                        self.undef(&tok.text)
+                    }
+                    | _ => self.error(String::from("macro names must be identifiers.")),
+                }
+            }
+            | "error" => self.error(format!("#error {}", arg)),
+            | "warning" => {
+                let f = self.file_stack.last().unwrap();
+                eprintln!(
+                    "{}:{}: warning: #warning {}",
+                    f.name,
+                    self.presumed_line(f.line_no),
+                    arg
+                );
+            }
+            | "line" => self.set_line(arg),
+            // 知らないpragmaは無視する.
+            | "pragma" => {
+                if arg == "once" {
+                    let key = file_key(&self.file_stack.last().unwrap().path);
+                    self.pragma_once.insert(key);
+                }
+            }
+            | _ => self.error(format!("invalid preprocessing directive #{}.", name)),
+        }
+    }
+
+    // `#line N "file"`. 次の行の行番号をNにし、fileを指定すればfile名も変える.
+    fn set_line(&mut self, arg: &str) {
+        let line_no = self.cur_line();
+        let toks = self.expand(lex(arg, line_no).into());
+        let n = match toks.first() {
+            | Some(tok) if tok.kind == PPTokenKind::NUM => tok.text.parse::<i64>().ok(),
+            | _ => None,
+        }
+        .unwrap_or_else(|| {
+            self.error(String::from("#line directive requires a positive integer."))
+        });
+        let name = match toks.get(1) {
+            | Some(tok) if tok.kind == PPTokenKind::STR && tok.text.starts_with('"') => {
+                Some(tok.text[1..tok.text.len() - 1].to_string())
+            }
+            | Some(tok) => self.error(format!("invalid filename \"{}\".", tok.text)),
+            | None => None,
+        };
+        let f = self.file_stack.last_mut().unwrap();
+        f.line_offset = n - (line_no as i64 + 1);
+        if let Some(name) = name {
+            f.name = name;
+        }
+    }
+
+    // `#include "file"`は、includeしているfileのdirectory、`-I`、system include pathの順に、
+    // `#include <file>`は、`-I`、system include pathの順に探す.
+    fn find_include(&self, name: &str, is_quoted: bool) -> Option<PathBuf> {
+        let cur_dir = self
+            .file_stack
+            .last()
+            .and_then(|f| f.path.parent())
+            .unwrap_or_else(|| Path::new(""))
+            .to_path_buf();
+        return (if is_quoted { Some(cur_dir) } else { None })
+            .iter()
+            .chain(self.include_paths.iter())
+            .chain(self.system_include_paths.iter())
+            .map(|dir| dir.join(name))
+            .find(|p| p.is_file());
+    }
+
+    // `#include MACRO`の様に`"`か`<`で始まらなければ、macroを展開してから読む.
+    fn include(&mut self, arg: &str, out: &mut String) {
+        let expanded;
+        let arg = if arg.starts_with(['"', '<']) {
+            arg
+        } else {
+            let toks = self.expand(lex(arg, self.cur_line()).into());
+            expanded = toks.iter().map(|t| t.text.as_str()).collect::<String>();
+            expanded.as_str()
+        };
+        let (name, is_quoted) = if let Some(rest) = arg.strip_prefix('"') {
+            (rest.split_once('"'), true)
+        } else if let Some(rest) = arg.strip_prefix('<') {
+            (rest.split_once('>'), false)
+        } else {
+            (None, false)
+        };
+        let name = name.map(|(name, _)| name).unwrap_or_else(|| {
+            self.error(String::from("#include expects \"FILENAME\" or <FILENAME>."))
+        });
+        let found = self.find_include(name, is_quoted).unwrap_or_else(|| {
+            self.error(format!("{}: No such file or directory.", name))
+        });
+
+        // 2回目以降のincludeでは、`#pragma once`やinclude guardのfileは読まない.
+        let key = file_key(&found);
+        if self.pragma_once.contains(&key) {
+            return;
+        }
+        if let Some(guard) = self.include_guards.get(&key) {
+            if self.macros.contains_key(guard) {
+                return;
+            }
+        }
+        let input = fs::read_to_string(&found)
+            .unwrap_or_else(|e| self.error(format!("{}: {}", found.display(), e)));
+        self.process_file(&input, &found, out);
+    }
+
+    // `#if`の条件を評価する.
+    // `defined`と`__has_include`を先に置き換えてからmacroを展開し、
+    // 残った識別子は0として、整数の定数式を評価する.
+    fn eval_if(&self, arg: &str) -> bool {
+        let line_no = self.cur_line();
+        let toks = lex(arg, line_no);
+        let mut replaced = VecDeque::new();
+        let mut i = 0;
+        while i < toks.len() {
+            let tok = &toks[i];
+            let value = match tok.text.as_str() {
+                | "defined" if tok.kind == PPTokenKind::IDENT => {
+                    let paren = toks.get(i + 1).map_or(false, |t| t.is_punct("("));
+                    let name = toks.get(i + 1 + paren as usize);
+                    let name = match name {
+                        | Some(t) if t.kind == PPTokenKind::IDENT => &t.text,
+                        | _ => self.error(String::from(
+                            "operator \"defined\" requires an identifier.",
+                        )),
+                    };
+                    i += 2;
+                    if paren {
+                        if !toks.get(i + 1).map_or(false, |t| t.is_punct(")")) {
+                            self.error(String::from("missing ')' after \"defined\"."));
+                        }
+                        i += 2;
+                    }
+                    self.macros.contains_key(name)
+                }
+                | "__has_include" if tok.kind == PPTokenKind::IDENT => {
+                    // `(`から`)`までのtokenをつなげて、`#include`と同じ形にする.
+                    let close = toks[i..].iter().position(|t| t.is_punct(")"));
+                    let (close, name) = match (toks.get(i + 1), close) {
+                        | (Some(t), Some(close)) if t.is_punct("(") => {
+                            let name: String = toks[i + 2..i + close]
+                                .iter()
+                                .map(|t| t.text.as_str())
+                                .collect();
+                            (i + close, name)
+                        }
+                        | _ => self.error(String::from(
+                            "missing '(' or ')' after \"__has_include\".",
+                        )),
+                    };
+                    i = close + 1;
+                    let is_quoted = name.starts_with('"');
+                    match name.get(1..name.len().max(1) - 1) {
+                        | Some(file) if name.starts_with(['"', '<']) => {
+                            self.find_include(file, is_quoted).is_some()
+                        }
+                        | _ => self.error(String::from(
+                            "operator \"__has_include\" requires a header-name.",
+                        )),
+                    }
+                }
+                | _ => {
+                    replaced.push_back(tok.clone());
+                    i += 1;
+                    continue;
+                }
+            };
+            replaced.push_back(PPToken {
+                kind: PPTokenKind::NUM,
+                text: (value as i64).to_string(),
+                space: true,
+                line_no: line_no,
+                hideset: HashSet::new(),
+            });
+        }
+        let toks = self.expand(replaced);
+        let mut expr = IfExpr {
+            pp: self,
+            toks: &toks,
+            pos: 0,
+            unevaluated: 0,
+        };
+        let (value, _) = expr.ternary();
+        if let Some(tok) = toks.get(expr.pos) {
+            self.error(format!(
+                "missing binary operator before token \"{}\".",
+                tok.text
+            ));
+        }
+        return value != 0;
+    }
+
+    // `__FILE__`と`__LINE__`は、展開する場所によって値が変わる.
+    fn dynamic_macro(&self, tok: &PPToken) -> Option<PPToken> {
+        let (kind, text) = match tok.text.as_str() {
+            | "__LINE__" => (
+                PPTokenKind::NUM,
+                self.presumed_line(tok.line_no).to_string(),
+            ),
+            | "__FILE__" => {
+                let name = &self.file_stack.last()?.name;
+                let name = name.replace('\\', "\\\\").replace('"', "\\\"");
+                (PPTokenKind::STR, format!("\"{}\"", name))
+            }
+            | _ => return None,
+        };
+        return Some(PPToken {
+            kind: kind,
+            text: text,
+            ..tok.clone()
+        });
+    }
+
+    // `#define NAME body`, `#define NAME(params) body`.
+    // nameの直後に空白を置かずに`(`が続けば、関数形式のmacro.
+    fn define_macro(&mut self, arg: &str) {
+        let line_no = self.cur_line();
+        let toks = lex(arg, line_no);
+        let name = match toks.first() {
+            | Some(tok) if tok.kind == PPTokenKind::IDENT => tok.text.clone(),
+            | _ => self.error(String::from("macro names must be identifiers.")),
+        };
+        let mut i = 1;
+        let mut params = None;
+        let mut is_variadic = false;
+        if toks.get(i).map_or(false, |t| t.is_punct("(") && !t.space) {
+            let mut names = vec![];
+            i += 1;
+            loop {
+                let tok = toks.get(i).unwrap_or_else(|| {
+                    self.error(String::from("missing ')' in macro parameter list."))
+                });
+                if names.is_empty() && tok.is_punct(")") {
+                    i += 1;
+                    break;
+                }
+                if tok.is_punct("...") {
+                    is_variadic = true;
+                    names.push(String::from("__VA_ARGS__"));
+                } else if tok.kind == PPTokenKind::IDENT {
+                    names.push(tok.text.clone());
+                } else {
+                    self.error(format!(
+                        "expected parameter name, found \"{}\".",
+                        tok.text
+                    ));
+                }
+                i += 2;
+                match toks.get(i - 1) {
+                    | Some(t) if t.is_punct(")") => break,
+                    | Some(t) if t.is_punct(",") && !is_variadic => (),
+                    | _ => self
+                        .error(String::from("expected ',' or ')' in macro parameters.")),
+                }
+            }
+            params = Some(names);
+        }
+        let mut body = toks[i..].to_vec();
+        if let Some(first) = body.first_mut() {
+            first.space = false;
+        }
+        if body.first().map_or(false, |t| t.is_punct("##"))
+            || body.last().map_or(false, |t| t.is_punct("##"))
+        {
+            self.error(String::from(
+                "'##' cannot appear at either end of a macro expansion.",
+            ));
+        }
+        self.macros.insert(
+            name,
+            Macro {
+                params: params,
+                is_variadic: is_variadic,
+                body: body,
+            },
+        );
+    }
+
+    // 溜めておいたtokenのmacroを展開して、outに書き出す.
+    fn flush(&self, pending: &mut Vec<PPToken>, out: &mut String) {
+        let toks = self.expand(std::mem::take(pending).into());
+        let f = self.file_stack.last().unwrap();
+        emit(&toks, out, &f.name, f.line_offset);
+    }
+
+    // tokenの列のmacroを全て展開する.
+    // 展開したtokenには、展開したmacroをhide setとして付け、同じmacroを再び展開しない.
+    fn expand(&self, mut input: VecDeque<PPToken>) -> Vec<PPToken> {
+        let mut out = vec![];
+        while let Some(tok) = input.pop_front() {
+            let m = match self.macros.get(&tok.text) {
+                | Some(m)
+                    if tok.kind == PPTokenKind::IDENT
+                        && !tok.hideset.contains(&tok.text) =>
+                {
+                    m
+                }
+                | _ => {
+                    out.push(tok);
+                    continue;
+                }
+            };
+            if let Some(t) = self.dynamic_macro(&tok) {
+                out.push(t);
+                continue;
+            }
+            let mut hideset = tok.hideset.clone();
+            let body = match m.params.as_ref() {
+                | None => self.subst(m, &[]),
+                // 関数形式macroの名前の後に`(`が続かなければ、展開しない.
+                | Some(_) if !input.front().map_or(false, |t| t.is_punct("(")) => {
+                    out.push(tok);
+                    continue;
+                }
+                | Some(params) => {
+                    let (args, rparen) = read_macro_args(&mut input, params.len())
+                        .unwrap_or_else(|| {
+                            self.error_at(
+                                tok.line_no,
+                                format!(
+                                    "unterminated argument list invoking macro \"{}\".",
+                                    tok.text
+                                ),
+                            )
+                        });
+                    let args = self.check_arg_count(&tok, m, args);
+                    // hide setは、macroの名前と`)`のhide setの共通部分.
+                    hideset.retain(|name| rparen.hideset.contains(name));
+                    self.subst(m, &args)
+                }
+            };
+            hideset.insert(tok.text.clone());
+            for (i, mut t) in body.into_iter().enumerate().rev() {
+                t.hideset.extend(hideset.iter().cloned());
+                t.line_no = tok.line_no;
+                if i == 0 {
+                    t.space = tok.space;
+                }
+                input.push_front(t);
+            }
+        }
+        return out;
+    }
+
+    // 引数の数がmacroの引数の数と合うか確かめる.
+    // 可変長引数が省略されていれば、空の引数を補う.
+    fn check_arg_count(
+        &self,
+        tok: &PPToken,
+        m: &Macro,
+        mut args: Vec<Vec<PPToken>>,
+    ) -> Vec<Vec<PPToken>> {
+        let n_params = m.params.as_ref().unwrap().len();
+        // `F()`は、引数を取らないmacroの呼び出し.
+        if n_params == 0 && args.len() == 1 && args[0].is_empty() {
+            args.clear();
+        }
+        if m.is_variadic && args.len() + 1 == n_params {
+            args.push(vec![]);
+        }
+        if args.len() != n_params {
+            self.error_at(
+                tok.line_no,
+                format!(
+                    "macro \"{}\" passed {} arguments, but takes {}.",
+                    tok.text,
+                    args.len(),
+                    n_params
+                ),
+            );
+        }
+        return args;
+    }
+
+    // macroのbodyの引数を置き換える.
+    // `#x`は引数を文字列literalにし、`a ## b`は両側のtokenをつなげる.
+    // `##`のoperandになる引数は、展開せずにそのまま使う.
+    fn subst(&self, m: &Macro, args: &[Vec<PPToken>]) -> Vec<PPToken> {
+        let params = m.params.clone().unwrap_or_default();
+        let arg_of = |t: &PPToken| match t.kind {
+            | PPTokenKind::IDENT => {
+                params.iter().position(|p| *p == t.text).map(|i| &args[i])
+            }
+            | _ => None,
+        };
+        let body = &m.body;
+        let mut res: Vec<PPToken> = vec![];
+        let mut i = 0;
+        while i < body.len() {
+            let t = &body[i];
+            let next = body.get(i + 1);
+            let next_is_paste = next.map_or(false, |n| n.is_punct("##"));
+            if t.is_punct("#") && m.params.is_some() {
+                let arg = next.and_then(arg_of).unwrap_or_else(|| {
+                    self.error_at(
+                        t.line_no,
+                        String::from("'#' is not followed by a macro parameter."),
+                    )
+                });
+                let mut s = stringify(arg, t.line_no);
+                s.space = t.space;
+                res.push(s);
+                i += 2;
+                continue;
+            }
+            // GNU拡張の`, ## __VA_ARGS__`は、可変長引数が空ならcommaを取り除く.
+            if t.is_punct(",")
+                && next_is_paste
+                && m.is_variadic
+                && body.get(i + 2).map_or(false, |n| n.text == "__VA_ARGS__")
+            {
+                let va_args = args.last().unwrap();
+                if !va_args.is_empty() {
+                    res.push(t.clone());
+                    res.extend(va_args.iter().cloned());
+                }
+                i += 3;
+                continue;
+            }
+            if t.is_punct("##") {
+                let rhs = next.unwrap();
+                let rhs = match arg_of(rhs) {
+                    | Some(arg) => arg.clone(),
+                    | None => vec![rhs.clone()],
+                };
+                if let Some((first, rest)) = rhs.split_first() {
+                    let lhs = res.pop().unwrap_or_else(PPToken::placemarker);
+                    res.push(self.paste(&lhs, first));
+                    res.extend(rest.iter().cloned());
+                }
+                i += 2;
+                continue;
+            }
+            if let Some(arg) = arg_of(t) {
+                let mut toks = match next_is_paste {
+                    | true => arg.clone(),
+                    | false => self.expand(arg.clone().into()),
+                };
+                match toks.first_mut() {
+                    | Some(first) => first.space = t.space,
+                    | None if next_is_paste => toks.push(PPToken::placemarker()),
+                    | None => (),
+                }
+                res.extend(toks);
+                i += 1;
+                continue;
+            }
+            res.push(t.clone());
+            i += 1;
+        }
+        res.retain(|t| !t.text.is_empty());
+        return res;
+    }
+
+    // 2つのtokenをつなげて、1つのtokenにする.
+    fn paste(&self, lhs: &PPToken, rhs: &PPToken) -> PPToken {
+        if lhs.text.is_empty() {
+            return rhs.clone();
+        }
+        if rhs.text.is_empty() {
+            return lhs.clone();
+        }
+        let mut toks = lex(&format!("{}{}", lhs.text, rhs.text), lhs.line_no);
+        if toks.len() != 1 {
+            self.error_at(
+                lhs.line_no,
+                format!(
+                    "pasting \"{}\" and \"{}\" does not give a valid preprocessing token.",
+                    lhs.text, rhs.text
+                ),
+            );
+        }
+        let mut tok = toks.pop().unwrap();
+        tok.space = lhs.space;
+        tok.hideset = lhs.hideset.clone();
+        return tok;
+    }
+}
+
+fn is_cond_directive(name: &str) -> bool {
+    return ["if", "ifdef", "ifndef", "elif", "else", "endif"].contains(&name);
+}
+
+// `#if`の条件式.
+// ternary = binary ( "?" ternary ":" ternary )?
+// binary = unary ( binary_op unary )*   (演算子の優先順位はCと同じ)
+// unary = ( "+" | "-" | "~" | "!" ) unary | "(" ternary ")" | number | char
+// MEMO: 値は64bitの整数と、unsignedかどうかの組(IfValue)として計算する.
+//       二項演算は、どちらかがunsignedならunsignedとして行う.(`-1 > 0u`は真)
+struct IfExpr<'a> {
+    pp: &'a Preprocessor,
+    toks: &'a [PPToken],
+    pos: usize,
+    // 0より大きければ、評価されない部分式を読んでいる.(`0 && 1 / 0`など)
+    unevaluated: usize,
+}
+// `#if`の式の値と、unsignedかどうか.
+type IfValue = (i64, bool);
+
+impl<'a> IfExpr<'a> {
+    fn error(&self, message: String) -> ! {
+        let line_no = self.toks.first().map_or(self.pp.cur_line(), |t| t.line_no);
+        self.pp.error_at(line_no, message);
+    }
+
+    fn consume(&mut self, s: &str) -> bool {
+        if self.toks.get(self.pos).map_or(false, |t| t.is_punct(s)) {
+            self.pos += 1;
+            return true;
+        }
+        return false;
+    }
+
+    fn ternary(&mut self) -> IfValue {
+        let (cond, cond_unsigned) = self.binary(1);
+        if !self.consume("?") {
+            return (cond, cond_unsigned);
+        }
+        self.unevaluated += (cond == 0) as usize;
+        let then = self.ternary();
+        self.unevaluated -= (cond == 0) as usize;
+        if !self.consume(":") {
+            self.error(String::from("expected ':' in #if."));
+        }
+        self.unevaluated += (cond != 0) as usize;
+        let els = self.ternary();
+        self.unevaluated -= (cond != 0) as usize;
+        // 結果のtypeは、両方の値のtypeから決まる.
+        let value = if cond != 0 { then.0 } else { els.0 };
+        return (value, then.1 || els.1);
+    }
+
+    // 優先順位がmin_prec以上の二項演算子を読む.
+    fn binary(&mut self, min_prec: usize) -> IfValue {
+        let (mut lhs, mut unsigned) = self.unary();
+        loop {
+            let op = match self.toks.get(self.pos) {
+                | Some(t) if t.kind == PPTokenKind::PUNCT => t.text.as_str(),
+                | _ => return (lhs, unsigned),
+            };
+            let prec = match op {
+                | "||" => 1,
+                | "&&" => 2,
+                | "|" => 3,
+                | "^" => 4,
+                | "&" => 5,
+                | "==" | "!=" => 6,
+                | "<" | ">" | "<=" | ">=" => 7,
+                | "<<" | ">>" => 8,
+                | "+" | "-" => 9,
+                | "*" | "/" | "%" => 10,
+                | _ => return (lhs, unsigned),
+            };
+            if prec < min_prec {
+                return (lhs, unsigned);
+            }
+            self.pos += 1;
+            // `&&`と`||`は、左辺で値が決まれば右辺を評価しない.
+            let short = (op == "&&" && lhs == 0) || (op == "||" && lhs != 0);
+            self.unevaluated += short as usize;
+            let (rhs, rhs_unsigned) = self.binary(prec + 1);
+            self.unevaluated -= short as usize;
+            // shiftの結果は左辺のtype、それ以外はどちらかがunsignedならunsignedになる.
+            let is_shift = op == "<<" || op == ">>";
+            let conv_unsigned = unsigned || rhs_unsigned;
+            let (ul, ur) = (lhs as u64, rhs as u64);
+            let ord = if conv_unsigned {
+                ul.cmp(&ur)
+            } else {
+                lhs.cmp(&rhs)
+            };
+            lhs = match op {
+                | "||" => (lhs != 0 || rhs != 0) as i64,
+                | "&&" => (lhs != 0 && rhs != 0) as i64,
+                | "|" => lhs | rhs,
+                | "^" => lhs ^ rhs,
+                | "&" => lhs & rhs,
+                | "==" => ord.is_eq() as i64,
+                | "!=" => ord.is_ne() as i64,
+                | "<" => ord.is_lt() as i64,
+                | ">" => ord.is_gt() as i64,
+                | "<=" => ord.is_le() as i64,
+                | ">=" => ord.is_ge() as i64,
+                | "<<" => lhs.wrapping_shl(rhs as u32),
+                | ">>" if unsigned => ul.wrapping_shr(rhs as u32) as i64,
+                | ">>" => lhs.wrapping_shr(rhs as u32),
+                | "+" => lhs.wrapping_add(rhs),
+                | "-" => lhs.wrapping_sub(rhs),
+                | "*" => lhs.wrapping_mul(rhs),
+                | _ if rhs == 0 && self.unevaluated > 0 => 0,
+                | _ if rhs == 0 => self.error(String::from("division by zero in #if.")),
+                | "/" if conv_unsigned => (ul / ur) as i64,
+                | "%" if conv_unsigned => (ul % ur) as i64,
+                | "/" => lhs.wrapping_div(rhs),
+                | _ => lhs.wrapping_rem(rhs),
+            };
+            // 論理演算と比較の結果は、signedの0か1になる.
+            unsigned = match op {
+                | "||" | "&&" | "==" | "!=" | "<" | ">" | "<=" | ">=" => false,
+                | _ if is_shift => unsigned,
+                | _ => conv_unsigned,
+            };
+        }
+    }
+
+    fn unary(&mut self) -> IfValue {
+        if self.consume("+") {
+            return self.unary();
+        }
+        if self.consume("-") {
+            let (v, unsigned) = self.unary();
+            return (v.wrapping_neg(), unsigned);
+        }
+        if self.consume("~") {
+            let (v, unsigned) = self.unary();
+            return (!v, unsigned);
+        }
+        if self.consume("!") {
+            return ((self.unary().0 == 0) as i64, false);
+        }
+        if self.consume("(") {
+            let v = self.ternary();
+            if !self.consume(")") {
+                self.error(String::from("missing ')' in #if."));
+            }
+            return v;
+        }
+        let tok = match self.toks.get(self.pos) {
+            | Some(tok) => tok,
+            | None => self.error(String::from("#if with no expression.")),
+        };
+        self.pos += 1;
+        let value = match tok.kind {
+            // macroでない識別子は0になる.
+            | PPTokenKind::IDENT => Some((0, false)),
+            | PPTokenKind::NUM => parse_int(&tok.text),
+            | PPTokenKind::STR if tok.text.starts_with('\'') => {
+                char_value(&tok.text).map(|v| (v, false))
+            }
+            | _ => None,
+        };
+        return value.unwrap_or_else(|| {
+            self.error(format!("token \"{}\" is not valid in #if.", tok.text))
+        });
+    }
+}
+
+// 整数のliteralの値と、unsignedかどうか.
+// `u`のsuffixが付いているか、値がi64に収まらなければunsignedになる.
+fn parse_int(text: &str) -> Option<IfValue> {
+    let digits_end = text.trim_end_matches(['u', 'U', 'l', 'L']).len();
+    let (text, suffix) = text.split_at(digits_end);
+    let lower = text.to_ascii_lowercase();
+    let (digits, radix) = if let Some(hex) = lower.strip_prefix("0x") {
+        (hex, 16)
+    } else if let Some(bin) = lower.strip_prefix("0b") {
+        (bin, 2)
+    } else if lower.len() > 1 && lower.starts_with('0') {
+        (&lower[1..], 8)
+    } else {
+        (lower.as_str(), 10)
+    };
+    let v = u64::from_str_radix(digits, radix).ok()?;
+    let unsigned = suffix.contains(['u', 'U']) || v > i64::MAX as u64;
+    return Some((v as i64, unsigned));
+}
+
+// `'a'`や`'\n'`の様な文字のliteralの値.
+fn char_value(text: &str) -> Option<i64> {
+    let inner = text.strip_prefix('\'')?.strip_suffix('\'')?;
+    let mut chars = inner.chars();
+    let c = chars.next()?;
+    if c != '\\' {
+        return chars.next().is_none().then_some(c as i64);
+    }
+    let rest: String = chars.collect();
+    let value = match rest.as_str() {
+        | "n" => 10,
+        | "t" => 9,
+        | "r" => 13,
+        | "a" => 7,
+        | "b" => 8,
+        | "f" => 12,
+        | "v" => 11,
+        | "\\" | "'" | "\"" | "?" => rest.chars().next()? as i64,
+        | _ if rest.starts_with('x') => i64::from_str_radix(&rest[1..], 16).ok()?,
+        | _ => i64::from_str_radix(&rest, 8).ok()?,
+    };
+    return Some(value);
+}
+
+// 関数形式macroの引数を、`(`から対応する`)`まで読む.
+// 引数ごとのtokenの列と、`)`のtokenを返す.
+// 可変長引数はmax_args番目の引数にまとめ、その中の`,`では区切らない.
+fn read_macro_args(
+    input: &mut VecDeque<PPToken>,
+    max_args: usize,
+) -> Option<(Vec<Vec<PPToken>>, PPToken)> {
+    input.pop_front();
+    let mut args = vec![vec![]];
+    let mut depth = 0;
+    while let Some(tok) = input.pop_front() {
+        if tok.kind == PPTokenKind::PUNCT {
+            match tok.text.as_str() {
+                | "(" => depth += 1,
+                | ")" if depth == 0 => return Some((args, tok)),
+                | ")" => depth -= 1,
+                | "," if depth == 0 && args.len() < max_args => {
+                    args.push(vec![]);
+                    continue;
+                }
+                | _ => (),
+            }
+        }
+        args.last_mut().unwrap().push(tok);
+    }
+    return None;
+}
+
+// `#x`で、引数のtokenの列を文字列literalにする.
+// 文字列と文字のliteralの中の`\`と`"`はescapeする.
+fn stringify(arg: &[PPToken], line_no: usize) -> PPToken {
+    let mut s = String::new();
+    for (i, tok) in arg.iter().enumerate() {
+        if i > 0 && tok.space {
+            s.push(' ');
+        }
+        match tok.kind {
+            | PPTokenKind::STR => {
+                s.push_str(&tok.text.replace('\\', "\\\\").replace('"', "\\\""))
+            }
+            | _ => s.push_str(&tok.text),
+        }
+    }
+    return PPToken {
+        kind: PPTokenKind::STR,
+        text: format!("\"{}\"", s),
+        space: false,
+        line_no: line_no,
+        hideset: HashSet::new(),
+    };
+}
+
+// 1行をtokenに分ける.
+fn lex(text: &str, line_no: usize) -> Vec<PPToken> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut toks = vec![];
+    let mut space = false;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            space = true;
+            i += 1;
+            continue;
+        }
+        let start = i;
+        let is_ident_char = |c: char| c.is_alphanumeric() || c == '_';
+        let kind = if c.is_alphabetic() || c == '_' {
+            while i < chars.len() && is_ident_char(chars[i]) {
+                i += 1;
+            }
+            PPTokenKind::IDENT
+        } else if c.is_ascii_digit()
+            || (c == '.' && chars.get(i + 1).map_or(false, |d| d.is_ascii_digit()))
+        {
+            // pp-number. `1e+5`の様に、指数の後の符号も含む.
+            i += 1;
+            while i < chars.len()
+                && (is_ident_char(chars[i])
+                    || chars[i] == '.'
+                    || ("eEpP".contains(chars[i - 1]) && "+-".contains(chars[i])))
+            {
+                i += 1;
+            }
+            PPTokenKind::NUM
+        } else if c == '"' || c == '\'' {
+            i += 1;
+            while i < chars.len() && chars[i] != c {
+                if chars[i] == '\\' {
+                    i += 1;
+                }
+                i += 1;
+            }
+            i = (i + 1).min(chars.len());
+            PPTokenKind::STR
+        } else {
+            let rest: String = chars[i..].iter().take(3).collect();
+            i += PUNCTS
+                .iter()
+                .find(|p| rest.starts_with(*p))
+                .map_or(1, |p| p.len());
+            PPTokenKind::PUNCT
+        };
+        toks.push(PPToken {
+            kind: kind,
+            text: chars[start..i].iter().collect(),
+            space: space,
+            line_no: line_no,
+            hideset: HashSet::new(),
+        });
+        space = false;
+    }
+    return toks;
+}
+
+// tokenの列を、元の行に合わせて改行しながら書き出す.
+// 隣り合うtokenがつながって別のtokenにならない様に、間に空白を入れることがある.
+// 出力の各行が元のfileの何行目かを、errorの位置の表示のために記録する.
+fn emit(toks: &[PPToken], out: &mut String, file: &str, line_offset: i64) {
+    let is_word =
+        |t: &PPToken| t.kind == PPTokenKind::IDENT || t.kind == PPTokenKind::NUM;
+    for (i, tok) in toks.iter().enumerate() {
+        if i == 0 || tok.line_no != toks[i - 1].line_no {
+            if i > 0 {
+                out.push('\n');
+            }
+            add_line_map(out.len(), file, tok.line_no as i64 + line_offset);
+        } else {
+            let prev = &toks[i - 1];
+            if tok.space
+                || (is_word(prev) && is_word(tok))
+                || (prev.kind == PPTokenKind::PUNCT && tok.kind == PPTokenKind::PUNCT)
+            {
+                out.push(' ');
+            }
+        }
+        out.push_str(&tok.text);
+    }
+    if !toks.is_empty() {
+        out.push('\n');
+    }
+}
+
+// 同じfileを指すpathを、同じkeyにする.
+fn file_key(path: &Path) -> PathBuf {
+    return fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+}
+
+// `#name arg`の行なら、nameとargを返す.
+fn parse_directive(text: &str) -> Option<(&str, &str)> {
+    let text = text.trim_start().strip_prefix('#')?.trim_start();
+    let len = text
+        .find(|c: char| !c.is_alphanumeric() && c != '_')
+        .unwrap_or(text.len());
+    return Some((&text[..len], text[len..].trim()));
+}
+
+// inputを論理的な行に分ける.
+// 行末の`\`で次の行とつなげ、注釈は空白に置き換える.
+// MEMO: 複数行にまたがる`/* */`の注釈は、前後の行を1行につなげる.
+fn split_lines(input: &str) -> Vec<Line> {
+    let mut lines = vec![];
+    let mut text = String::new();
+    let (mut line_no, mut start) = (1, 1);
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            | '\\' if chars.peek() == Some(&'\n') => {
+                chars.next();
+                line_no += 1;
+            }
+            | '\n' => {
+                lines.push(Line {
+                    line_no: start,
+                    text: std::mem::take(&mut text),
+                });
+                line_no += 1;
+                start = line_no;
+            }
+            // 文字列と文字のliteralの中は、そのまま読む.
+            // 閉じられていない場合は、改行を消費せずに行末で止める.
+            | '"' | '\'' => {
+                text.push(c);
+                while let Some(&d) = chars.peek() {
+                    if d == '\n' {
+                        break;
+                    }
+                    chars.next();
+                    if d == '\\' && chars.peek() == Some(&'\n') {
+                        chars.next();
+                        line_no += 1;
+                        continue;
+                    }
+                    text.push(d);
+                    if d == '\\' {
+                        text.extend(chars.next());
+                    } else if d == c {
+                        break;
+                    }
+                }
+            }
+            | '/' if chars.peek() == Some(&'/') => {
+                while chars.peek().map_or(false, |d| *d != '\n') {
+                    chars.next();
+                }
+                text.push(' ');
+            }
+            | '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut prev = ' ';
+                while let Some(d) = chars.next() {
+                    if d == '\n' {
+                        line_no += 1;
+                    }
+                    if prev == '*' && d == '/' {
+                        break;
+                    }
+                    prev = d;
+                }
+                text.push(' ');
+            }
+            | _ => text.push(c),
+        }
+    }
+    if !text.is_empty() {
+        lines.push(Line {
+            line_no: start,
+            text: text,
+        });
+    }
+    return lines;
+}
+
+// fileの全体が`#ifndef X` `#define X` ... `#endif`で囲まれていれば、Xを返す.
+fn find_include_guard(lines: &[Line]) -> Option<String> {
+    let lines: Vec<&Line> = lines.iter().filter(|l| !l.text.trim().is_empty()).collect();
+    if lines.len() < 3 {
+        return None;
+    }
+    let guard = match parse_directive(&lines[0].text)? {
+        | ("ifndef", name) => name,
+        | _ => return None,
+    };
+    if parse_directive(&lines[1].text)? != ("define", guard) {
+        return None;
+    }
+    // 最初の`#ifndef`に対応する`#endif`が、最後の行であること.
+    let mut depth = 0;
+    for (i, line) in lines.iter().enumerate() {
+        match parse_directive(&line.text).map(|(name, _)| name) {
+            | Some("if") | Some("ifdef") | Some("ifndef") => depth += 1,
+            | Some("endif") => {
+                depth -= 1;
+                if depth == 0 {
+                    return (i == lines.len() - 1).then(|| guard.to_string());
+                }
+            }
+            | _ => (),
+        }
+    }
+    return None;
+}
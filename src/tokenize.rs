@@ -1,4 +1,6 @@
-use crate::errors::{display_around_pos, init_error};
+use crate::errors::{error_at, init_error};
+use once_cell::sync::Lazy;
+use std::{collections::HashMap, sync::Mutex};
 
 #[derive(Clone)]
 pub struct Token {
@@ -108,8 +110,8 @@ impl Lexer {
         self.next();
         loop {
             let char = self.cur_char();
-            // 文字でない or 終端に達したら.
-            if !char.is_alphabetic() {
+            // 識別子に使える文字でない or 終端に達したら.
+            if !char.is_alphanumeric() && char != '_' {
                 break;
             }
             cur_str.push(char);
@@ -126,6 +128,9 @@ struct Tokens(Vec<Token>);
 pub enum TokenKind {
     INI,
     NUM,
+    // 浮動小数点数のliteral. typeはsuffixで決まり、charにliteralの文字列を持つ.
+    FNUM(Type),
+    STR,
     PUNCT,
     EOF,
     IDENT,
@@ -141,29 +146,350 @@ pub enum TokenKind {
     ELIF,
     ELSE,
     FOR,
+    TYPEDEF,
+    STATIC,
+    EXTERN,
+    SIZEOF,
+    ALIGNOF,
+    ALIGNAS,
+    STATICASSERT,
+    GENERIC,
+    ASM,
     TYPE(Type),
+    QUALIFIER,
+    STRUCT,
+    UNION,
 }
+#[allow(non_camel_case_types)]
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum Type {
     None,
     Unknown,
+    VOID,
+    // `_Bool`. 0か1だけを持つ、1byteの符号なし整数型.
+    BOOL,
+    CHAR,
+    UCHAR,
+    SHORT,
+    USHORT,
     INT,
+    UINT,
+    LONG,
+    ULONG,
+    FLOAT,
+    DOUBLE,
+    // x87の80bit拡張倍精度. 16byteの領域に置く.
+    LDOUBLE,
     PTR(Box<Type>),
+    // 要素のtypeと要素数. 要素数を省略した宣言では、初期化式から決まるまで0にしておく.
+    ARRAY(Box<Type>, usize),
+    // 可変長配列. 要素のtypeと、実行時に計算した配列全体のsizeを持つ隠れた変数の名前.
+    // 変数自体には、stack上に確保した領域の先頭のアドレスを置く.
+    VLA(Box<Type>, String),
+    // SysV ABIの`va_list`. gp_offset, fp_offset, overflow_arg_area, reg_save_areaを持つ.
+    // 本来は1要素の配列なので、式の中ではpointerとして扱う.
+    VA_LIST,
+    // 関数のtype. 返り値のtype, 引数のtype, 可変長引数かどうか.
+    // MEMO: 変数は関数typeを持たず、関数pointer(PTR(FUNC))として扱う.
+    FUNC(Box<Type>, Vec<Type>, bool),
+    // 型修飾子の付いたtype. `const char *`は、PTR(QUAL(CHAR, const))になる.
+    // MEMO: QUALは入れ子にしない.(Type::qualifyで1つにまとめる)
+    QUAL(Box<Type>, Qualifiers),
+    // structとunion. STRUCT_DEFSのindexを持つ.
+    // MEMO: 自分自身へのpointerをmemberに持てる様に、定義そのものではなくindexを持たせる.
+    STRUCT(usize),
 }
+
+// structとunionのmember. offsetはstructの先頭からのbyte数.
+// alignは`_Alignas`で指定されたalignmentか、typeのalignment.
+// bit-fieldは、offsetから始まるtypeのsizeの領域(storage unit)の、bit_offset bit目から
+// bit_width bitを使う. 名前のないbit-fieldは、領域を空けるためだけに置かれる.
+#[derive(Clone, Debug)]
+pub struct Member {
+    pub name: String,
+    pub typ: Type,
+    pub offset: usize,
+    pub align: usize,
+    pub bit_width: Option<usize>,
+    pub bit_offset: usize,
+}
+
+// structとunionの定義. membersがNoneの間は不完全型.
+#[derive(Clone)]
+pub struct StructDef {
+    pub tag: String,
+    pub is_union: bool,
+    pub members: Option<Vec<Member>>,
+    pub size: usize,
+    pub align: usize,
+}
+
+// 宣言された全てのstructとunion. 同じtagでもscopeが異なれば別のものになる.
+static STRUCT_DEFS: Lazy<Mutex<Vec<StructDef>>> = Lazy::new(|| Mutex::new(vec![]));
+
+// 新しいstructかunionを、不完全型として登録する.
+pub fn new_struct(tag: String, is_union: bool) -> usize {
+    let mut defs = STRUCT_DEFS.lock().unwrap();
+    defs.push(StructDef {
+        tag: tag,
+        is_union: is_union,
+        members: None,
+        size: 0,
+        align: 1,
+    });
+    return defs.len() - 1;
+}
+
+pub fn get_struct_def(id: usize) -> StructDef {
+    return STRUCT_DEFS.lock().unwrap()[id].clone();
+}
+
+// memberのoffsetと、全体のsizeとalignmentを決めて定義を完成させる.
+// SysV ABIに従い、structのmemberはそれぞれのalignmentに合わせて宣言順に並べ、
+// unionのmemberは全て先頭に置く. sizeはalignmentの倍数に切り上げる.
+// bit-fieldは直前のmemberの後ろに詰め、typeのalignmentの境界を跨ぐ場合は次の境界から置く.
+// 幅0のbit-fieldは、次のmemberをtypeのalignmentの境界に置く.
+// MEMO: 名前のないbit-fieldは、structのalignmentに影響しない.(gccと同じ)
+// MEMO: 末尾の要素数のない配列(flexible array member)はsizeが0なので、sizeには含まれない.
+pub fn define_struct(id: usize, mut members: Vec<Member>) {
+    let is_union = get_struct_def(id).is_union;
+    // structの先頭からのbit数.
+    let (mut bits, mut size, mut align) = (0, 0, 1);
+    for m in members.iter_mut() {
+        let unit = m.typ.size() * 8;
+        if is_union {
+            size = size.max(m.typ.size());
+        } else {
+            match m.bit_width {
+                | Some(0) => bits = align_to(bits, unit),
+                | Some(width) => {
+                    if bits / unit != (bits + width - 1) / unit {
+                        bits = align_to(bits, unit);
+                    }
+                    m.offset = bits / unit * m.typ.size();
+                    m.bit_offset = bits % unit;
+                    bits += width;
+                }
+                | None => {
+                    bits = align_to(bits, m.align * 8);
+                    m.offset = bits / 8;
+                    bits += unit;
+                }
+            }
+        }
+        if !m.name.is_empty() {
+            align = align.max(m.align);
+        }
+    }
+    if !is_union {
+        size = align_to(bits, 8) / 8;
+    }
+    // memberのalignを求める時にSTRUCT_DEFSを読むので、lockは最後に取る.
+    let mut defs = STRUCT_DEFS.lock().unwrap();
+    let def = &mut defs[id];
+    def.members = Some(members);
+    def.size = align_to(size, align);
+    def.align = align;
+}
+
+// 完成したstructのsizeとalignment.
+fn struct_layout(id: usize) -> Option<(usize, usize)> {
+    let defs = STRUCT_DEFS.lock().unwrap();
+    let def = &defs[id];
+    return def.members.as_ref().map(|_| (def.size, def.align));
+}
+
+// structやunionのtypeから、名前でmemberを探す.
+pub fn find_member(typ: &Type, name: &str) -> Option<Member> {
+    match typ.unqual() {
+        | Type::STRUCT(id) => get_struct_def(*id)
+            .members?
+            .into_iter()
+            .find(|m| m.name == name),
+        | _ => None,
+    }
+}
+
+fn align_to(n: usize, align: usize) -> usize {
+    return n.div_ceil(align) * align;
+}
+
+// 診断で使う、`struct tag`の形の名前.
+pub fn struct_name(id: usize) -> String {
+    let def = get_struct_def(id);
+    let kind = if def.is_union { "union" } else { "struct" };
+    return format!("{} {}", kind, def.tag);
+}
+
+// 型修飾子.
+// MEMO: volatileなobjectへのアクセスは、最適化で削除したり並び替えたりしてはいけない.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct Qualifiers {
+    pub is_const: bool,
+    pub is_volatile: bool,
+    pub is_restrict: bool,
+}
+impl Qualifiers {
+    fn is_empty(&self) -> bool {
+        return !self.is_const && !self.is_volatile && !self.is_restrict;
+    }
+    fn merge(&mut self, other: Qualifiers) {
+        self.is_const |= other.is_const;
+        self.is_volatile |= other.is_volatile;
+        self.is_restrict |= other.is_restrict;
+    }
+    // selfの修飾子を全てotherも持っているかどうか.
+    pub fn is_subset_of(&self, other: &Qualifiers) -> bool {
+        return (!self.is_const || other.is_const)
+            && (!self.is_volatile || other.is_volatile)
+            && (!self.is_restrict || other.is_restrict);
+    }
+}
+
 impl Type {
+    // 型修飾子を取り除いたtype.
+    pub fn unqual(&self) -> &Type {
+        match self {
+            | Type::QUAL(t, _) => t,
+            | t => t,
+        }
+    }
+    pub fn qualifiers(&self) -> Qualifiers {
+        match self {
+            | Type::QUAL(_, q) => *q,
+            | _ => Qualifiers::default(),
+        }
+    }
+    pub fn is_const(&self) -> bool {
+        return self.qualifiers().is_const;
+    }
+    // 型修飾子を付けたtypeを返す.
+    pub fn qualify(self, q: Qualifiers) -> Type {
+        if q.is_empty() {
+            return self;
+        }
+        match self {
+            | Type::QUAL(t, mut q0) => {
+                q0.merge(q);
+                Type::QUAL(t, q0)
+            }
+            | t => Type::QUAL(Box::new(t), q),
+        }
+    }
     pub fn size(&self) -> usize {
-        match &self {
-            | Type::INT => 8,
+        match self.unqual() {
+            | Type::BOOL | Type::CHAR | Type::UCHAR => 1,
+            | Type::SHORT | Type::USHORT => 2,
+            | Type::INT | Type::UINT => 4,
+            | Type::LONG | Type::ULONG => 8,
+            | Type::FLOAT => 4,
+            | Type::DOUBLE => 8,
+            | Type::LDOUBLE => 16,
             | Type::PTR(_) => 8,
+            | Type::ARRAY(elem, len) => elem.size() * len,
+            // 実行時のsizeは隠れた変数から読む. ここでは先頭のアドレスを置くslotのsize.
+            | Type::VLA(..) => 8,
+            | Type::VA_LIST => 24,
+            | Type::STRUCT(id) => match struct_layout(*id) {
+                | Some((size, _)) => size,
+                | None => panic!("incomplete type `{}`.", struct_name(*id)),
+            },
             | _ => panic!("unknown size"),
         }
     }
+    // alignment of the type in bytes.
+    pub fn align(&self) -> usize {
+        match self.unqual() {
+            | Type::PTR(_) | Type::VA_LIST | Type::VLA(..) => 8,
+            | Type::ARRAY(elem, _) => elem.align(),
+            // 算術型はsizeとalignmentが一致する.
+            | t if t.is_arith() => t.size(),
+            | Type::STRUCT(id) => match struct_layout(*id) {
+                | Some((_, align)) => align,
+                | None => panic!("incomplete type `{}`.", struct_name(*id)),
+            },
+            | _ => panic!("unknown align"),
+        }
+    }
     pub fn is_ptr(&self) -> bool {
-        match &self {
+        match self.unqual() {
             | Type::PTR(_) => true,
             | _ => false,
         }
     }
+    pub fn is_array(&self) -> bool {
+        match self.unqual() {
+            | Type::ARRAY(..) => true,
+            | _ => false,
+        }
+    }
+    pub fn is_vla(&self) -> bool {
+        match self.unqual() {
+            | Type::VLA(..) => true,
+            | _ => false,
+        }
+    }
+    pub fn is_struct(&self) -> bool {
+        match self.unqual() {
+            | Type::STRUCT(_) => true,
+            | _ => false,
+        }
+    }
+    // 宣言だけされて、まだ定義されていないstructやunion.
+    pub fn is_incomplete(&self) -> bool {
+        match self.unqual() {
+            | Type::STRUCT(id) => struct_layout(*id).is_none(),
+            | _ => false,
+        }
+    }
+    pub fn is_integer(&self) -> bool {
+        match self.unqual() {
+            | Type::BOOL
+            | Type::CHAR
+            | Type::UCHAR
+            | Type::SHORT
+            | Type::USHORT
+            | Type::INT
+            | Type::UINT
+            | Type::LONG
+            | Type::ULONG => true,
+            | _ => false,
+        }
+    }
+    pub fn is_flonum(&self) -> bool {
+        match self.unqual() {
+            | Type::FLOAT | Type::DOUBLE | Type::LDOUBLE => true,
+            | _ => false,
+        }
+    }
+    // 整数型と浮動小数点型を算術型とする.
+    pub fn is_arith(&self) -> bool {
+        return self.is_integer() || self.is_flonum();
+    }
+    pub fn is_unsigned(&self) -> bool {
+        match self.unqual() {
+            | Type::BOOL | Type::UCHAR | Type::USHORT | Type::UINT | Type::ULONG => true,
+            | _ => false,
+        }
+    }
+    // pointerや配列が指す先のtype.
+    pub fn base(&self) -> Option<&Type> {
+        match self.unqual() {
+            | Type::PTR(base) | Type::ARRAY(base, _) | Type::VLA(base, _) => Some(base),
+            | _ => None,
+        }
+    }
+    // 式の中では、配列は先頭の要素へのpointerになる.
+    pub fn decay(&self) -> Type {
+        match self.unqual() {
+            | Type::ARRAY(elem, _) | Type::VLA(elem, _) => Type::PTR(elem.clone()),
+            | t => t.clone(),
+        }
+    }
+    // 算術型とpointer型をscalar型とする.
+    pub fn is_scalar(&self) -> bool {
+        return self.is_arith() || self.is_ptr();
+    }
 }
 
 // read chars from lexer' current position and if it matches some specific string,
@@ -179,6 +505,11 @@ fn read_punct(l: &mut Lexer) -> Token {
     } else if l.expect_and_read(">=") {
         return Token::new_token(TokenKind::BE, 0, String::from(">="), l.cur_pos());
     }
+    if l.expect_and_read("...") {
+        return Token::new_token(TokenKind::PUNCT, 0, String::from("..."), l.cur_pos());
+    } else if l.expect_and_read("->") {
+        return Token::new_token(TokenKind::PUNCT, 0, String::from("->"), l.cur_pos());
+    }
     // single char.
     if l.expect_and_read("=") {
         return Token::new_token(TokenKind::PUNCT, 0, String::from("="), l.cur_pos());
@@ -204,8 +535,16 @@ fn read_punct(l: &mut Lexer) -> Token {
         return Token::new_token(TokenKind::PUNCT, 0, String::from("{"), l.cur_pos());
     } else if l.expect_and_read("}") {
         return Token::new_token(TokenKind::PUNCT, 0, String::from("}"), l.cur_pos());
+    } else if l.expect_and_read("[") {
+        return Token::new_token(TokenKind::PUNCT, 0, String::from("["), l.cur_pos());
+    } else if l.expect_and_read("]") {
+        return Token::new_token(TokenKind::PUNCT, 0, String::from("]"), l.cur_pos());
     } else if l.expect_and_read(",") {
         return Token::new_token(TokenKind::PUNCT, 0, String::from(","), l.cur_pos());
+    } else if l.expect_and_read(":") {
+        return Token::new_token(TokenKind::PUNCT, 0, String::from(":"), l.cur_pos());
+    } else if l.expect_and_read(".") {
+        return Token::new_token(TokenKind::PUNCT, 0, String::from("."), l.cur_pos());
     } else if l.expect_and_read("&") {
         return Token::new_token(TokenKind::PUNCT, 0, String::from("&"), l.cur_pos());
     } else if l.expect_and_read("*") {
@@ -214,6 +553,157 @@ fn read_punct(l: &mut Lexer) -> Token {
     panic!("Unexpected token, {}", l.cur_char());
 }
 
+// string literalの中の`\xff`の様な、UTF-8の文字にならないbyteを表す文字の先頭.
+// (Unicodeの私用領域の末尾の256文字を使い、literal_bytesで元のbyteに戻す)
+const RAW_BYTE_BASE: u32 = 0x10FF00;
+
+// escape sequenceで表したbyteを、string literalの内容の文字にする.
+fn byte_char(b: u8) -> char {
+    if b < 0x80 {
+        return b as char;
+    }
+    return char::from_u32(RAW_BYTE_BASE + b as u32).unwrap();
+}
+
+// string literalの内容を、Cの文字列としてのbyte列にする.(終端文字は含まない)
+pub fn literal_bytes(s: &str) -> Vec<u8> {
+    let mut bytes = vec![];
+    for c in s.chars() {
+        match (c as u32).checked_sub(RAW_BYTE_BASE) {
+            | Some(b) => bytes.push(b as u8),
+            | None => bytes.extend_from_slice(c.to_string().as_bytes()),
+        }
+    }
+    return bytes;
+}
+
+// `\`の次から、escape sequenceを1つ読んでその文字を返す.
+// `\x41`(16進数)と`\101`(8進数、3桁まで)は、値をbyteに切り詰める.
+// MEMO: escape sequenceの最後の文字を指して戻る.
+fn read_escape(l: &mut Lexer) -> char {
+    let c = l.next_char();
+    let radix = match c {
+        | 'x' => 16,
+        | '0'..='7' => 8,
+        | _ => {
+            return match c {
+                | 'n' => '\n',
+                | 't' => '\t',
+                | 'r' => '\r',
+                | 'a' => '\x07',
+                | 'b' => '\x08',
+                | 'f' => '\x0c',
+                | 'v' => '\x0b',
+                | 'e' => '\x1b',
+                // `\\`, `\"`, `\'`, `\?` etc.
+                | other => other,
+            };
+        }
+    };
+    let (mut value, mut len) = match c.to_digit(8) {
+        | Some(d) => (d, 1),
+        | None => (0, 0),
+    };
+    while (radix == 16 || len < 3) && l.get_nth_next(1).is_digit(radix) {
+        value = value.wrapping_mul(radix) + l.next_char().to_digit(radix).unwrap();
+        len += 1;
+    }
+    return byte_char(value as u8);
+}
+
+// read string literal which starts with `"`, and return it as STR token.
+// escape sequences are resolved here, so the token's char holds actual content.
+fn read_str_literal(l: &mut Lexer) -> Token {
+    let mut content = String::new();
+    let mut c = l.next_char();
+    while c != '"' {
+        if c == '\0' {
+            panic!("unclosed string literal.");
+        }
+        if c == '\\' {
+            c = read_escape(l);
+        }
+        content.push(c);
+        c = l.next_char();
+    }
+    // `"`の次を指す.
+    l.next();
+    return Token::new_token(TokenKind::STR, 0, content, l.cur_pos());
+}
+
+// 文字定数(`'a'`, `'\n'`)を読んで、その値のNUM tokenにする.(typeはint)
+// MEMO: charは符号付きなので、`'\xff'`は-1になる.
+fn read_char_literal(l: &mut Lexer) -> Token {
+    let start = l.cur_pos();
+    let mut c = l.next_char();
+    if c == '\\' {
+        c = read_escape(l);
+    }
+    let value = match (c as u32).checked_sub(RAW_BYTE_BASE) {
+        | Some(b) => b as u8 as i8 as i32,
+        | None => c as i32,
+    };
+    if l.next_char() != '\'' {
+        error_at(start, String::from("unclosed char literal."), 1);
+    }
+    // `'`の次を指す.
+    l.next();
+    return Token::new_token(TokenKind::NUM, value, String::new(), l.cur_pos());
+}
+
+// 浮動小数点数のliteralを読む. 整数のliteralの場合は何も読まずにNoneを返す.
+// MEMO: suffixの`f`はfloat、`l`はlong double、それ以外はdoubleになる.
+fn read_float_literal(l: &mut Lexer) -> Option<Token> {
+    let mut len = 0;
+    while l.get_nth_next(len).is_ascii_digit() {
+        len += 1;
+    }
+    let c = l.get_nth_next(len);
+    if c != '.' && c != 'e' && c != 'E' {
+        return None;
+    }
+    // `.`だけの場合や、数字の後に`.`のない識別子が続く場合は数値ではない.
+    if c == '.' && len == 0 && !l.get_nth_next(1).is_ascii_digit() {
+        return None;
+    }
+    if c != '.' && len == 0 {
+        return None;
+    }
+
+    let mut text = String::new();
+    while l.cur_char().is_ascii_digit() || l.cur_char() == '.' {
+        text.push(l.cur_char());
+        l.next();
+    }
+    if l.cur_char() == 'e' || l.cur_char() == 'E' {
+        text.push(l.cur_char());
+        l.next();
+        if l.cur_char() == '+' || l.cur_char() == '-' {
+            text.push(l.cur_char());
+            l.next();
+        }
+        while l.cur_char().is_ascii_digit() {
+            text.push(l.cur_char());
+            l.next();
+        }
+    }
+    if text.parse::<f64>().is_err() {
+        panic!("invalid floating constant: {}", text);
+    }
+    let typ = match l.cur_char() {
+        | 'f' | 'F' => {
+            l.next();
+            Type::FLOAT
+        }
+        | 'l' | 'L' => {
+            l.next();
+            Type::LDOUBLE
+        }
+        | _ => Type::DOUBLE,
+    };
+    return Some(Token::new_token(TokenKind::FNUM(typ), 0, text, l.cur_pos()));
+}
+
 pub fn tokenize(string: String) -> Vec<Token> {
     init_error(string.clone());
 
@@ -256,13 +746,42 @@ pub fn tokenize(string: String) -> Vec<Token> {
             continue;
         }
 
+        // string literal
+        // 隣り合ったstring literalは、1つのliteralに連結する.
+        if char == '"' {
+            let tok = read_str_literal(&mut l);
+            match l.token_vec.last_mut() {
+                | Some(prev) if prev.kind == TokenKind::STR => {
+                    prev.char.push_str(&tok.char);
+                    prev.pos = tok.pos;
+                }
+                | _ => l.push_tok(tok),
+            }
+            continue;
+        }
+
+        // char literal
+        if char == '\'' {
+            let tok = read_char_literal(&mut l);
+            l.push_tok(tok);
+            continue;
+        }
+
         // punct
-        if char.is_ascii_punctuation() {
+        // MEMO: `_`は識別子の一部として読む. `.5`の`.`は浮動小数点数として読む.
+        let is_float_dot = char == '.' && l.get_nth_next(1).is_ascii_digit();
+        if char.is_ascii_punctuation() && char != '_' && !is_float_dot {
             let tok = read_punct(&mut l);
             l.push_tok(tok);
             continue;
         }
 
+        // floating point number. (`1.5`, `.5`, `1e3`, `2.0f`)
+        if let Some(tok) = read_float_literal(&mut l) {
+            l.push_tok(tok);
+            continue;
+        }
+
         // number
         if char.is_ascii_digit() {
             let mut cur_num: i32 = char.to_digit(10).unwrap() as i32;
@@ -288,9 +807,8 @@ pub fn tokenize(string: String) -> Vec<Token> {
         }
 
         // local variable or C specific keyword.
-        // ひとまずアルファベットで構成された文字列なら許可する.
-        // TODO: local valは2文字目以降は数字·記号も許可する.
-        if char.is_ascii_alphabetic() {
+        // アルファベットか`_`で始まり、2文字目以降は数字も許可する.
+        if char.is_ascii_alphabetic() || char == '_' {
             let mut cur_str = l.read_to_whitespace();
             // specify token kind by cur_str.
             // TODO: use hashmap
@@ -299,7 +817,32 @@ pub fn tokenize(string: String) -> Vec<Token> {
                 | "for" => tok_kind = TokenKind::FOR,
                 | "return" => tok_kind = TokenKind::RETURN,
                 | "if" => tok_kind = TokenKind::IF,
+                | "void" => tok_kind = TokenKind::TYPE(Type::VOID),
+                | "_Bool" => tok_kind = TokenKind::TYPE(Type::BOOL),
+                | "char" => tok_kind = TokenKind::TYPE(Type::CHAR),
+                | "short" => tok_kind = TokenKind::TYPE(Type::SHORT),
                 | "int" => tok_kind = TokenKind::TYPE(Type::INT),
+                | "long" => tok_kind = TokenKind::TYPE(Type::LONG),
+                | "float" => tok_kind = TokenKind::TYPE(Type::FLOAT),
+                | "double" => tok_kind = TokenKind::TYPE(Type::DOUBLE),
+                // MEMO: signed, unsignedは他のtype keywordと組み合わせてtypeが決まる.
+                //       組み合わせはTokenReader::try_get_type()で解決する.
+                | "signed" => tok_kind = TokenKind::TYPE(Type::INT),
+                | "unsigned" => tok_kind = TokenKind::TYPE(Type::UINT),
+                | "const" | "volatile" | "__volatile__" | "restrict" | "__restrict" => {
+                    tok_kind = TokenKind::QUALIFIER
+                }
+                | "typedef" => tok_kind = TokenKind::TYPEDEF,
+                | "static" => tok_kind = TokenKind::STATIC,
+                | "extern" => tok_kind = TokenKind::EXTERN,
+                | "sizeof" => tok_kind = TokenKind::SIZEOF,
+                | "_Alignof" => tok_kind = TokenKind::ALIGNOF,
+                | "_Alignas" => tok_kind = TokenKind::ALIGNAS,
+                | "_Static_assert" => tok_kind = TokenKind::STATICASSERT,
+                | "_Generic" => tok_kind = TokenKind::GENERIC,
+                | "asm" | "__asm__" => tok_kind = TokenKind::ASM,
+                | "struct" => tok_kind = TokenKind::STRUCT,
+                | "union" => tok_kind = TokenKind::UNION,
                 | "else" => {
                     // read whitespace.
                     l.next();
@@ -329,10 +872,92 @@ pub fn tokenize(string: String) -> Vec<Token> {
     return l.token_vec;
 }
 
+// An identifier registered in a scope.
+// typedef names and variable names share the same namespace, so a variable
+// declared in an inner block hides a typedef name of the outer block.
+#[derive(Clone)]
+enum ScopeEntry {
+    Typedef(Type),
+    Var,
+}
+
+// TypedefTable holds typedef names visible from the current position.
+// The lexer can't tell whether an identifier is a type name or not, so the parser
+// consults this table to decide whether a statement starts a declaration.
+#[derive(Clone)]
+pub struct TypedefTable {
+    // scopes[0] is the file scope, and the last one is the innermost block.
+    scopes: Vec<HashMap<String, ScopeEntry>>,
+    // struct and union tags live in their own namespace, scoped like `scopes`.
+    // each tag maps to the index of STRUCT_DEFS.
+    tags: Vec<HashMap<String, usize>>,
+}
+impl TypedefTable {
+    pub fn new() -> Self {
+        // `va_list`はheaderなしで使える組み込みのtypedefとする.
+        let mut builtin = HashMap::new();
+        for name in ["va_list", "__builtin_va_list"] {
+            builtin.insert(String::from(name), ScopeEntry::Typedef(Type::VA_LIST));
+        }
+        return TypedefTable {
+            scopes: vec![builtin],
+            tags: vec![HashMap::new()],
+        };
+    }
+    pub fn enter_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+        self.tags.push(HashMap::new());
+    }
+    pub fn leave_scope(&mut self) {
+        self.scopes.pop();
+        self.tags.pop();
+    }
+    // 関数やblockの中でなく、file scopeを読んでいるかどうか.
+    pub fn is_file_scope(&self) -> bool {
+        return self.scopes.len() == 1;
+    }
+    pub fn push_typedef(&mut self, name: String, typ: Type) {
+        self.scopes
+            .last_mut()
+            .unwrap()
+            .insert(name, ScopeEntry::Typedef(typ));
+    }
+    // register variable name so that it hides the same name typedef in outer scope.
+    pub fn push_var(&mut self, name: String) {
+        self.scopes.last_mut().unwrap().insert(name, ScopeEntry::Var);
+    }
+    // search typedef name from the innermost scope.
+    pub fn find_typedef(&self, name: &str) -> Option<Type> {
+        for scope in self.scopes.iter().rev() {
+            match scope.get(name) {
+                | Some(ScopeEntry::Typedef(t)) => return Some(t.clone()),
+                | Some(ScopeEntry::Var) => return None,
+                | None => (),
+            }
+        }
+        return None;
+    }
+    pub fn push_tag(&mut self, name: String, id: usize) {
+        self.tags.last_mut().unwrap().insert(name, id);
+    }
+    // search struct or union tag from the innermost scope.
+    // if current_only is true, only the innermost scope is searched.
+    pub fn find_tag(&self, name: &str, current_only: bool) -> Option<usize> {
+        let n = if current_only { 1 } else { self.tags.len() };
+        return self
+            .tags
+            .iter()
+            .rev()
+            .take(n)
+            .find_map(|scope| scope.get(name).cloned());
+    }
+}
+
 #[derive(Clone)]
 pub struct TokenReader {
     pub tokens: Vec<Token>,
     pub cur: usize,
+    pub typedef_table: TypedefTable,
 }
 impl TokenReader {
     // return cur's index Token.
@@ -380,12 +1005,44 @@ impl TokenReader {
             false
         }
     }
+    // cur_tokがtypeの始まり(type keyword or typedef name)を指しているかどうか.
+    pub fn is_typename(&self) -> bool {
+        return self.is_typename_nth(0);
+    }
+    // curからoffset番目のtokenがtypeの始まりを指しているかどうか.
+    pub fn is_typename_nth(&self, offset: usize) -> bool {
+        let tok = self.get_next_nth_tok(offset);
+        match tok.kind {
+            | TokenKind::TYPE(_)
+            | TokenKind::QUALIFIER
+            | TokenKind::STRUCT
+            | TokenKind::UNION => true,
+            | TokenKind::IDENT => self.typedef_table.find_typedef(&tok.char).is_some(),
+            | _ => false,
+        }
+    }
     // cur_tokがtypeを指している時に、そのtypeを返す.
-    // pointerにも対応している.
+    // pointerにも対応していて、curはtypeの次のtokenまで進める.
     pub fn try_get_type(&mut self) -> Result<Type, String> {
+        let mut q = self.read_qualifiers();
         // どういうtypeか
-        let t = match self.cur_tok().kind {
-            | TokenKind::TYPE(t) => t,
+        let mut t = match self.cur_tok().kind {
+            | TokenKind::TYPE(_) => self.read_type_keywords(&mut q)?,
+            | TokenKind::STRUCT | TokenKind::UNION => self.read_struct_type()?,
+            | TokenKind::IDENT => {
+                match self.typedef_table.find_typedef(&self.cur_tok().char) {
+                    | Some(t) => {
+                        self.next();
+                        t
+                    }
+                    | None => {
+                        return Err(format!(
+                            "Expect Type, but got ident: {}",
+                            self.cur_tok().char
+                        ))
+                    }
+                }
+            }
             | _ => {
                 return Err(format!(
                     "Expect Type, but got kind: {:?}",
@@ -394,36 +1051,151 @@ impl TokenReader {
             }
         };
 
-        // pointerかpointer以外か
-        match self.get_next_tok().char.as_str() {
-            | "*" => Ok(Type::PTR(Box::new(t))),
-            | _ => Ok(t),
+        // `int const`の様に、型修飾子はtypeの後ろにも置ける.
+        q.merge(self.read_qualifiers());
+        t = t.qualify(q);
+
+        // pointerの場合は`*`の数だけ読む
+        // `*`の後の型修飾子は、そのpointer自身を修飾する.
+        while self.cur_tok().char == "*" {
+            t = Type::PTR(Box::new(t));
+            self.next();
+            t = t.qualify(self.read_qualifiers());
         }
+        return Ok(t);
     }
 
-    pub fn error(&self, input_pos: usize, message: String, tok_len: usize) -> ! {
-        // TODO: refactor
-        let str = display_around_pos(input_pos);
-        print!("input pos: {}\n", input_pos);
-        print!("tok len: {}\n", tok_len);
-        print!("Err place:\n");
-        print!("{}\n", str);
-
-        if input_pos < 9 {
-            for _ in 0..input_pos {
-                print!(" ");
+    // struct_type = ( "struct" | "union" ) ( &ident | &ident? struct_body )
+    // 見えるscopeに宣言されていないtagは、このscopeで不完全型として宣言する.
+    // `struct tag;`と`struct tag { ... }`は、外側のscopeに同じtagがあっても、このscopeで新しく宣言する.
+    fn read_struct_type(&mut self) -> Result<Type, String> {
+        let is_union = self.cur_tok().kind == TokenKind::UNION;
+        self.next();
+        if self.cur_tok().kind != TokenKind::IDENT {
+            if !self.expect("{") {
+                return Err(format!(
+                    "expect struct tag, but got kind: {:?}",
+                    self.cur_tok().kind
+                ));
             }
-        } else {
-            for _ in 0..10 {
-                print!(" ");
+            // tagのないstruct.
+            let id = new_struct(String::from("<anonymous>"), is_union);
+            crate::parse::parse_struct_body(self, id);
+            return Ok(Type::STRUCT(id));
+        }
+        let tag = self.cur_tok().char;
+        let tag_pos = self.cur_input_pos();
+        self.next();
+        let current_only = self.expect(";") || self.expect("{");
+        let id = match self.typedef_table.find_tag(&tag, current_only) {
+            | Some(id) => id,
+            | None => {
+                let id = new_struct(tag.clone(), is_union);
+                self.typedef_table.push_tag(tag.clone(), id);
+                id
             }
+        };
+        if get_struct_def(id).is_union != is_union {
+            return Err(format!("`{}` defined as wrong kind of tag.", tag));
+        }
+        if self.expect("{") {
+            if !Type::STRUCT(id).is_incomplete() {
+                self.error(
+                    tag_pos,
+                    format!("redefinition of `{}`.", struct_name(id)),
+                    tag.len(),
+                );
+            }
+            crate::parse::parse_struct_body(self, id);
+        }
+        return Ok(Type::STRUCT(id));
+    }
+
+    fn read_qualifiers(&mut self) -> Qualifiers {
+        let mut q = Qualifiers::default();
+        while self.cur_tok().kind == TokenKind::QUALIFIER {
+            match self.cur_tok().char.as_str() {
+                | "const" => q.is_const = true,
+                | "volatile" | "__volatile__" => q.is_volatile = true,
+                | _ => q.is_restrict = true,
+            }
+            self.next();
+        }
+        return q;
+    }
+
+    // `unsigned long int`の様に連続したtype keywordを読んで、1つのtypeにまとめる.
+    // 間に置かれた型修飾子(`unsigned const int`)はqに加える.
+    fn read_type_keywords(&mut self, q: &mut Qualifiers) -> Result<Type, String> {
+        let (mut void, mut char, mut short, mut int, mut long) = (0, 0, 0, 0, 0);
+        let mut bool = 0;
+        let (mut signed, mut unsigned) = (0, 0);
+        let (mut float, mut double) = (0, 0);
+        loop {
+            match self.cur_tok().kind {
+                | TokenKind::TYPE(_) => (),
+                | TokenKind::QUALIFIER => {
+                    q.merge(self.read_qualifiers());
+                    continue;
+                }
+                | _ => break,
+            }
+            match self.cur_tok().char.as_str() {
+                | "void" => void += 1,
+                | "_Bool" => bool += 1,
+                | "char" => char += 1,
+                | "short" => short += 1,
+                | "int" => int += 1,
+                | "long" => long += 1,
+                | "signed" => signed += 1,
+                | "unsigned" => unsigned += 1,
+                | "float" => float += 1,
+                | "double" => double += 1,
+                | s => return Err(format!("unknown type keyword: {}", s)),
+            }
+            self.next();
+        }
+
+        if signed + unsigned > 1 || (void > 0 && void + char + short + int + long > 1)
+        {
+            return Err(String::from("invalid type"));
         }
-        for _ in 0..tok_len {
-            print!("^");
+        // `_Bool`は、他のtype keywordと組み合わせられない.
+        if bool > 0 {
+            let others =
+                void + char + short + int + long + signed + unsigned + float + double;
+            if bool > 1 || others > 0 {
+                return Err(String::from("invalid type"));
+            }
+            return Ok(Type::BOOL);
+        }
+        // 浮動小数点型は、`long double`の他は、他のtype keywordと組み合わせられない.
+        if float + double > 0 {
+            let others = void + char + short + int + signed + unsigned;
+            return match (float, double, long, others) {
+                | (1, 0, 0, 0) => Ok(Type::FLOAT),
+                | (0, 1, 0, 0) => Ok(Type::DOUBLE),
+                | (0, 1, 1, 0) => Ok(Type::LDOUBLE),
+                | _ => Err(String::from("invalid type")),
+            };
         }
-        println!("");
-        println!("Err message: {}", message);
-        panic!()
+        let t = match (void, char, short, int, long) {
+            | (1, 0, 0, 0, 0) if signed + unsigned == 0 => Type::VOID,
+            | (0, 1, 0, 0, 0) if unsigned == 1 => Type::UCHAR,
+            | (0, 1, 0, 0, 0) => Type::CHAR,
+            | (0, 0, 1, 0 | 1, 0) if unsigned == 1 => Type::USHORT,
+            | (0, 0, 1, 0 | 1, 0) => Type::SHORT,
+            | (0, 0, 0, 0 | 1, 0) if unsigned == 1 => Type::UINT,
+            | (0, 0, 0, 0 | 1, 0) => Type::INT,
+            | (0, 0, 0, 0 | 1, 1 | 2) if unsigned == 1 => Type::ULONG,
+            | (0, 0, 0, 0 | 1, 1 | 2) => Type::LONG,
+            | _ => return Err(String::from("invalid type")),
+        };
+        return Ok(t);
+    }
+
+    pub fn error(&self, input_pos: usize, message: String, tok_len: usize) -> ! {
+        error_at(input_pos, message, tok_len);
     }
 }
 
@@ -431,6 +1203,7 @@ pub fn new_token_reader(token: Vec<Token>) -> TokenReader {
     return TokenReader {
         tokens: token,
         cur: 0,
+        typedef_table: TypedefTable::new(),
     };
 }
 
@@ -451,7 +1224,7 @@ fn print_token_info(tok: &Token) {
         | TokenKind::NUM => {
             println!("kind: {:?}, val: {}, pos: {}", tok.kind, tok.value, tok.pos)
         }
-        | TokenKind::PUNCT | TokenKind::IDENT => {
+        | TokenKind::PUNCT | TokenKind::IDENT | TokenKind::STR | TokenKind::FNUM(_) => {
             println!("kind: {:?}, char: {}, pos: {}", tok.kind, tok.char, tok.pos)
         }
         | _ => {
@@ -4,7 +4,8 @@ test() {
     input=$1
     expect=$2
 
-    ./target/debug/rcc --std "$input"
+    # 3番目以降の引数は、rccにそのまま渡す.
+    ./target/debug/rcc --std "$input" "${@:3}"
     gcc -o gen gen.s
 
     ./gen
@@ -18,6 +19,36 @@ test() {
     fi
 }
 
+# 生成したgen.sに、patternに一致する行がexpect個あるかを確認する.
+test_asm_count() {
+    input=$1
+    pattern=$2
+    expect=$3
+
+    ./target/debug/rcc --std "$input" "${@:4}"
+    count=$(grep -c -- "$pattern" gen.s)
+    if [ $count -eq $expect ]
+    then
+        echo "$input -> $count x $pattern ok"
+    else
+        echo "Fail, expect $expect x $pattern, got $count"
+    fi
+}
+
+# rccが出す警告やerrorに、期待する文字列が含まれているかを確認する.
+test_diag() {
+    input=$1
+    expect=$2
+
+    output=$(./target/debug/rcc --std "$input" "${@:3}" 2>&1)
+    if [[ "$output" == *"$expect"* ]]
+    then
+        echo "$input -> $expect ok"
+    else
+        echo "Fail, expect \"$expect\", got \"$output\""
+    fi
+}
+
 # # exit code test.
 test "int main(){ 42; }" 42
 
@@ -295,4 +326,1641 @@ int main() {
     int b = foo(&a);
     return b;
 }
-" 36
\ No newline at end of file
+" 36
+test "
+typedef int myint;
+int main() {
+    myint a = 3;
+    typedef myint *ptr;
+    ptr b = &a;
+    return *b;
+}
+" 3
+test "
+typedef int T;
+T add(T a, T b) {
+    return a + b;
+}
+int main() {
+    T a = 2;
+    {
+        int T = 5;
+        a = a + T;
+    }
+    T b = add(a, 1);
+    return b;
+}
+" 8
+
+# struct and union tags in typedef and declarations.
+test "
+typedef struct node Node;
+typedef struct node *NodePtr;
+int is_null(Node *n) {
+    return n == 0;
+}
+int main() {
+    Node *p = 0;
+    NodePtr q = p;
+    struct node *node = q;
+    return is_null(node) + sizeof(Node *) + sizeof(union u *);
+}
+" 17
+test "
+typedef struct node Node;
+int main() {
+    Node *p = 0;
+    int a = _Generic(p, struct node *: 1, default: 2);
+    int b = 0;
+    {
+        struct node;
+        b = _Generic(p, struct node *: 1, default: 2);
+    }
+    return a * 10 + b;
+}
+" 12
+test_diag "
+typedef struct node Node;
+int main() {
+    Node n;
+    return 0;
+}
+" "storage size of \`n\` isn't known."
+test_diag "
+struct s *p;
+union s *q;
+int main() {
+    return 0;
+}
+" "\`s\` defined as wrong kind of tag."
+
+# struct and union definitions, member access and struct assignment.
+test "
+struct P {
+    char c;
+    int x;
+    long y;
+};
+union U {
+    char c;
+    long l;
+};
+struct S {
+    char c[3];
+    short s;
+};
+int main() {
+    return sizeof(struct P) + sizeof(union U) * 2 + sizeof(struct S) * 10 + _Alignof(struct S);
+}
+" 94
+test "
+struct node {
+    int val;
+    struct node *next;
+};
+int sum(struct node *n) {
+    if (n == 0)
+        return 0;
+    return n->val + sum(n->next);
+}
+int main() {
+    struct node a;
+    struct node b;
+    struct node c;
+    a.val = 1;
+    a.next = &b;
+    b.val = 2;
+    b.next = &c;
+    c.val = 3;
+    c.next = 0;
+    return sum(&a) + a.next->next->val * 10;
+}
+" 36
+test "
+struct in {
+    int a[3];
+    short s;
+};
+struct out {
+    char c;
+    struct in in;
+} g;
+int main() {
+    struct out arr[2];
+    arr[1].in.a[2] = 4;
+    arr[1].in.s = 5;
+    g.in.a[1] = 6;
+    struct out *p = arr;
+    int *ip = p[1].in.a;
+    (*p).c = 7;
+    struct {
+        int x;
+    } anon;
+    anon.x = 8;
+    return ip[2] + p[1].in.s * 10 + g.in.a[1] + arr[0].c + anon.x + sizeof(struct out);
+}
+" 95
+test "
+union U {
+    char c;
+    int i;
+};
+int main() {
+    union U u;
+    u.i = 0;
+    u.c = 3;
+    return u.i;
+}
+" 3
+test "
+struct P {
+    int x;
+    long y;
+    char c[4];
+};
+int main() {
+    struct P p;
+    p.x = 1;
+    p.y = 2;
+    p.c[3] = 3;
+    struct P q = p;
+    struct P r;
+    r = q;
+    q.x = 10;
+    struct P *pp = &r;
+    pp->y = pp->y + 20;
+    return p.x + p.y + q.x + r.x * 100 + r.y + r.c[3];
+}
+" 138
+test "
+int main() {
+    double d = .5;
+    return d * 4;
+}
+" 2
+test_diag "
+struct P {
+    int x;
+};
+int main() {
+    struct P p;
+    p.y = 1;
+    return 0;
+}
+" "no member named \`y\` in \`struct P\`."
+test_diag "
+int main() {
+    int a;
+    a.x = 1;
+    return 0;
+}
+" "request for member \`x\` in something not a structure or union."
+test_diag "
+struct Q;
+int f(struct Q *q) {
+    return q->x;
+}
+int main() {
+    return 0;
+}
+" "invalid use of incomplete type \`struct Q\`."
+test_diag "
+struct P {
+    int x;
+};
+struct P {
+    int y;
+};
+int main() {
+    return 0;
+}
+" "redefinition of \`struct P\`."
+test_diag "
+struct Q;
+struct P {
+    struct Q q;
+};
+int main() {
+    return 0;
+}
+" "field \`q\` has incomplete type."
+test_diag "
+struct P {
+    int x;
+    int x;
+};
+int main() {
+    return 0;
+}
+" "duplicate member \`x\`."
+test_diag "
+struct P {
+    int x;
+};
+int main() {
+    const struct P p;
+    p.x = 1;
+    return 0;
+}
+" "assignment of read-only location."
+test_diag "
+struct P {
+    int x;
+};
+struct R {
+    int x;
+};
+int main() {
+    struct P p;
+    struct R r;
+    p = r;
+    return 0;
+}
+" "incompatible types when assigning to \`struct P\` from \`struct R\`."
+
+# bit-fields are packed like gcc, sign-extended on read and merged on write.
+test "
+struct B {
+    int a : 3;
+    unsigned b : 5;
+    int c : 20;
+    long d : 40;
+    char e;
+    int : 0;
+    short f : 4;
+};
+int main() {
+    struct B s;
+    s.a = 5;
+    s.b = 33;
+    s.c = 524287;
+    s.d = 123456789;
+    s.e = 9;
+    s.f = 15;
+    int ok = (s.a == 0 - 3) + (s.b == 1) * 2 + (s.f == 0 - 1) * 4;
+    ok = ok + (s.c == 524287) * 8 + (s.d == 123456789) * 16 + (s.e == 9) * 32;
+    return ok + sizeof(struct B) * 8;
+}
+" 255
+test "
+struct C {
+    char c;
+    int x : 3;
+};
+struct D {
+    char c;
+    int : 3;
+};
+struct E {
+    char c;
+    int : 0;
+};
+union U {
+    char c;
+    int x : 3;
+};
+int main() {
+    return sizeof(struct C) + sizeof(struct D) * 10 + sizeof(struct E) * 20 + sizeof(union U) * 30;
+}
+" 224
+test "
+struct B {
+    int a : 3;
+    unsigned b : 5;
+    int c : 20;
+};
+union V {
+    struct B s;
+    int raw;
+};
+int main() {
+    union V v;
+    v.raw = 0;
+    v.s.a = 5;
+    v.s.b = 33;
+    v.s.c = 524287;
+    int packed = v.raw == 134217485;
+    v.raw = 54;
+    return packed + (v.s.a == 0 - 2) * 2 + v.s.b * 10;
+}
+" 63
+test_diag "
+struct P {
+    double x : 3;
+};
+int main() {
+    return 0;
+}
+" "bit-field \`x\` has invalid type."
+test_diag "
+struct P {
+    char x : 9;
+};
+int main() {
+    return 0;
+}
+" "width of \`x\` exceeds its type."
+test_diag "
+struct P {
+    int x : 0;
+};
+int main() {
+    return 0;
+}
+" "zero width for bit-field \`x\`."
+test_diag "
+int n;
+struct P {
+    int x : n;
+};
+int main() {
+    return 0;
+}
+" "bit-field \`x\` width not an integer constant."
+
+# flexible array members and _Alignas on struct members.
+test "
+void *calloc(long n, long size);
+struct S {
+    char c;
+    int data[];
+};
+struct L {
+    short n;
+    long xs[];
+};
+int main() {
+    struct S *p = calloc(1, sizeof(struct S) + 4 * 4);
+    p->c = 2;
+    p->data[3] = 7;
+    return p->c + p->data[3] + sizeof(struct S) * 10 + sizeof(struct L) * 20;
+}
+" 209
+test "
+struct A {
+    char c;
+    _Alignas(16) int x;
+    char d;
+};
+struct B {
+    _Alignas(8) char c;
+};
+struct C {
+    char c;
+    _Alignas(long) short s;
+};
+int main() {
+    struct A a;
+    a.x = 1;
+    a.d = 2;
+    return a.x + a.d + sizeof(struct A) + _Alignof(struct A) * 2 + sizeof(struct B) * 4 + sizeof(struct C);
+}
+" 115
+test_diag "
+struct S {
+    int data[];
+    int n;
+};
+int main() {
+    return 0;
+}
+" "flexible array member \`data\` not at end of struct."
+test_diag "
+struct S {
+    int data[];
+};
+int main() {
+    return 0;
+}
+" "flexible array member \`data\` in a struct with no named members."
+test_diag "
+union U {
+    int n;
+    int data[];
+};
+int main() {
+    return 0;
+}
+" "flexible array member \`data\` in union."
+test_diag "
+struct S {
+    _Alignas(2) int x;
+};
+int main() {
+    return 0;
+}
+" "requested alignment 2 is not allowed for \`x\`."
+test_diag "
+struct S {
+    _Alignas(8) int x : 3;
+};
+int main() {
+    return 0;
+}
+" "alignment specified for bit-field \`x\`."
+
+test "
+int main() {
+    int a = 3;
+    int *p = &a;
+    return sizeof(int) + sizeof a + sizeof(p) + sizeof(int *);
+}
+" 24
+test "
+int main() {
+    int x = 3;
+    char c = 1;
+    long n = sizeof(x = 5) + sizeof(c = 300) * 10;
+    int a[2];
+    a[0] = 7;
+    return x + n + sizeof(a[0] = 1) * 100 + a[0];
+}
+" 168
+test "
+typedef int *iptr;
+int foo(iptr p) {
+    return sizeof(*p) + _Alignof(iptr);
+}
+int main() {
+    int my_val1 = 2;
+    return (foo(&my_val1) + sizeof my_val1) * 2 / (1 + 1);
+}
+" 16
+
+
+# cast.
+test "int main(){ return (char)300; }" 44
+test "int main(){ int a = 255; char b = a; return b == (char)a; }" 1
+test "
+int main() {
+    int a = 511;
+    char c = (char)a;
+    unsigned char uc = (unsigned char)a;
+    long l = (long)c;
+    if (l < 0) {
+        return uc;
+    }
+    return 1;
+}
+" 255
+test "
+int main() {
+    unsigned int u = (unsigned int)(0 - 1);
+    long l = u;
+    if (l > 0) {
+        return (short)(u / 65536) == (short)65535;
+    }
+    return 0;
+}
+" 1
+test "
+int main() {
+    int a = 7;
+    long p = (long)&a;
+    int *q = (int *)p;
+    (void)a;
+    return *q + sizeof(unsigned long int) + sizeof(short) + sizeof(char);
+}
+" 18
+
+# void.
+test "
+void nop(void) {
+}
+int g(int *p, int v) {
+    if (v > 10) {
+        return;
+    }
+    return *p + v;
+}
+void skip(int v) {
+    if (v > 10) {
+        return;
+    }
+    v = 3;
+}
+int main() {
+    int a = 1;
+    nop();
+    skip(20);
+    skip(7);
+    return g(&a, 7);
+}
+" 8
+test "
+int main() {
+    int a = 9;
+    void *vp = &a;
+    int *ip = vp;
+    return *ip;
+}
+" 9
+test "
+int count(void) {
+    int sum = 0;
+    for(int i = 0; i < 5; i = i + 1;) {
+        sum = sum + i;
+        i;
+    }
+    sum;
+}
+int main() {
+    return count();
+}
+" 10
+
+# prototype.
+test "
+int add(int, int);
+char narrow(long x);
+int main() {
+    return add(narrow(1027), 2);
+}
+int add(int a, int b) {
+    return a + b;
+}
+char narrow(long x) {
+    return x;
+}
+" 5
+test "
+long twice(long);
+void *id(void *p);
+int main() {
+    int a = 21;
+    int *p = id(&a);
+    return twice(*p);
+}
+long twice(long v) {
+    return v * 2;
+}
+void *id(void *p) {
+    return p;
+}
+" 42
+
+# more than six arguments.
+test "
+int sum8(int a, int b, int c, int d, int e, int f, int g, int h) {
+    return a + b + c + d + e + f + g * 2 + h * 3;
+}
+int sub(int a, int b) {
+    return a - b;
+}
+int main() {
+    return sum8(1, 2, 3, 4, sub(10, 5), 6, sub(20, 13), 8);
+}
+" 59
+
+# only one branch of if statement is executed.
+test "
+int main() {
+    int a = 0;
+    if (1 < 2) a = 1; else if (2 < 3) a = 2; else a = 3;
+    return a;
+}
+" 1
+test "
+int main() {
+    int a = 0;
+    if (2 < 1) a = 1; else if (1 < 2) a = 2; else a = 3;
+    return a;
+}
+" 2
+test "
+int main() {
+    int a = 0;
+    if (a == 0) a = 1; else if (a == 1) a = 2;
+    return a;
+}
+" 1
+
+# conditions after the taken branch are not evaluated.
+test "
+int count(int *p) {
+    p[0] = p[0] + 1;
+    return 1;
+}
+int main() {
+    int n = 0;
+    if (1 < 2) n = n + 10; else if (count(&n) == 1) n = n + 100; else n = n + 1000;
+    return n;
+}
+" 10
+test "
+int f(int a) {
+    return a;
+}
+int main() {
+    int sum = 0;
+    for (int i = 0; i < 4; i = i + 1;) {
+        f(i);
+        sum = sum + f(i) + f(1);
+    }
+    return sum;
+}
+" 10
+
+# rsp is 16-byte aligned at a call made while values are on the stack.
+# (printf with %f uses SSE instructions and crashes on a misaligned stack)
+test "
+int printf(char *fmt, ...);
+int main() {
+    return 1 + printf(\"%f\\n\", 1.5) + (2 + printf(\"%f\\n\", 2.5));
+}
+" 21
+
+# variadic function call.
+test "
+int printf(char *fmt, ...);
+int puts(char *s);
+void *malloc(long size);
+void free(void *p);
+int main() {
+    char *p = malloc(16);
+    free(p);
+    puts(\"hello\");
+    return printf(\"%d-%s\n\", 42, \"ok\");
+}
+" 6
+
+# string literals are char arrays that decay to pointers.
+test "
+int printf(char *fmt, ...);
+int strcmp(const char *a, const char *b);
+int len(char *s) {
+    int n = 0;
+    for (int i = 0; s[i] != 0; i = i + 1;) {
+        n = n + 1;
+    }
+    return n;
+}
+int main() {
+    char s[] = \"abc\";
+    char t[3] = \"xyz\";
+    char *p;
+    p = \"hello\";
+    const char *q = \"q\";
+    printf(\"%s\n\", \"va\");
+    return sizeof(\"abc\") + sizeof(\"\") * 10 + sizeof(s) * 100 + sizeof(t) * 1000 - 3400 + len(\"abcd\") + strcmp(p, \"hello\") + strcmp(q, \"q\") + len((char *)\"xy\");
+}
+" 20
+
+# escape sequences, char literals and adjacent string literals.
+test "
+int strcmp(char *a, char *b);
+char *g = \"ab\" \"cd\";
+int main() {
+    char s[] = \"\\x41\\102\\x43\\0x\\101\\?\\\"\\\\\\1234\";
+    unsigned char u[] = \"\\xff\\200\";
+    char *t = \"con\" \"cat\" \"\\x21\";
+    int c = 'a' + '\\n' + '\\'' + '\\x41' + '\\101' + '\\0';
+    char n = '\\xff';
+    return sizeof(s) + sizeof(\"ab\" \"c\") * 10 + c + n + u[0] + u[1] + strcmp(t, \"concat!\") + strcmp(g, \"abcd\") + sizeof(u) * 3 + s[10];
+}
+" 3
+
+# variadic function definition.
+test "
+int vprintf(char *fmt, va_list ap);
+int log_fmt(char *fmt, ...) {
+    va_list ap;
+    va_start(ap, fmt);
+    int n = vprintf(fmt, ap);
+    va_end(ap);
+    return n;
+}
+long sum(int n, ...) {
+    va_list ap;
+    va_start(ap, n);
+    long s = 0;
+    for (int i = 0; i < n; i = i + 1;) {
+        s = s + va_arg(ap, long);
+    }
+    va_end(ap);
+    return s;
+}
+int main() {
+    log_fmt(\"%d-%s\n\", 42, \"ok\");
+    return sum(8, 1, 2, 3, 4, 5, 6, 7, 8) + sizeof(va_list);
+}
+" 60
+test "
+#include <stdarg.h>
+int twice(int n, ...) {
+    va_list ap;
+    va_list aq;
+    va_start(ap, n);
+    va_copy(aq, ap);
+    int s = 0;
+    for (int i = 0; i < n; i = i + 1;) {
+        s = s + va_arg(ap, int);
+    }
+    for (int j = 0; j < n; j = j + 1;) {
+        s = s + va_arg(aq, int) * 10;
+    }
+    va_end(aq);
+    __builtin_va_copy(aq, ap);
+    va_end(ap);
+    va_end(aq);
+    return s;
+}
+int main() {
+    return twice(8, 1, 2, 3, 4, 5, 6, 7, 8);
+}
+" 140
+
+# function pointer.
+test "
+int add(int a, int b) {
+    return a + b;
+}
+int sub(int a, int b) {
+    return a - b;
+}
+int apply(int (*op)(int, int), int a, int b) {
+    return op(a, b);
+}
+typedef int (*binop)(int, int);
+int main() {
+    int (*fp)(int, int) = add;
+    binop g = &sub;
+    int x = fp(3, 4) + (*g)(10, 2);
+    fp = sub;
+    return x + apply(fp, 9, 1) + apply(add, 1, 2) + sizeof(fp);
+}
+" 34
+test "
+int add(int a, int b) {
+    return a + b;
+}
+int sub(int a, int b) {
+    return a - b;
+}
+int inc(int x) {
+    return x + 1;
+}
+int (*gp)(int) = inc;
+int (*gt[2])(int, int) = {add, sub};
+struct Ops {
+    int (*f)(int);
+    int (*g[2])(int, int);
+};
+int (*pick(int i))(int, int) {
+    if (i == 0) {
+        return add;
+    }
+    return sub;
+}
+int main() {
+    int (*t[2])(int, int) = {add, sub};
+    struct Ops o = {inc, {add, sub}};
+    struct Ops *p = &o;
+    return t[0](1, 2) + (*t[1])(10, 4) * 10 + o.f(5) * 100 + (*o.f)(1) + p->g[1](9, 2) + pick(1)(20, 3) + gp(40) + gt[1](3, 1);
+}
+" 220
+test "
+int (*pick(int i))(int, int);
+typedef int (*binop)(int, int);
+binop pick2(void);
+int mul(int a, int b) {
+    return a * b;
+}
+int main() {
+    return pick(0)(3, 4) + pick2()(2, 5);
+}
+int (*pick(int i))(int, int) {
+    return mul;
+}
+binop pick2(void) {
+    return mul;
+}
+" 22
+
+# declaration without initializer.
+test "
+int main() {
+    int a;
+    long b;
+    a = 3;
+    if (a == 3) b = 4; else b = 5;
+    return a + b;
+}
+" 7
+test "
+int main() {
+    int sum;
+    sum = 0;
+    for (int i = 0; i < 5; i = i + 1;) {
+        int x;
+        x = i * 2;
+        sum = sum + x;
+    }
+    return sum;
+}
+" 20
+test_diag "
+int main() {
+    int a;
+    int b;
+    b = 1;
+    if (b)
+        a = 2;
+    return a + b;
+}
+" "<stdin>:8: warning: \`a\` may be used uninitialized in function main."
+
+# static and extern.
+test "
+static int counter() {
+    static int count = 10 * 2;
+    static long calls;
+    count = count + 1;
+    calls = calls + 1;
+    return count + calls;
+}
+int main() {
+    counter();
+    counter();
+    return counter();
+}
+" 26
+test "
+extern int optind;
+int main() {
+    extern int opterr;
+    return optind + opterr;
+}
+" 2
+test "
+int printf(char *fmt, ...);
+int counter;
+static int g = 1;
+extern int later;
+int later = 3;
+static char c;
+double d = 1.5;
+int inc() {
+    counter = counter + 1;
+    return counter;
+}
+int shadow() {
+    int g = 100;
+    return g;
+}
+int main() {
+    inc();
+    inc();
+    g = g + 10;
+    c = 4;
+    int *p = &counter;
+    p[0] = p[0] + 1;
+    printf(\"%d %d %d %d %f\n\", counter, g, later, c, d);
+    return counter + g + later + c + (d == 1.5) + shadow();
+}
+" 122
+test "
+int a[3];
+long n = sizeof(a) + 1;
+int g;
+int g = 4;
+extern int g;
+int main() {
+    a[1] = 4;
+    return n + a[1] + g;
+}
+" 21
+test_asm_count "int a[5]; static long z = 1; int main() { return a[0] + z; }" "^\.size \(a, 20\|z, 8\)$" 2
+
+# type qualifiers.
+test "
+int puts(const char *s);
+int len(const char * const s, volatile int n) {
+    const char *p = s;
+    return n + sizeof(*p);
+}
+int main() {
+    const int a = 3;
+    int const b = 4;
+    unsigned const int c = 5;
+    const int *pa = &a;
+    char * restrict q = 0;
+    volatile long v = 6;
+    puts(\"const\");
+    return a + b + c + *pa + v + len(\"x\", 1) + sizeof(const long);
+}
+" 31
+test_diag "int main() {
+    const int a = 3;
+    a = 4;
+    return a;
+}" "Err place: <stdin>:3"
+test_diag "int f(char *s) { return 0; }
+int main() {
+    const char *p = \"x\";
+    char *q = p;
+    return f(p);
+}" "<stdin>:4: warning: conversion discards \`const\` qualifier"
+test_diag "int f(char *s) { return 0; }
+int main() {
+    const char *p = \"x\";
+    return f(p);
+}" "<stdin>:4: warning: conversion discards \`const\` qualifier"
+
+# const global variables are placed in .rodata.
+test_asm_count "const int g = 5; const char s[] = \"ab\"; int h = 1; int main() { return g + h; }" "^\.section \.rodata$" 2
+test "const int g = 5; const int t[3] = {1, 2, 3}; int main() { return g + t[2]; }" 8
+
+# every access to a volatile object is a separate load or store.
+test "
+int main() {
+    volatile int v = 1;
+    int x = v;
+    x = v + v;
+    v = x;
+    volatile char buf[2];
+    buf[0] = 3;
+    buf[1] = buf[0];
+    volatile int *p = &v;
+    x = p[0];
+    p[0] = 5;
+    int y = *p;
+    return x + y + v + buf[1];
+}
+" 15
+test_asm_count "
+int main() {
+    volatile int v = 1;
+    int x = v;
+    x = v + v;
+    v = x;
+    volatile char buf[2];
+    buf[0] = 3;
+    buf[1] = buf[0];
+    volatile int *p = &v;
+    x = p[0];
+    p[0] = 5;
+    int y = *p;
+    return x + y + v + buf[1];
+}
+" "(%rax), %[er]ax # volatile" 8
+test_asm_count "
+int main() {
+    volatile int v = 1;
+    int x = v;
+    x = v + v;
+    v = x;
+    volatile char buf[2];
+    buf[0] = 3;
+    buf[1] = buf[0];
+    volatile int *p = &v;
+    x = p[0];
+    p[0] = 5;
+    int y = *p;
+    return x + y + v + buf[1];
+}
+" ", (%rdi) # volatile" 5
+test_asm_count "
+int main() {
+    int v = 1;
+    int x = v;
+    v = x + v;
+    return v;
+}
+" "# volatile" 0
+
+# arrays and brace initializers.
+test "
+int sum(int *p, int n) {
+    int s = 0;
+    for(int i = 0; i < n; i = i + 1;) {
+        s = s + p[i];
+    }
+    return s;
+}
+int main() {
+    int a[] = {1, 2, 3};
+    int b[2][3] = {{1, 2}, 4, 5, 6};
+    int c[5] = {[2] = 5, 7};
+    char s[] = \"abc\";
+    static int d[3] = {10, 20};
+    static int z[4];
+    a[1] = a[0] + c[3];
+    d[2] = d[2] + 1;
+    return sum(a, 3) + b[0][1] + b[1][2] + c[2] + c[0] + s[1] - 98 + sizeof a + sizeof s + d[1] + d[2] + z[3] + (c + 4 - c);
+}
+" 66
+test "
+int g[3] = {1, 2, 3};
+int h[] = {4, 5};
+char s[] = \"xy\";
+int m[2][2] = {{1, 2}, [1] = {3, 4}};
+int main() {
+    return g[0] + g[1] * 2 + g[2] * 3 + sizeof h + h[1] + s[1] - 121 + m[1][1];
+}
+" 31
+
+# struct initializers and member designators.
+test "
+struct P {
+    int x;
+    char c;
+    long y;
+};
+struct N {
+    struct P p;
+    int arr[2];
+};
+int main() {
+    struct P a = {1, 2, 3};
+    struct P b = {.y = 7, .x = 5, 6};
+    struct P c = {};
+    struct N n = {{1, 2, 3}, {4, 5}};
+    struct N e = {1, 2, 3, 4, 5};
+    struct P ps[2] = {[1] = {.c = 9}};
+    return a.x + a.c + a.y + b.x + b.c * 2 + b.y + c.x + c.y + n.p.y + n.arr[1] + e.p.c + e.arr[0] + ps[1].c + ps[0].c;
+}
+" 53
+test "
+struct P {
+    int x;
+    char c;
+    long y;
+};
+struct B {
+    int a : 3;
+    unsigned b : 5;
+    int c : 20;
+};
+union U {
+    char c;
+    int i;
+};
+struct P gp = {1, 2, 3};
+struct P gd = {.y = 7, .x = 5};
+struct B gb = {5, 33, 1000};
+union U gu = {.i = 258};
+union U gf = {3};
+int main() {
+    struct B lb = {.c = 7, .a = 5, 33};
+    union U lu = {.i = 258};
+    int ok = (gb.a == 0 - 3) + (gb.b == 1) * 2 + (gb.c == 1000) * 4;
+    ok = ok + (lb.a == 0 - 3) * 8 + (lb.b == 1) * 16 + (lb.c == 7) * 32;
+    return ok + gp.x + gp.c + gp.y + gd.x + gd.c + gd.y + gu.i + gf.i + lu.i - 512;
+}
+" 88
+test "
+struct In { int a; int b; int c; };
+struct Out { int x; struct In in; int y; };
+struct Out g = {.in.b = 5, 6, .y = 9};
+int ga[3][2] = {[2][1] = 7, [0] = 1, 2};
+struct P { int x; int y; } ps[3] = {[1].y = 4, 5};
+struct Q { int n; int arr[3]; } q = {.arr[1] = 8, 3};
+int main() {
+    struct Out o = {1, .in.c = 3, .in.a = 2, 4};
+    int a[3][2] = {[1][1] = 7, 1};
+    int ok = g.in.b * 1000 + g.in.c * 100 + g.y + ga[2][1] * 10000 + ga[0][0] + ga[0][1] * 2;
+    return ok + ps[1].y + ps[2].x * 3 + q.arr[1] + q.arr[2] + o.in.a + o.in.c + o.y * 7 + a[1][1] + a[2][0] * 5 + o.in.b;
+}
+" 145
+test_diag "
+struct P {
+    int x;
+};
+int main() {
+    struct P p = {.y = 1};
+    return 0;
+}
+" "unknown member \`y\` in initializer."
+test_diag "
+struct P {
+    int x;
+};
+int main() {
+    struct P p = {1, 2};
+    return 0;
+}
+" "excess elements in struct initializer."
+test_diag "
+union U {
+    int x;
+    char c;
+};
+int main() {
+    union U u = {1, 2};
+    return 0;
+}
+" "excess elements in struct initializer."
+test_diag "
+struct S {
+    int n;
+    int data[];
+};
+int main() {
+    struct S s = {1, {2}};
+    return 0;
+}
+" "initialization of flexible array member \`data\`."
+
+# compound literals.
+test "
+int sum(int *p, int n) {
+    int s = 0;
+    for(int i = 0; i < n; i = i + 1;) {
+        s = s + p[i];
+    }
+    return s;
+}
+int main() {
+    int *p = (int[]){1, 2, 3};
+    int x = (int){5};
+    const char *s = (const char[]){\"hi\"};
+    return sum(p, 3) + sum((int[4]){[3] = 10}, 4) + x + s[1] + sizeof((long[]){1, 2}) + (char){300};
+}
+" 186
+test "
+struct P { int x; int y; };
+struct Q { struct P a; int z; };
+int main() {
+    struct Q q = {(struct P){3, 4}, 5};
+    struct P l;
+    l = (struct P){6, 7};
+    return q.a.x + q.a.y * 2 + q.z * 3 + l.x + l.y * 10 + (struct P){8, 9}.y + sizeof((struct P){1, 2});
+}
+" 119
+test "
+struct P { int x; int y; };
+int *p = (int[]){1, 2, 3};
+struct P g = (struct P){4, 5};
+struct Q { int *a; struct P b; } q = {(int[]){7, 8}, (struct P){9, 10}};
+int main() {
+    return p[2] + g.x * 10 + g.y + q.a[1] + q.b.y;
+}
+" 66
+test "
+char *msg = \"hello\";
+int x = 3;
+int *px = &x;
+int arr[4] = {1, 2, 3, 4};
+int *pa = &arr[2];
+int *pb = arr + 1;
+struct S { int a; int b; } s = {5, 6};
+int *ps = &s.b;
+char *names[] = {\"ab\", \"cd\"};
+char *mp = \"xyz\" + 1;
+int main() {
+    static char *m = \"hey\";
+    static int *p = &x;
+    static int y = 9;
+    static int *q = &y;
+    return *px + *pa + *pb + *ps + names[1][1] + msg[4] + m[1] + *p + *q + mp[0];
+}
+" 203
+test_diag "int main() { static int *p = (int[]){1, 2}; return p[1]; }" "initializer of static variable \`p\` is not constant."
+test_diag "int main() { int a = 1; static int *p = &a; return *p; }" "initializer of static variable \`p\` is not constant."
+test "
+struct P { int x; int y; };
+struct Q { struct P p; int a[3]; };
+int sum(struct P *p) {
+    return p->x + p->y;
+}
+int main() {
+    struct Q q = {{1, 2}, {3, 4, 5}};
+    int *px = &q.p.y;
+    int *pa = &q.a[1];
+    struct P *pp = &q.p;
+    struct P *lit = &(struct P){10, 20};
+    int *e = &*pa;
+    return *px + *pa * 10 + pp->x * 100 + sum(&(struct P){3, 4}) + lit->y + *e;
+}
+" 173
+test_diag "int main() { int *p = &(1 + 2); return 0; }" "lvalue required as unary \`&\` operand."
+test_diag "struct B { int a : 3; }; int main() { struct B b; int *p = &b.a; return 0; }" "cannot take address of bit-field \`a\`."
+
+# float and double.
+test "
+double atof(const char *s);
+int printf(const char *fmt, ...);
+float half(float x) {
+    return x / 2;
+}
+double mix(int a, double b, long c, float d, double e, double f, double g, double h, double i, double j, double k, int l) {
+    return a + b + c + d + e + f + g + h + i + j + k + l;
+}
+double sum(int n, ...) {
+    va_list ap;
+    va_start(ap, n);
+    double s = 0;
+    for(int i = 0; i < n; i = i + 1;) {
+        s = s + va_arg(ap, double);
+    }
+    va_end(ap);
+    return s;
+}
+int main() {
+    double x = 1.5;
+    float y = 2.25f;
+    static double z = 0.5 * 3;
+    int n = x * 4;
+    double big = 1e3;
+    printf(\"%f %f %f %d %g\\n\", x, y, z, n, big);
+    if (x < y) {
+        n = n + 1;
+    }
+    if (x == 1.5) {
+        n = n + 1;
+    }
+    if (x != z) {
+        n = n + 100;
+    }
+    return n + half(9) + mix(1, 2.5, 3, 4.5f, 1, 1, 1, 1, 1, 1, 1, 2) + atof(\"2.7\") + sum(3, 1.5, 2.5, y);
+}
+" 41
+test "
+int main() {
+    unsigned long a = 0 - 1;
+    unsigned long h = a / 2 + 1;
+    double d = a;
+    float fl = a;
+    unsigned long b = h + 1025;
+    double e = b;
+    unsigned long back = e;
+    unsigned long hd = d / 2.0;
+    unsigned long hf = fl / 4.0f;
+    int ok = (d / 1000000.0 > 18446744073709.0) + (hd == h) * 2 + (hf == h / 2) * 4;
+    ok = ok + (back == h + 2048) * 8 + ((unsigned long)(double)h == h) * 16;
+    ok = ok + ((unsigned long)3.5 == 3) * 32 + ((double)(unsigned long)7 == 7.0) * 64;
+    return ok;
+}
+" 127
+
+# long double.
+test "
+int printf(const char *fmt, ...);
+long double f(int a, int b, int c, int d, int e, int g, int h, long double x, int i) {
+    return x * h + i;
+}
+long double sum(int n, ...) {
+    va_list ap;
+    va_start(ap, n);
+    long double s = 0;
+    for(int k = 0; k < n; k = k + 1;) {
+        s = s + va_arg(ap, long double);
+    }
+    va_end(ap);
+    return s;
+}
+int main() {
+    static long double g = 2.5;
+    long double a = 1.5;
+    long double b = 3;
+    long double arr[3] = {1, 2.5L, 4};
+    double d = a * b;
+    int i = b - a;
+    printf(\"%Lf %Lf\\n\", a / b + arr[2], f(1, 2, 3, 4, 5, 6, 7, 1.5, 3));
+    if (a < b) {
+        i = i + 10;
+    }
+    if (a == 1.5) {
+        i = i + 10;
+    }
+    return i + d * 2 + sizeof(long double) + (arr[1] + arr[2] == g + 4) + sum(3, 1.25L, (long double)2, a);
+}
+" 51
+
+# variable-length arrays.
+test "
+int fill(int n) {
+    int buf[n];
+    for(int i = 0; i < n; i = i + 1;) {
+        buf[i] = i * i;
+    }
+    int s = 0;
+    for(int j = 0; j < n; j = j + 1;) {
+        s = s + buf[j];
+    }
+    return s + sizeof buf;
+}
+int main() {
+    int n = 3;
+    int m = 4;
+    long first = 0;
+    long last = 0;
+    int a[n][m];
+    for(int i = 0; i < n; i = i + 1;) {
+        for(int j = 0; j < m; j = j + 1;) {
+            a[i][j] = i * 10 + j;
+        }
+    }
+    for(int k = 0; k < 3; k = k + 1;) {
+        char tmp[n * 100];
+        char *p = tmp;
+        if (k == 0) {
+            first = (long)p;
+        }
+        if (k == 2) {
+            last = (long)p;
+        }
+    }
+    return a[2][3] + a[1][0] + sizeof a + sizeof(a[0]) + fill(4) + (first == last);
+}
+" 128
+
+# _Alignas.
+test "
+int main() {
+    char c = 1;
+    _Alignas(64) int x = 5;
+    _Alignas(32) char buf[10] = {1, 2, 3};
+    static _Alignas(128) int s = 7;
+    _Alignas(long) int y = 3;
+    long px = (long)&x;
+    char *pb = buf;
+    long pbuf = (long)pb;
+    long ps = (long)&s;
+    long py = (long)&y;
+    return (px / 64 * 64 == px) + (pbuf / 32 * 32 == pbuf) + (ps / 128 * 128 == ps) + (py / 8 * 8 == py) + x + buf[2] + s + c + y;
+}
+" 23
+
+# _Static_assert, __func__ and _Generic.
+test "
+int strcmp(const char *a, const char *b);
+_Static_assert(sizeof(long) == 8, \"long must be 8 bytes\");
+_Static_assert((char)300 == 44, \"truncated by cast\");
+int helper() {
+    return sizeof(__func__) + strcmp(__func__, \"helper\");
+}
+int main() {
+    _Static_assert(2 * 3 >= 6, \"arith\");
+    int a[4];
+    _Static_assert(sizeof(a) == 16, \"array size\");
+    long l = 1;
+    char *s = \"hi\";
+    double d = 1.5;
+    int g = _Generic(l, int: 1, long: 2, default: 3) * 100 + _Generic(s, char *: 4, default: 5) * 10 + _Generic(d, float: 6, double: 7);
+    int h = _Generic(a, int *: 1, default: 0) + _Generic(1.5f, default: 2, float: 3);
+    return g + h + helper() + strcmp(__func__, \"main\");
+}
+" 2
+test "
+_Static_assert((unsigned long)(0 - 1) > 0, \"unsigned long is compared as unsigned\");
+_Static_assert((0 - 1 < (unsigned)0) == 0, \"int is converted to unsigned int\");
+_Static_assert((unsigned)1 - 2 > 0, \"unsigned subtraction wraps\");
+_Static_assert((unsigned long)(0 - 2) / 2 > 0, \"unsigned division\");
+_Static_assert((unsigned char)200 + 100 == 300, \"promoted to int\");
+_Static_assert((0 - 7) / 2 == 0 - 3, \"signed division\");
+int main() {
+    _Static_assert((unsigned)(0 - 1) / 2 == 2147483647, \"unsigned int division\");
+    return 3;
+}
+" 3
+test "
+struct S {
+    int a;
+    _Static_assert(sizeof(int) == 4, \"int in struct\");
+    char b;
+};
+_Static_assert(sizeof(struct S) == 8, \"struct size\");
+int main() {
+    struct S s;
+    s.b = 5;
+    return sizeof(s) + s.b;
+}
+" 13
+test_diag "
+int main() {
+    int a[4];
+    _Static_assert(sizeof(a) == 8, \"block scope\");
+    return 0;
+}
+" "Err place: <stdin>:4"
+test_diag "
+int main() {
+    int a[4];
+    _Static_assert(sizeof(a) == 8, \"block scope\");
+    return 0;
+}
+" "static assertion failed: \"block scope\""
+test_diag "
+struct S {
+    int a;
+    _Static_assert(sizeof(int) == 2, \"in struct\");
+};
+int main() { return 0; }
+" "Err place: <stdin>:4"
+test_diag "
+int main() {
+    long l = 1;
+    return _Generic(l, int: 1, char *: 2);
+}
+" "<stdin>:4"
+test_diag "
+int main() {
+    long l = 1;
+    return _Generic(l, int: 1, char *: 2);
+}
+" "_Generic: no association matches type LONG."
+test "
+int main() {
+    unsigned int lo;
+    unsigned int hi;
+    asm volatile(\"rdtsc\" : \"=a\"(lo), \"=d\"(hi));
+    int t = 0;
+    if (lo == 0) { if (hi == 0) { t = 100; } }
+    int x = 3;
+    int y = 4;
+    int z;
+    asm(\"mov %1, %0\n\tadd %2, %0\" : \"=r\"(z) : \"r\"(x), \"r\"(y));
+    asm __volatile__(\"addl %1, %0\" : \"+r\"(x) : \"i\"(10));
+    long m = 5;
+    asm(\"addq \$20, %0\" : \"+m\"(m));
+    int w;
+    asm(\"lea 1(%q1), %q0\" : \"=r\"(w) : \"0\"(y));
+    int b;
+    asm(\"mov \$2, %%ebx\n\tmov %%ebx, %0\" : \"=r\"(b) : : \"rbx\", \"cc\");
+    asm(\"nop\");
+    return t + z + x + m + w + b;
+}
+" 52
+test_asm_count "int main() { asm(\"mov \$1, %%ebx\" : : : \"ebx\", \"%r12d\", \"memory\", \"xmm0\"); return 0; }" "push %r\(bx\|12\)$" 2
+test_diag "int main() { asm(\"nop\" : : : \"foo\"); return 0; }" "asm: unknown register name \`foo\` in clobber."
+test "
+#include \"test/include/guard.h\"
+#include <guard.h>
+# include \"test/include/once.h\"
+#include <sys.h> /* system include path */
+#
+int main() {
+    /* 複数行の
+       注釈 */ return guard_val() + once_val() + sys_val(); // 126
+}
+" 126 -I test/include --isystem test/include/sys
+
+# headers shipped with rcc are searched before the system include path.
+test "
+#include <stdio.h>
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdalign.h>
+#include <stddef.h>
+int sum(int n, ...) {
+    va_list ap;
+    va_start(ap, n);
+    int s = 0;
+    for(int i = 0; i < n; i = i + 1;) {
+        s = s + va_arg(ap, int);
+    }
+    va_end(ap);
+    return s;
+}
+int main() {
+    char buf[16];
+    alignas(16) bool b = 2;
+    size_t len = snprintf(buf, sizeof(buf), \"%d-%d\", 12, 34);
+    bool p = NULL;
+    return len + b * 10 + p + sum(2, 3, 4) + alignof(max_align_t);
+}
+" 38
+test "
+int sum(int a[3], int n) {
+    int s = 0;
+    for(int i = 0; i < n; i = i + 1;) {
+        s = s + a[i];
+    }
+    return s;
+}
+char buf[2 * sizeof(int) + 1];
+int main() {
+    int a[3] = {1, 2, 3};
+    return sum(a, 3) + sizeof(buf);
+}
+" 15
+
+# _Bool converts to 0 or 1.
+test "
+_Bool g = 256;
+_Bool h = 0.5;
+_Bool is_set(int *p) {
+    return p;
+}
+int main() {
+    _Bool a = 2;
+    _Bool c = 0.25;
+    long double ld = 0.5;
+    _Bool d = ld;
+    _Bool e;
+    e = 512;
+    int x = 0;
+    return a + c * 2 + d * 4 + e * 8 + g * 16 + h * 32 + is_set(&x) * 64 + (_Bool)3 + sizeof(_Bool);
+}
+" 129
+test "
+#define N 10
+#define ADD(a, b) ((a) + (b))
+#define TWICE(x) ADD(x, x)
+#define STR(x) #x
+#define XSTR(x) STR(x)
+#define CAT(a, b) a ## b
+#define SUM(first, ...) sum(first, ## __VA_ARGS__)
+#define ONE(x, ...) one(x, ## __VA_ARGS__)
+#define f(x) (x + f)
+#define g f
+#define OLD 1
+#undef OLD
+int strcmp(char *a, char *b);
+int sum(int a, int b, int c) { return a + b + c; }
+int one(int x) { return x; }
+int main() {
+    int f = 5;
+    int OLD = 0;
+    int DROP = 0;
+    int CAT(fo, o) = TWICE(N);
+    int s = strcmp(STR(a  +  b), \"a + b\") + strcmp(XSTR(N), \"10\");
+    return foo + s + g(g(1)) + SUM(1, 2,
+        3) + ONE(2) + CAT(, 4) + DEF + FN(3) + OLD + DROP;
+}
+" 56 -D DEF=7 -D "FN(x)=x*2" -D DROP=100 -U DROP
+test "
+int main() {
+#ifdef NONE
+    return 1;
+#endif
+    return FOO + BAR;
+}
+" 8 -UFOO -DFOO=3 -DBAR=5 -UNONE -DNONE -UNONE
+test "
+#warning don't
+#define X 5
+#if 0
+it's skipped
+#endif
+int main() { return X; }
+" 5
+test_diag "
+int x;
+#line 20 \"file.c\"
+#warning don't
+" "file.c:20: warning: #warning don't"
+test "
+#include \"guard.h\"
+#include \"guard.h\"
+int strcmp(char *a, char *b);
+#define A 3
+#if A == 3 && defined(A) && !defined B
+int c1() { return 1; }
+#elif 1 / 0
+int c1() { return 2; }
+#else
+int c1() { return 3; }
+#endif
+#ifdef B
+#error not reached
+#elif 0
+#else
+# if (1 ? 2 : 1 / 0) == 2 && 0x10 + 010 + 0b1 == 25 && 'a' == 97 && '\\n' == 10
+#  if -1 < 0 && (1 << 4 | 3) == 19 && 10 % 4 * 3 == 6 && (0 && 1 / 0) == 0
+int c2() { return 4; }
+#  endif
+# else
+int c2() { return 5; }
+# endif
+#endif
+#ifndef A
+#bogus directive in skipped group
+#endif
+#if __has_include(\"once.h\") && __has_include(<sys.h>) && !__has_include(\"none.h\")
+int c3() { return 6; }
+#endif
+#if __STDC__ && __x86_64__ && __rcc__ && UNDEFINED_NAME == 0 && __STDC_VERSION__ >= 201112
+int c4() { return 7; }
+#endif
+#line 100 \"foo.c\"
+int c5() { return __LINE__ + strcmp(__FILE__, \"foo.c\"); }
+int main() { return c1() + c2() + c3() + c4() + c5() + guard_val(); }
+" 141 -I test/include --isystem test/include/sys
+# #if uses intmax_t and uintmax_t arithmetic.
+test "
+int main() {
+    int ok = 0;
+#if -1 > 0u
+    ok = ok + 1;
+#endif
+#if 18446744073709551615 == -1 && 0xFFFFFFFFFFFFFFFF > 0
+    ok = ok + 2;
+#endif
+#if -1 > 0
+    ok = ok + 100;
+#endif
+#if (0u - 1) / 2 == 0x7FFFFFFFFFFFFFFF && (0u - 1) >> 63 == 1 && -1 >> 63 == -1
+    ok = ok + 4;
+#endif
+#if 010 == 8 && 0x10 == 16 && 10UL == 10
+    ok = ok + 8;
+#endif
+#if (1 ? -1 : 0u) > 0 && !0u - 2 < 0
+    ok = ok + 16;
+#endif
+    return ok;
+}
+" 31
@@ -0,0 +1,6 @@
+// include guardで囲まれたheader.
+#ifndef GUARD_H
+#define GUARD_H
+#include "once.h"
+int guard_val() { return once_val() + 20; }
+#endif
@@ -0,0 +1 @@
+int sys_val() { return 100; }